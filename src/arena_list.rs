@@ -0,0 +1,321 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt::{self, Write};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::custom_types::List;
+
+// An arena backed linked list
+// Every Cons in the boxed List is its own heap allocation, so building and
+// throwing away millions of lists means millions of mallocs and frees
+// Here every node lives in one Vec owned by a ListArena and the links are
+// just indexes into it, so allocating a node is a push and throwing away
+// every list at once is a single clear()
+
+// A list is a handle into an arena, in the same shape as List:
+// either where in the arena its first node is, or Nil
+// Handles are Copy, and prepending never changes an existing node,
+// so lists in the same arena can share tails for free
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArenaList {
+    // Cons: Where the first node is in the arena
+    Cons(NodeRef),
+    // Nil: The empty list
+    Nil,
+}
+
+// Which node, which arena it's in and which batch it was made in
+// clear() starts a new generation, so a handle kept from before it is
+// caught the moment it's used instead of reading whatever node happens to
+// be at that index now
+// Handing one arena's list to another is caught the same way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeRef {
+    index: usize,
+    arena: u64,
+    generation: u64,
+}
+
+// Every arena gets its own id from here
+static NEXT_ARENA: AtomicU64 = AtomicU64::new(0);
+
+struct Node<T> {
+    elem: T,
+    next: ArenaList,
+}
+
+pub struct ListArena<T> {
+    nodes: Vec<Node<T>>,
+    id: u64,
+    // Bumped by every clear()
+    generation: u64,
+}
+
+impl<T> ListArena<T> {
+    pub fn new() -> ListArena<T> {
+        ListArena::with_capacity(0)
+    }
+
+    // Reserve room for capacity nodes up front so building lists never reallocates
+    pub fn with_capacity(capacity: usize) -> ListArena<T> {
+        ListArena {
+            nodes: Vec::with_capacity(capacity),
+            id: NEXT_ARENA.fetch_add(1, Ordering::Relaxed),
+            generation: 0,
+        }
+    }
+
+    // Return a list with elem in front of list
+    // Unlike List::prepend the old list isn't consumed, it's still a valid handle
+    pub fn prepend(&mut self, list: ArenaList, elem: T) -> ArenaList {
+        // Checked here too, or a stale tail would sneak into the new batch
+        if let ArenaList::Cons(next) = list {
+            self.node(next);
+        }
+        self.nodes.push(Node { elem, next: list });
+        ArenaList::Cons(NodeRef {
+            index: self.nodes.len() - 1,
+            arena: self.id,
+            generation: self.generation,
+        })
+    }
+
+    // Look at the first element of list
+    pub fn head(&self, list: ArenaList) -> Option<&T> {
+        match list {
+            ArenaList::Cons(first) => Some(&self.node(first).elem),
+            ArenaList::Nil => None,
+        }
+    }
+
+    // Everything after the first element of list
+    pub fn tail(&self, list: ArenaList) -> Option<ArenaList> {
+        match list {
+            ArenaList::Cons(first) => Some(self.node(first).next),
+            ArenaList::Nil => None,
+        }
+    }
+
+    // Return the length of the list
+    pub fn len(&self, list: ArenaList) -> usize {
+        self.iter(list).count()
+    }
+
+    pub fn iter(&self, list: ArenaList) -> Iter<'_, T> {
+        Iter { arena: self, next: list }
+    }
+
+    // Return representation of the list as a (heap allocated) string
+    // Same "3, 2, 1, Nil" form as List::stringify
+    pub fn stringify(&self, list: ArenaList) -> String
+    where
+        T: fmt::Display,
+    {
+        let mut out = String::new();
+        for elem in self.iter(list) {
+            // Writing to a String can't fail
+            write!(out, "{}, ", elem).unwrap();
+        }
+        out.push_str("Nil");
+        out
+    }
+
+    // How many nodes every list in the arena uses all together
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Throw away every list in the arena at once but keep the memory around
+    // for the next batch
+    // Any ArenaList handed out before this is no longer valid, and using one
+    // with this arena panics
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.generation += 1;
+    }
+
+    // Every way into the nodes comes through here, so a stale handle never
+    // gets as far as indexing
+    fn node(&self, at: NodeRef) -> &Node<T> {
+        assert_eq!(at.arena, self.id, "ArenaList used with an arena it didn't come from");
+        assert_eq!(
+            at.generation, self.generation,
+            "ArenaList used after the arena it came from was cleared"
+        );
+        &self.nodes[at.index]
+    }
+}
+
+impl<T> Default for ListArena<T> {
+    fn default() -> Self {
+        ListArena::new()
+    }
+}
+
+// Borrowing iterator over one list in an arena, front to back
+pub struct Iter<'a, T> {
+    arena: &'a ListArena<T>,
+    next: ArenaList,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next {
+            ArenaList::Cons(at) => {
+                let node = self.arena.node(at);
+                self.next = node.next;
+                Some(&node.elem)
+            },
+            ArenaList::Nil => None,
+        }
+    }
+}
+
+// Build and drop `lists` lists of `len` elements each, with boxed Lists
+fn bench_boxed(lists: usize, len: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..lists {
+        let mut list = List::new();
+        for n in 0..len {
+            list = list.prepend(n);
+        }
+        black_box(list.len());
+        // list gets dropped here, freeing every node one by one
+    }
+    start.elapsed()
+}
+
+// Same thing again, but with every list living in one reused arena
+fn bench_arena(lists: usize, len: u32) -> Duration {
+    let start = Instant::now();
+    let mut arena = ListArena::with_capacity(len as usize);
+    for _ in 0..lists {
+        let mut list = ArenaList::Nil;
+        for n in 0..len {
+            list = arena.prepend(list, n);
+        }
+        black_box(arena.len(list));
+        arena.clear();
+    }
+    start.elapsed()
+}
+
+pub fn run() {
+    // Same example as the boxed list
+    let mut arena = ListArena::new();
+    let mut list = ArenaList::Nil;
+    list = arena.prepend(list, 1);
+    list = arena.prepend(list, 2);
+    list = arena.prepend(list, 3);
+
+    println!("Linked list has length: {}", arena.len(list));
+    println!("{}", arena.stringify(list));
+
+    // It behaves just like the boxed one
+    let boxed = List::new().prepend(1).prepend(2).prepend(3);
+    assert_eq!(arena.len(list), boxed.len());
+    assert_eq!(arena.stringify(list), boxed.stringify());
+    assert_eq!(arena.stringify(ArenaList::Nil), List::<u32>::new().stringify());
+
+    // The old handle is still around after prepending, and shares its nodes
+    let longer = arena.prepend(list, 4);
+    assert_eq!(arena.stringify(longer), "4, 3, 2, 1, Nil");
+    assert_eq!(arena.tail(longer), Some(list));
+    assert_eq!(arena.node_count(), 4);
+
+    // Benchmark: build and throw away a lot of lists both ways
+    // A debug build mostly measures the missing optimizations, so try this
+    // one with cargo run --release too
+    let lists = 1_000;
+    let len = 1_000;
+    let boxed_time = bench_boxed(lists, len);
+    let arena_time = bench_arena(lists, len);
+    println!("{} lists of {} elements:", lists, len);
+    println!("    boxed List: {:.2?}", boxed_time);
+    println!("    ListArena:  {:.2?}", arena_time);
+    println!(
+        "    arena is {:.1}x the speed of boxed",
+        boxed_time.as_secs_f64() / arena_time.as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_arenas_are_reused() {
+        let mut arena = ListArena::new();
+        let list = arena.prepend(ArenaList::Nil, 1);
+        arena.prepend(list, 2);
+        arena.clear();
+        assert_eq!(arena.node_count(), 0);
+
+        let list = arena.prepend(ArenaList::Nil, 3);
+        assert_eq!(arena.stringify(list), "3, Nil");
+        assert_eq!(arena.tail(list), Some(ArenaList::Nil));
+    }
+
+    // Same index as a node in the new batch, so without the generation this
+    // would quietly read 30
+    #[test]
+    #[should_panic(expected = "cleared")]
+    fn stale_handles_dont_alias_the_next_batch() {
+        let mut arena = ListArena::new();
+        let stale = arena.prepend(ArenaList::Nil, 10);
+        arena.clear();
+        arena.prepend(ArenaList::Nil, 30);
+        arena.head(stale);
+    }
+
+    #[test]
+    #[should_panic(expected = "cleared")]
+    fn stale_handles_cant_be_iterated() {
+        let mut arena = ListArena::new();
+        let stale = arena.prepend(ArenaList::Nil, 10);
+        arena.clear();
+        arena.len(stale);
+    }
+
+    #[test]
+    #[should_panic(expected = "cleared")]
+    fn stale_tails_cant_be_prepended_to() {
+        let mut arena = ListArena::new();
+        let stale = arena.prepend(ArenaList::Nil, 10);
+        arena.clear();
+        arena.prepend(stale, 20);
+    }
+
+    // Both arenas have a node at index 0, so without the arena id this
+    // would quietly read 2
+    #[test]
+    #[should_panic(expected = "didn't come from")]
+    fn handles_dont_work_in_other_arenas() {
+        let mut first = ListArena::new();
+        let mut second = ListArena::new();
+        let list = first.prepend(ArenaList::Nil, 1);
+        second.prepend(ArenaList::Nil, 2);
+        second.head(list);
+    }
+
+    #[test]
+    #[should_panic(expected = "didn't come from")]
+    fn other_arenas_lists_cant_be_prepended_to() {
+        let mut first = ListArena::new();
+        let mut second = ListArena::new();
+        let list = first.prepend(ArenaList::Nil, 1);
+        second.prepend(list, 2);
+    }
+
+    #[test]
+    fn stringify_matches_the_boxed_list() {
+        let mut arena = ListArena::new();
+        let list = [1, 2, 3].into_iter().fold(ArenaList::Nil, |list, n| arena.prepend(list, n));
+        assert_eq!(arena.stringify(list), "3, 2, 1, Nil");
+        assert_eq!(arena.stringify(ArenaList::Nil), "Nil");
+    }
+}
@@ -0,0 +1,533 @@
+#![allow(dead_code)] // Allow unused code
+
+use crate::geometry::{Point, Rectangle};
+
+// Algorithms on plain lists of Points: polygon area, point in polygon,
+// segment intersection, convex hulls and simplification
+// A polygon here is just a slice of vertices where the last one connects
+// back to the first, going either way round
+// Nothing here uses a tolerance unless it asks for one, so "collinear"
+// means the cross product is exactly zero
+
+// A straight line between two points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+// What two segments have in common
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentIntersection {
+    None,
+    // They cross or touch at one point
+    Point(Point),
+    // They lie on the same line and share a stretch of it
+    Overlap(Segment),
+}
+
+// Where a point is relative to a polygon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    Inside,
+    Outside,
+    OnBoundary,
+}
+
+impl Segment {
+    pub fn new(start: Point, end: Point) -> Segment {
+        Segment { start, end }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.start.distance(self.end)
+    }
+
+    // True if point lies on the segment, ends included
+    pub fn contains(&self, point: Point) -> bool {
+        (self.end - self.start).cross(point - self.start) == 0.0
+            && Rectangle::new(self.start, self.end).contains(point)
+    }
+
+    pub fn intersection(&self, other: &Segment) -> SegmentIntersection {
+        // Write both segments as start + direction * t for t in 0..=1
+        let p = self.start;
+        let r = self.end - self.start;
+        let q = other.start;
+        let s = other.end - other.start;
+        let denominator = r.cross(s);
+        let start_offset = q - p;
+
+        if denominator != 0.0 {
+            // Not parallel, so the lines cross exactly once
+            // Check the crossing is within both segments
+            let t = start_offset.cross(s) / denominator;
+            let u = start_offset.cross(r) / denominator;
+            return if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+                SegmentIntersection::Point(p + r * t)
+            } else {
+                SegmentIntersection::None
+            };
+        }
+
+        if start_offset.cross(r) != 0.0 || start_offset.cross(s) != 0.0 {
+            // Parallel but on different lines
+            return SegmentIntersection::None;
+        }
+
+        // Collinear, or one of them is only a point
+        if r == Point::ORIGIN {
+            return if other.contains(p) {
+                SegmentIntersection::Point(p)
+            } else {
+                SegmentIntersection::None
+            };
+        }
+
+        // Measure where other starts and ends along self, in units of t,
+        // and clip that range to self's own 0..=1
+        let t0 = start_offset.dot(r) / r.dot(r);
+        let t1 = t0 + s.dot(r) / r.dot(r);
+        let low = t0.min(t1).max(0.0);
+        let high = t0.max(t1).min(1.0);
+
+        if low > high {
+            SegmentIntersection::None
+        } else if low == high {
+            SegmentIntersection::Point(p + r * low)
+        } else {
+            SegmentIntersection::Overlap(Segment::new(p + r * low, p + r * high))
+        }
+    }
+
+    pub fn intersects(&self, other: &Segment) -> bool {
+        self.intersection(other) != SegmentIntersection::None
+    }
+}
+
+// Every edge of a polygon, including the one that closes the loop
+pub fn edges(polygon: &[Point]) -> impl Iterator<Item = Segment> + '_ {
+    let next = polygon.iter().cycle().skip(1);
+    polygon
+        .iter()
+        .zip(next)
+        .map(|(&start, &end)| Segment::new(start, end))
+}
+
+// Shoelace formula: add up the signed areas of the triangles each edge
+// makes with the origin, the bits outside the polygon cancel out
+// Positive if every turn has a positive cross product (counterclockwise
+// with y up, clockwise on screen), negative the other way round
+pub fn signed_area(polygon: &[Point]) -> f32 {
+    edges(polygon)
+        .map(|edge| edge.start.cross(edge.end))
+        .sum::<f32>()
+        / 2.0
+}
+
+pub fn polygon_area(polygon: &[Point]) -> f32 {
+    signed_area(polygon).abs()
+}
+
+// Cast a ray to the right of the point and count how many edges it crosses,
+// an odd count means we started inside
+pub fn point_in_polygon(point: Point, polygon: &[Point]) -> Containment {
+    let mut inside = false;
+    for edge in edges(polygon) {
+        if edge.contains(point) {
+            return Containment::OnBoundary;
+        }
+
+        let (a, b) = (edge.start, edge.end);
+        // Only edges that straddle the ray's height can cross it
+        // (one end strictly above, the other on or below, so a ray through
+        // a vertex isn't counted twice)
+        if (a.y > point.y) != (b.y > point.y) {
+            let crossing_x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    if inside {
+        Containment::Inside
+    } else {
+        Containment::Outside
+    }
+}
+
+// Sort points by x, then y
+fn sorted_unique(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    sorted
+}
+
+// Convex hull by Andrew's monotone chain
+// Walk the sorted points left to right for one half of the hull and right to
+// left for the other, throwing away any point that doesn't make a positive
+// turn, which also drops duplicates and points in the middle of an edge
+// The hull comes back in the same winding as signed_area's positive case,
+// starting at the leftmost point
+// All-collinear input gives just the two end points, a single repeated point
+// gives that point
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let sorted = sorted_unique(points);
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let turns_positive = |hull: &Vec<Point>, next: Point| {
+        let [.., a, b] = hull.as_slice() else {
+            return true;
+        };
+        (*b - *a).cross(next - *b) > 0.0
+    };
+
+    let mut lower: Vec<Point> = Vec::new();
+    for &point in &sorted {
+        while lower.len() >= 2 && !turns_positive(&lower, point) {
+            lower.pop();
+        }
+        lower.push(point);
+    }
+
+    let mut upper: Vec<Point> = Vec::new();
+    for &point in sorted.iter().rev() {
+        while upper.len() >= 2 && !turns_positive(&upper, point) {
+            upper.pop();
+        }
+        upper.push(point);
+    }
+
+    // Each half ends where the other starts
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+// Distance from point to the closest spot on segment
+pub fn distance_to_segment(point: Point, segment: &Segment) -> f32 {
+    let direction = segment.end - segment.start;
+    let length_squared = direction.length_squared();
+    if length_squared == 0.0 {
+        return point.distance(segment.start);
+    }
+    let t = ((point - segment.start).dot(direction) / length_squared).clamp(0.0, 1.0);
+    point.distance(segment.start + direction * t)
+}
+
+// Douglas-Peucker: keep the vertex furthest from the line between two kept
+// vertices if it's more than tolerance away, and split there, until every
+// dropped vertex is within tolerance of the edge that replaced it
+// A polygon is a loop, so it gets cut in two first, at the first vertex
+// and the one furthest from it, which are kept no matter what
+// Distances are always to the original vertices, so no point of the
+// original outline ends up more than tolerance from the simplified one,
+// and the other way round too
+// Repeated vertices always go, and so do vertices in the middle of a
+// straight edge, so a tolerance of 0.0 only removes those
+// The result keeps at least three vertices when the input has them, even
+// if that means keeping one the tolerance would let go
+pub fn simplify_polygon(polygon: &[Point], tolerance: f32) -> Vec<Point> {
+    let mut vertices = polygon.to_vec();
+    vertices.dedup();
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    if vertices.len() <= 3 {
+        return vertices;
+    }
+
+    let count = vertices.len();
+    let start = vertices[0];
+    let split = (1..count)
+        .max_by(|&a, &b| start.distance(vertices[a]).total_cmp(&start.distance(vertices[b])))
+        .unwrap();
+
+    // The vertex between first and last (going round the loop) that's
+    // furthest from the edge joining them
+    let furthest = |first: usize, last: usize| {
+        let edge = Segment::new(vertices[first % count], vertices[last % count]);
+        (first + 1..last)
+            .map(|index| (index, distance_to_segment(vertices[index % count], &edge)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    };
+
+    let mut keep = vec![false; count];
+    keep[0] = true;
+    keep[split] = true;
+    // Spans of the loop still to check, as (first, last) with last going
+    // past count to wrap back round to 0
+    let mut spans = vec![(0, split), (split, count)];
+    while let Some((first, last)) = spans.pop() {
+        if let Some((index, distance)) = furthest(first, last) {
+            if distance > tolerance {
+                keep[index % count] = true;
+                spans.push((first, index));
+                spans.push((index, last));
+            }
+        }
+    }
+
+    // Only the two ends survived, so bring back whichever vertex sticks
+    // out furthest from the line between them
+    if keep.iter().filter(|&&kept| kept).count() < 3 {
+        let sides = [furthest(0, split), furthest(split, count)];
+        if let Some((index, _)) = sides.into_iter().flatten().max_by(|a, b| a.1.total_cmp(&b.1)) {
+            keep[index % count] = true;
+        }
+    }
+
+    vertices.into_iter().zip(keep).filter(|&(_, kept)| kept).map(|(vertex, _)| vertex).collect()
+}
+
+pub fn run() {
+    let point = |x, y| Point::new(x, y);
+
+    let square = [point(0.0, 0.0), point(4.0, 0.0), point(4.0, 4.0), point(0.0, 4.0)];
+    println!("Square {:?} has area {}", square, polygon_area(&square));
+    println!("(2, 2) is {:?}", point_in_polygon(point(2.0, 2.0), &square));
+    println!("(4, 2) is {:?}", point_in_polygon(point(4.0, 2.0), &square));
+
+    let horizontal = Segment::new(point(0.0, 0.0), point(4.0, 0.0));
+    let vertical = Segment::new(point(2.0, -1.0), point(2.0, 1.0));
+    let overlapping = Segment::new(point(6.0, 0.0), point(2.0, 0.0));
+    println!("Crossing: {:?}", horizontal.intersection(&vertical));
+    println!("Collinear: {:?}", horizontal.intersection(&overlapping));
+
+    let scattered = [
+        point(0.0, 0.0),
+        point(2.0, 1.0),
+        point(4.0, 0.0),
+        point(4.0, 4.0),
+        point(1.0, 2.0),
+        point(0.0, 4.0),
+        point(4.0, 4.0),
+    ];
+    println!("Hull: {:?}", convex_hull(&scattered));
+
+    let noisy = [
+        point(0.0, 0.0),
+        point(2.0, 0.0),
+        point(4.0, 0.0),
+        point(4.1, 3.0),
+        point(4.0, 4.0),
+        point(0.0, 4.0),
+    ];
+    println!("Simplified: {:?}", simplify_polygon(&noisy, 0.2));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn square() -> Vec<Point> {
+        vec![point(0.0, 0.0), point(4.0, 0.0), point(4.0, 4.0), point(0.0, 4.0)]
+    }
+
+    // Shoelace area, either winding
+    #[test]
+    fn area_either_winding() {
+        let square = square();
+        assert_eq!(signed_area(&square), 16.0);
+        let backwards: Vec<Point> = square.iter().rev().copied().collect();
+        assert_eq!(signed_area(&backwards), -16.0);
+        assert_eq!(polygon_area(&backwards), 16.0);
+    }
+
+    #[test]
+    fn degenerate_polygons_have_no_area() {
+        assert_eq!(polygon_area(&[]), 0.0);
+        assert_eq!(polygon_area(&[point(1.0, 1.0)]), 0.0);
+        assert_eq!(polygon_area(&[point(1.0, 1.0), point(1.0, 1.0), point(1.0, 1.0)]), 0.0);
+        assert_eq!(polygon_area(&[point(0.0, 0.0), point(1.0, 1.0), point(2.0, 2.0)]), 0.0);
+    }
+
+    #[test]
+    fn point_in_polygon_edges_and_vertices() {
+        let square = square();
+        assert_eq!(point_in_polygon(point(2.0, 2.0), &square), Containment::Inside);
+        assert_eq!(point_in_polygon(point(5.0, 2.0), &square), Containment::Outside);
+        assert_eq!(point_in_polygon(point(4.0, 2.0), &square), Containment::OnBoundary);
+        assert_eq!(point_in_polygon(point(0.0, 0.0), &square), Containment::OnBoundary);
+    }
+
+    // A ray straight through a vertex isn't counted twice
+    #[test]
+    fn point_in_polygon_ray_through_vertex() {
+        let diamond = [point(2.0, 0.0), point(4.0, 2.0), point(2.0, 4.0), point(0.0, 2.0)];
+        assert_eq!(point_in_polygon(point(1.0, 2.0), &diamond), Containment::Inside);
+        assert_eq!(point_in_polygon(point(-1.0, 2.0), &diamond), Containment::Outside);
+        assert_eq!(point_in_polygon(point(-1.0, 0.0), &diamond), Containment::Outside);
+    }
+
+    #[test]
+    fn segments_crossing_touching_and_parallel() {
+        let horizontal = Segment::new(point(0.0, 0.0), point(4.0, 0.0));
+        let vertical = Segment::new(point(2.0, -1.0), point(2.0, 1.0));
+        assert_eq!(horizontal.intersection(&vertical), SegmentIntersection::Point(point(2.0, 0.0)));
+        // Touching at an end point
+        let touching = Segment::new(point(4.0, 0.0), point(5.0, 3.0));
+        assert_eq!(horizontal.intersection(&touching), SegmentIntersection::Point(point(4.0, 0.0)));
+        // Parallel lines never meet
+        let parallel = Segment::new(point(0.0, 1.0), point(4.0, 1.0));
+        assert_eq!(horizontal.intersection(&parallel), SegmentIntersection::None);
+        // Crossing lines but the segments stop short
+        let short = Segment::new(point(5.0, -1.0), point(5.0, 1.0));
+        assert!(!horizontal.intersects(&short));
+    }
+
+    // Overlapping, touching end to end, apart, and one inside the other
+    #[test]
+    fn collinear_segments() {
+        let horizontal = Segment::new(point(0.0, 0.0), point(4.0, 0.0));
+        let overlapping = Segment::new(point(6.0, 0.0), point(2.0, 0.0));
+        assert_eq!(
+            horizontal.intersection(&overlapping),
+            SegmentIntersection::Overlap(Segment::new(point(2.0, 0.0), point(4.0, 0.0)))
+        );
+        let end_to_end = Segment::new(point(4.0, 0.0), point(8.0, 0.0));
+        assert_eq!(horizontal.intersection(&end_to_end), SegmentIntersection::Point(point(4.0, 0.0)));
+        let apart = Segment::new(point(5.0, 0.0), point(8.0, 0.0));
+        assert_eq!(horizontal.intersection(&apart), SegmentIntersection::None);
+        let inside = Segment::new(point(1.0, 0.0), point(3.0, 0.0));
+        assert_eq!(horizontal.intersection(&inside), SegmentIntersection::Overlap(inside));
+    }
+
+    // Segments that are really just points
+    #[test]
+    fn zero_length_segments() {
+        let horizontal = Segment::new(point(0.0, 0.0), point(4.0, 0.0));
+        let vertical = Segment::new(point(2.0, -1.0), point(2.0, 1.0));
+        let dot = Segment::new(point(1.0, 0.0), point(1.0, 0.0));
+        assert_eq!(dot.intersection(&horizontal), SegmentIntersection::Point(point(1.0, 0.0)));
+        assert_eq!(horizontal.intersection(&dot), SegmentIntersection::Point(point(1.0, 0.0)));
+        assert_eq!(dot.intersection(&vertical), SegmentIntersection::None);
+        assert_eq!(dot.intersection(&dot), SegmentIntersection::Point(point(1.0, 0.0)));
+    }
+
+    // Duplicates and points on the hull's edges get dropped
+    #[test]
+    fn hull_drops_duplicate_and_collinear_points() {
+        let scattered = [
+            point(0.0, 0.0),
+            point(2.0, 1.0),
+            point(4.0, 0.0),
+            point(4.0, 4.0),
+            point(1.0, 2.0),
+            point(0.0, 4.0),
+            point(4.0, 4.0),
+            point(2.0, 0.0),
+            point(0.0, 2.0),
+        ];
+        let hull = convex_hull(&scattered);
+        assert_eq!(hull, square());
+        assert_eq!(signed_area(&hull), 16.0);
+    }
+
+    #[test]
+    fn hull_of_degenerate_input() {
+        assert_eq!(convex_hull(&[]), vec![]);
+        assert_eq!(convex_hull(&[point(1.0, 1.0); 5]), vec![point(1.0, 1.0)]);
+        let line = [point(3.0, 3.0), point(1.0, 1.0), point(2.0, 2.0), point(0.0, 0.0)];
+        assert_eq!(convex_hull(&line), vec![point(0.0, 0.0), point(3.0, 3.0)]);
+    }
+
+    #[test]
+    fn simplify_drops_duplicates_and_near_straight_points() {
+        let noisy = [
+            point(0.0, 0.0),
+            point(0.0, 0.0),
+            point(2.0, 0.0),
+            point(4.0, 0.0),
+            point(4.0, 2.0),
+            point(4.1, 3.0),
+            point(4.0, 4.0),
+            point(0.0, 4.0),
+            point(0.0, 0.0),
+        ];
+        // With no tolerance only the duplicates and the straight-edge midpoint go
+        assert_eq!(simplify_polygon(&noisy, 0.0).len(), 6);
+        assert_eq!(simplify_polygon(&noisy, 0.2), square());
+        // It never goes below a triangle
+        assert_eq!(simplify_polygon(&square(), 100.0).len(), 3);
+    }
+
+    // How far point is from the outline of polygon
+    fn distance_to_outline(point: Point, polygon: &[Point]) -> f32 {
+        edges(polygon).map(|edge| distance_to_segment(point, &edge)).fold(f32::INFINITY, f32::min)
+    }
+
+    // The furthest any point on the outline of a is from the outline of b,
+    // checking every vertex and a few points along every edge
+    fn furthest_from(a: &[Point], b: &[Point]) -> f32 {
+        edges(a)
+            .flat_map(|edge| (0..4).map(move |step| edge.start + (edge.end - edge.start) * (step as f32 / 4.0)))
+            .map(|point| distance_to_outline(point, b))
+            .fold(0.0, f32::max)
+    }
+
+    // A wobbly circle with a lot of vertices, some of them spiky
+    fn wobbly_circle(seed: u64, count: usize) -> Vec<Point> {
+        let mut random = crate::random::XorShift::new(seed);
+        (0..count)
+            .map(|index| {
+                let angle = index as f32 / count as f32 * std::f32::consts::TAU;
+                let radius = 100.0 + random.unit() * 4.0 + if random.below(50) == 0 { 15.0 } else { 0.0 };
+                point(angle.cos() * radius, angle.sin() * radius)
+            })
+            .collect()
+    }
+
+    // Removing vertices one at a time against their current neighbours let
+    // the error pile up well past the tolerance, this keeps it to the
+    // tolerance measured against the original
+    #[test]
+    fn simplified_outline_stays_within_tolerance() {
+        for (seed, tolerance) in [(1, 0.5), (2, 2.0), (3, 5.0), (4, 20.0)] {
+            let polygon = wobbly_circle(seed, 600);
+            let simplified = simplify_polygon(&polygon, tolerance);
+            assert!(simplified.len() < polygon.len());
+            assert!(simplified.iter().all(|vertex| polygon.contains(vertex)));
+            // A little slack for f32 rounding in the distances
+            assert!(furthest_from(&polygon, &simplified) <= tolerance + 1e-3, "tolerance {}", tolerance);
+            assert!(furthest_from(&simplified, &polygon) <= tolerance + 1e-3, "tolerance {}", tolerance);
+        }
+    }
+
+    // A slow drift that never moves any one vertex far from its neighbours
+    #[test]
+    fn small_steps_dont_add_up() {
+        let mut polygon: Vec<Point> = (0..=100).map(|x| point(x as f32, (x as f32 / 100.0).powi(2) * 10.0)).collect();
+        polygon.push(point(100.0, 50.0));
+        polygon.push(point(0.0, 50.0));
+        let simplified = simplify_polygon(&polygon, 1.0);
+        assert!(simplified.len() < 20);
+        assert!(furthest_from(&polygon, &simplified) <= 1.0 + 1e-4);
+    }
+
+    #[test]
+    fn simplified_vertices_keep_their_order() {
+        let polygon = wobbly_circle(5, 500);
+        let simplified = simplify_polygon(&polygon, 3.0);
+        let positions: Vec<usize> = simplified.iter().map(|v| polygon.iter().position(|p| p == v).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(positions[0], 0);
+    }
+
+    #[test]
+    fn tiny_polygons_come_back_as_they_are() {
+        assert_eq!(simplify_polygon(&[], 1.0), vec![]);
+        assert_eq!(simplify_polygon(&[point(1.0, 1.0), point(1.0, 1.0)], 1.0), vec![point(1.0, 1.0)]);
+        let triangle = [point(0.0, 0.0), point(1.0, 0.0), point(0.0, 1.0)];
+        assert_eq!(simplify_polygon(&triangle, 100.0), triangle);
+    }
+}
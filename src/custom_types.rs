@@ -1,6 +1,13 @@
 #![allow(dead_code)] // Allow unused code
 
-use crate::List::*;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::num::IntErrorKind;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use self::List::*;
 
 // Custom types are poggies!!
 // Rust custom types are formed mainly through two keywords
@@ -21,19 +28,12 @@ struct Unit;
 // A tuple struct
 struct Pair(i32, f32);
 
-// A struct with two fields
-struct Point {
-    x: f32,
-    y: f32,
-}
-
-// Struct can be reused as fields of another struct
-struct Rectangle {
-    // A rectangle can be specified by where
-    // the top left and bottom right are
-    top_left: Point,
-    bottom_right: Point,
-}
+// A struct with two fields, and a struct that reuses it as its fields
+// (a rectangle can be specified by where the top left and bottom right are)
+// Point and Rectangle used to be defined right here, they moved to
+// geometry.rs once they needed real methods
+use crate::events::EventBus;
+use crate::geometry::{Point, Rectangle};
 
 #[derive(Debug)] // Allow us to get debug stuff with prints using this struct
 struct Person {
@@ -42,32 +42,26 @@ struct Person {
 }
 
 fn rect_area(rectangle: Rectangle) -> f32 {
-    let Rectangle {
-        top_left: Point { x: x1, y: y1 },
-        bottom_right: Point { x: x2, y: y2 },
-    } = rectangle;
-
-    let width: f32 = x2 - x1;
-    let height: f32 = y2 - y1;
-
-    width * height // This method of returning I believe is called a "tail"
+    // This used to destructure the corners and do (x2 - x1) * (y2 - y1),
+    // which went negative whenever the corners were the wrong way round
+    // Rectangle keeps its corners in order now so area() never does
+    rectangle.area() // This method of returning I believe is called a "tail"
     // I kind of despise it. I can also just do "return" but yeahhh...
 }
 
 fn square(top_left: Point, width: f32, height: f32) -> Rectangle {
-    let Point { x, y } = top_left;
-
-    Rectangle {
-        top_left,
-        bottom_right: Point { x: x + width, y: y + height },
-    }
+    // y grows downward (see geometry.rs) so the bottom right corner
+    // is at +width, +height from the top left
+    // Point adds like a vector so there's no need to pull x and y out by hand
+    Rectangle::new(top_left, top_left + Point::new(width, height))
 }
 
 // Create an enum to classify a web event
 // Note how both names and type information together specify the variant:
 // "PageLoad != PageUnload" and "KeyPress(char) != Paste(String)"
 // Each is different and independant
-enum WebEvent {
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebEvent {
     // An enum variant may either be unit-like,
     PageLoad,
     PageUnload,
@@ -79,7 +73,9 @@ enum WebEvent {
 }
 
 // A function which takes a WebEvent enum as an argumetn and returns nothing
-fn inspect(event: WebEvent) {
+// It borrows the event so it can be registered as a handler on an EventBus
+// (see events.rs) next to any others
+pub fn inspect(event: &WebEvent) {
     match event {
         WebEvent::PageLoad => println!("Page loaded"),
         WebEvent::PageUnload => println!("Page unloaded"),
@@ -132,65 +128,408 @@ enum Number {
 }
 
 // enum with explicit discriminator
-enum Color {
+// The discriminants are the colours' hex codes, which the svg chapter uses
+// to draw with them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
     Red = 0xff0000,
     Green = 0x00ff00,
     Blue = 0x0000ff,
+    Black = 0x000000,
+    White = 0xffffff,
 }
 
 // This is a test use case
 // A common way to implement a linked-list is with enums
-enum List {
+// It's generic now so it can hold any T instead of only u32
+#[derive(Default)]
+pub enum List<T> {
     // Cons: Tuple struct that wraps an element and a pointer to the next node
-    Cons(u32, Box<List>),
+    // The pointer is a Link, which is a Box<List<T>> that knows how to drop
+    // itself without recursing (see below)
+    Cons(T, Link<T>),
     // Nil: A node that signifies the end of the linked list
+    #[default]
     Nil,
 }
 
 // Methods can be attached to an enum
-impl List {
+impl<T> List<T> {
     // Create an empty list
-    fn new() -> List {
+    pub fn new() -> List<T> {
         // Nil has type List
         Nil
     }
 
     // Consume a list and return the same list with a new element at its front
-    fn prepend(self, elem: u32) -> List {
+    pub fn prepend(self, elem: T) -> List<T> {
         // Cons also has type List
-        Cons(elem, Box::new(self))
+        Cons(elem, Link::new(self))
+    }
+
+    // Same as prepend but works in place through a &mut instead of consuming the list
+    pub fn push_front(&mut self, elem: T) {
+        // mem::replace lets us move the old list out from behind the &mut
+        // as long as we leave something (Nil) in its place
+        let tail = mem::replace(self, Nil);
+        *self = Cons(elem, Link::new(tail));
+    }
+
+    // Remove the first element and hand it back, if there is one
+    pub fn pop_front(&mut self) -> Option<T> {
+        match mem::replace(self, Nil) {
+            Cons(head, mut tail) => {
+                *self = tail.take();
+                Some(head)
+            },
+            Nil => None,
+        }
+    }
+
+    // Look at the first element without removing it
+    pub fn peek(&self) -> Option<&T> {
+        match self {
+            Cons(head, _) => Some(head),
+            Nil => None,
+        }
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Cons(head, _) => Some(head),
+            Nil => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Nil)
     }
 
     // Return the length of the list
-    fn len(&self) -> u32 {
-        // self has to be matched because the behavior of this method
+    pub fn len(&self) -> usize {
+        // This used to match on self, because the behavior of this method
         // depends on the variant of self
         // self has type &List and *self has type List
         // Matching on a
         // concrete type T is preferred over a match on a reference &T
         // After Rust 2018 you can use self here and tail (with no ref) below as well
-        match *self {
-            // Can't take ownership of the tail because self is borrowed
-            // Instead take a reference to the tail
-            Cons(_, ref tail) => 1 + tail.len(),
-            // Base Case: An empty list has zero length
-            Nil => 0,
+        //     match *self {
+        //         // Can't take ownership of the tail because self is borrowed
+        //         // Instead take a reference to the tail
+        //         Cons(_, ref tail) => 1 + tail.len(),
+        //         // Base Case: An empty list has zero length
+        //         Nil => 0,
+        //     }
+        // That needs a stack frame per node and blows the stack on a long
+        // enough list
+        // Walking the list with an iterator only ever needs one frame
+        self.iter().count()
+    }
+
+    // Reverse the list in place by popping every node off the front
+    // and pushing it onto a new list
+    pub fn reverse(&mut self) {
+        let mut reversed = Nil;
+        while let Some(elem) = self.pop_front() {
+            reversed.push_front(elem);
+        }
+        *self = reversed;
+    }
+
+    // Move every element of other onto the end of this list
+    pub fn append(&mut self, other: List<T>) {
+        *self.last_link() = other;
+    }
+
+    pub fn contains(&self, elem: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|item| item == elem)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { next: Some(self) }
+    }
+
+    // Walk to the Nil at the very end of the list
+    // Anything written there ends up after the last element
+    fn last_link(&mut self) -> &mut List<T> {
+        let mut link = self;
+        while let Cons(_, tail) = link {
+            link = &mut **tail;
         }
+        link
     }
+}
 
+impl<T: fmt::Display> List<T> {
     // Return representation of the list as a (heap allocated) string
-    fn stringify(&self) -> String {
-        match *self {
-            Cons(head, ref tail) => {
-                // format! is similar to print! but returns a heap
-                // allocated string instead of printing to the console
-                format!("{}, {}", head, tail.stringify())
+    pub fn stringify(&self) -> String {
+        // This used to be format!("{}, {}", head, tail.stringify()) for a Cons
+        // and format!("Nil") for the end
+        // format! is similar to print! but returns a heap
+        // allocated string instead of printing to the console
+        // to_string() does the same through the Display impl below, which
+        // walks the list in a loop, so this is safe for long lists too
+        self.to_string()
+    }
+}
+
+// The pointer from one Cons to the next
+// With a plain Box<List<T>> dropping the head drops its tail, which drops its
+// tail, and so on, one stack frame per node until the stack runs out
+// Link unhooks each node before it is dropped so the whole thing is a loop
+pub struct Link<T>(Box<List<T>>);
+
+impl<T> Link<T> {
+    fn new(list: List<T>) -> Link<T> {
+        Link(Box::new(list))
+    }
+
+    // Move the list out from behind the link, leaving Nil in its place
+    fn take(&mut self) -> List<T> {
+        mem::replace(&mut *self.0, Nil)
+    }
+}
+
+impl<T> Deref for Link<T> {
+    type Target = List<T>;
+
+    fn deref(&self) -> &List<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Link<T> {
+    fn deref_mut(&mut self) -> &mut List<T> {
+        &mut self.0
+    }
+}
+
+impl<T> Drop for Link<T> {
+    fn drop(&mut self) {
+        let mut next = self.take();
+        // Every time next is overwritten the old node gets dropped, but its
+        // link was already emptied so that drop stops right there
+        while let Cons(_, ref mut tail) = next {
+            next = tail.take();
+        }
+    }
+}
+
+// Display and Debug both print the same "3, 2, 1, Nil" form stringify uses
+// Debug just uses {:?} for each element instead of {}
+impl<T: fmt::Display> fmt::Display for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for elem in self {
+            write!(f, "{}, ", elem)?;
+        }
+        write!(f, "Nil")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for elem in self {
+            write!(f, "{:?}, ", elem)?;
+        }
+        write!(f, "Nil")
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+// Borrowing iterator, yields &T from front to back
+pub struct Iter<'a, T> {
+    next: &'a List<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next {
+            Cons(head, tail) => {
+                self.next = &**tail;
+                Some(head)
             },
-            Nil => format!("Nil"),
+            Nil => None,
         }
     }
 }
 
+// Mutable iterator, yields &mut T from front to back
+// The Option is there so we can take() the reference out and split it
+// into the head and the rest of the list
+pub struct IterMut<'a, T> {
+    next: Option<&'a mut List<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next.take() {
+            Some(Cons(head, tail)) => {
+                self.next = Some(&mut **tail);
+                Some(head)
+            },
+            _ => None,
+        }
+    }
+}
+
+// Owning iterator, pops elements off the front until the list is empty
+pub struct IntoIter<T>(List<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+}
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// Collecting keeps the order of the iterator, so the first item ends up at the front
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Nil;
+        list.extend(iter);
+        list
+    }
+}
+
+// Extending adds the new items to the back, the same way Vec does
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut link = self.last_link();
+        for elem in iter {
+            *link = Cons(elem, Link::new(Nil));
+            // We just wrote a Cons so this always matches
+            if let Cons(_, tail) = link {
+                link = tail;
+            }
+        }
+    }
+}
+
+// Everything that can go wrong when reading a list back out of a string
+// Positions are byte offsets into the input so they point right at the problem
+#[derive(Debug, PartialEq)]
+pub enum ParseListError {
+    // Something that is neither a number nor Nil
+    InvalidToken { position: usize, token: String },
+    // A number that doesn't fit in a u32
+    Overflow { position: usize, token: String },
+    // The input ended before we saw Nil
+    MissingNil { position: usize },
+    // There is more input after Nil
+    TrailingInput { position: usize },
+}
+
+impl fmt::Display for ParseListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseListError::InvalidToken { position, token } => {
+                write!(f, "expected a number or Nil at position {}, found \"{}\"", position, token)
+            },
+            ParseListError::Overflow { position, token } => {
+                write!(f, "number \"{}\" at position {} does not fit in a u32", token, position)
+            },
+            ParseListError::MissingNil { position } => {
+                write!(f, "list ends at position {} without a Nil", position)
+            },
+            ParseListError::TrailingInput { position } => {
+                write!(f, "unexpected input after Nil at position {}", position)
+            },
+        }
+    }
+}
+
+impl Error for ParseListError {}
+
+// Reads back what stringify writes, so "3, 2, 1, Nil".parse() gives the same list
+// Whitespace around each item is ignored
+impl FromStr for List<u32> {
+    type Err = ParseListError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut elems = Vec::new();
+        let mut offset = 0;
+        let mut pieces = s.split(',');
+
+        while let Some(piece) = pieces.next() {
+            let token = piece.trim();
+            // Where the token itself starts, past any leading whitespace
+            let position = offset + (piece.len() - piece.trim_start().len());
+            // +1 for the comma split() ate
+            offset += piece.len() + 1;
+
+            if token == "Nil" {
+                // Nil has to be the very last thing in the string
+                return match pieces.next() {
+                    None => Ok(elems.into_iter().collect()),
+                    Some(_) => Err(ParseListError::TrailingInput { position: position + token.len() }),
+                };
+            }
+
+            match token.parse::<u32>() {
+                Ok(elem) => elems.push(elem),
+                Err(err) if *err.kind() == IntErrorKind::PosOverflow => {
+                    return Err(ParseListError::Overflow { position, token: token.to_owned() });
+                },
+                // An empty token at the very end means the input just stopped
+                Err(_) if token.is_empty() && offset > s.len() => {
+                    return Err(ParseListError::MissingNil { position: s.len() });
+                },
+                Err(_) => {
+                    return Err(ParseListError::InvalidToken { position, token: token.to_owned() });
+                },
+            }
+        }
+
+        Err(ParseListError::MissingNil { position: s.len() })
+    }
+}
+
 // Rust has two types of constants which can be declared in any scope, including global
 // Both require explicit type annotation
 // const: An unchangable value (the common case)
@@ -207,7 +546,7 @@ fn is_big(n: i32) -> bool {
     n > THRESHOLD
 }
 
-fn main() {
+pub fn run() {
 
     // Custom type shizz
     let name = String::from("Peter");
@@ -218,7 +557,7 @@ fn main() {
     println!("{:?}", peter);
     
     // Instantiate a Point
-    let point = Point { x: 10.3, y:0.4 };
+    let point: Point = Point::new(10.3, 0.4);
     println!("Coordinates: ({}, {})", point.x, point.y);
 
     // Make a new point by using struct update syntax to use the fields of our other one
@@ -227,14 +566,14 @@ fn main() {
     println!("Second coordinate: ({}, {})", bottom_right.x, bottom_right.y);
 
     // Destructure the point using a "let" binding
-    let Point { x: left_edge, y: top_edge } = point;
+    // The space marker has nothing in it, so .. skips it
+    let Point { x: left_edge, y: top_edge, .. } = point;
 
-    let _rectangle: Rectangle = Rectangle {
+    let _rectangle: Rectangle = Rectangle::new(
         // Struct instantiation is an expression too
-        top_left: Point { x: left_edge, y: top_edge },
-        bottom_right, // I wanted to do "bottom_right: bottom_right" here but the
-        // rust extension wants to use this shorthand so *shrugs*
-    };
+        Point::new(left_edge, top_edge),
+        bottom_right,
+    );
 
     // Instantiate a unit struct
     let _unit = Unit;
@@ -248,7 +587,7 @@ fn main() {
     println!("This pair contains {:?} and {:?}", integer, decimal);
 
     // Print our stuff
-    let rectangle2 = square(Point { x: 0.0, y: 0.0 }, 10.0, 10.0);
+    let rectangle2 = square(Point::new(0.0, 0.0), 10.0, 10.0);
     println!("The area of our rectangle is {}^2", rect_area(rectangle2));
 
     // Enum stuff
@@ -259,20 +598,20 @@ fn main() {
     let load = WebEvent::PageLoad;
     let unload = WebEvent::PageUnload;
 
-    inspect(pressed);
-    inspect(pasted);
-    inspect(click);
-    inspect(load);
-    inspect(unload);
+    // inspect is one handler on the bus, every event goes through it
+    let mut bus = EventBus::inspecting();
+    for event in [pressed, pasted, click, load, unload] {
+        bus.dispatch(&event);
+    }
 
     // Type alias enum shizz
     let _thingy = Operations::Add;
     // The most common place you'll see this is in impl blocks using the Self alias
 
     // Explicitly "use" each name so they are available without manual scoping
-    use crate::Status::{Poor, Rich};
+    use self::Status::{Poor, Rich};
     // Automatically "use" each name inside Work
-    use crate::Work::*;
+    use self::Work::*;
 
     // Equivalent to Status::Poor
     let status = Poor;
@@ -310,6 +649,43 @@ fn main() {
     println!("Linked list has length: {}", list.len());
     println!("{}", list.stringify());
 
+    // The list is generic now, so it works with anything, not just u32
+    let mut words: List<&str> = vec!["first", "second"].into_iter().collect();
+    words.push_front("zeroth");
+    words.extend(vec!["third"]);
+    println!("{}", words);
+    println!("{:?}", words);
+
+    // Pop and peek work on the front
+    assert_eq!(words.pop_front(), Some("zeroth"));
+    assert_eq!(words.peek(), Some(&"first"));
+    assert!(words.contains(&"third"));
+
+    // Iterators in all three flavors
+    let mut numbers: List<u32> = (1..=5).collect();
+    for number in numbers.iter_mut() {
+        *number *= 10;
+    }
+    let total: u32 = numbers.iter().sum();
+    println!("{} adds up to {}", numbers, total);
+
+    numbers.reverse();
+    numbers.append((1..=2).collect());
+    assert_eq!(numbers.to_string(), "50, 40, 30, 20, 10, 1, 2, Nil");
+    assert_eq!(numbers.stringify(), numbers.to_string());
+
+    let doubled: List<u32> = numbers.into_iter().map(|n| n * 2).collect();
+    assert_eq!(doubled, vec![100, 80, 60, 40, 20, 2, 4].into_iter().collect());
+
+    // Anything stringify writes can be parsed back into the same list
+    let parsed: List<u32> = "3, 2, 1, Nil".parse().unwrap();
+    assert_eq!(parsed.stringify(), "3, 2, 1, Nil");
+
+    // Bad input tells you what went wrong and where
+    if let Err(err) = "3, 2, 1".parse::<List<u32>>() {
+        println!("Parse error: {}", err);
+    }
+
     // Const stuff
     let n = 16;
 
@@ -322,4 +698,100 @@ fn main() {
     // Error! Cannot modify a const
     // THRESHOLD = 5;
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+
+    // None of the list methods recurse, so really long lists are fine
+    // Each of these would have overflowed the stack back when len, stringify
+    // and drop called themselves once per node
+    const LONG: u32 = 2_000_000;
+
+    #[test]
+    fn long_lists_measure_print_and_reverse() {
+        let mut list = List::new();
+        for n in 0..LONG {
+            list.push_front(n);
+        }
+        assert_eq!(list.len(), LONG as usize);
+        assert_eq!(list.peek(), Some(&(LONG - 1)));
+        assert!(list.stringify().ends_with("1, 0, Nil"));
+
+        list.reverse();
+        assert_eq!(list.peek(), Some(&0));
+    }
+
+    #[test]
+    fn long_lists_compare_and_drop() {
+        let list: List<u32> = (0..LONG).collect();
+        let copy: List<u32> = list.iter().copied().collect();
+        assert!(copy == list);
+        drop(copy);
+        drop(list);
+    }
+
+    #[test]
+    fn long_lists_built_with_prepend_and_append_drop() {
+        let mut prepended = List::new();
+        for n in 0..LONG / 2 {
+            prepended = prepended.prepend(n);
+        }
+        prepended.append((0..LONG / 2).collect());
+        assert_eq!(prepended.len(), LONG as usize);
+    }
+
+    // parse(stringify(list)) == list for a pile of random lists
+    #[test]
+    fn parse_reads_back_what_stringify_writes() {
+        let mut random = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..1_000 {
+            let len = random.below(50);
+            let list: List<u32> = (0..len)
+                .map(|_| match random.below(4) {
+                    // Make sure the edges of u32 show up often
+                    0 => 0,
+                    1 => u32::MAX,
+                    _ => random.next_u64() as u32,
+                })
+                .collect();
+            let text = list.stringify();
+            assert_eq!(text.parse::<List<u32>>().as_ref(), Ok(&list), "failed on {}", text);
+
+            // Padding every item with extra whitespace still parses to the same list
+            let padded = text.replace(", ", " ,\t  ");
+            assert_eq!(padded.parse::<List<u32>>().as_ref(), Ok(&list), "failed on {:?}", padded);
+        }
+    }
+
+    #[test]
+    fn parse_accepts_any_whitespace() {
+        let parsed: List<u32> = "3, 2, 1, Nil".parse().unwrap();
+        let spaced: List<u32> = "  3 ,2,   1 ,\tNil \n".parse().unwrap();
+        assert_eq!(spaced, parsed);
+        assert_eq!("Nil".parse::<List<u32>>(), Ok(List::new()));
+    }
+
+    // Bad input tells you what went wrong and where
+    #[test]
+    fn parse_errors_say_where() {
+        assert_eq!(
+            "3, x, Nil".parse::<List<u32>>(),
+            Err(ParseListError::InvalidToken { position: 3, token: "x".to_owned() })
+        );
+        assert_eq!(
+            "4294967296, Nil".parse::<List<u32>>(),
+            Err(ParseListError::Overflow { position: 0, token: "4294967296".to_owned() })
+        );
+        assert_eq!("3, 2, 1".parse::<List<u32>>(), Err(ParseListError::MissingNil { position: 7 }));
+        assert_eq!("3, 2, ".parse::<List<u32>>(), Err(ParseListError::MissingNil { position: 6 }));
+        assert_eq!("".parse::<List<u32>>(), Err(ParseListError::MissingNil { position: 0 }));
+        assert_eq!("1, Nil, 2".parse::<List<u32>>(), Err(ParseListError::TrailingInput { position: 6 }));
+        assert_eq!(
+            "1, , Nil".parse::<List<u32>>(),
+            Err(ParseListError::InvalidToken { position: 3, token: String::new() })
+        );
+    }
+}
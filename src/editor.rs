@@ -0,0 +1,688 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::ops::Range;
+
+use unicode_segmentation::GraphemeCursor;
+
+use crate::custom_types::WebEvent;
+
+// A text editor model driven by WebEvents
+// KeyPress types a character (or runs a command, see Key) and Paste inserts
+// a whole string, which may span several lines
+//
+// The cursor is a byte offset into the text that always sits between two
+// grapheme clusters: what a reader would call one character, even when it's
+// made of several chars like "e" plus a combining accent, a Devanagari
+// consonant and its vowel sign, a flag made of two regional indicators or an
+// emoji joined up with zero width joiners
+// Inserting can glue the new text onto whatever follows it, like typing "e"
+// in front of a lone accent, so after an insert the cursor goes to the end
+// of the cluster it landed in
+// The rules for those are Unicode's UAX #29, and the tables behind them are
+// far too big to keep by hand, so the unicode-segmentation crate finds them
+//
+// Line breaks are always stored as "\n", pasted "\r\n" and "\r" get turned
+// into it
+
+// What a KeyPress means to the editor
+// There's only a char to go on, so commands use the old terminal control
+// characters: backspace and delete, and the emacs keys for moving around
+// Any other control character, like Ctrl-C, means nothing to the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Newline,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Undo,
+    Redo,
+}
+
+// Which kind of edit made the last change, so a burst of the same kind can
+// be undone in one go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Backspace,
+    Delete,
+    Paste,
+}
+
+// One change to the text: at offset, removed was replaced with inserted
+#[derive(Debug, Clone, PartialEq)]
+struct Edit {
+    offset: usize,
+    removed: String,
+    inserted: String,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Editor {
+    text: String,
+    cursor: usize,
+    // Up and down try to stay in this column, even through shorter lines
+    preferred_column: Option<usize>,
+    // Each entry is one undo step, made of one or more edits
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    // The kind of the last edit, or None if something else happened since
+    // so the next edit has to start a new undo step
+    last_edit: Option<EditKind>,
+}
+
+impl Key {
+    // None for a control character that isn't one of the commands
+    pub fn from_char(c: char) -> Option<Key> {
+        let key = match c {
+            '\n' | '\r' => Key::Newline,
+            '\u{8}' => Key::Backspace,
+            '\u{7f}' => Key::Delete,
+            '\u{2}' => Key::Left,   // Ctrl-B, back
+            '\u{6}' => Key::Right,  // Ctrl-F, forward
+            '\u{10}' => Key::Up,    // Ctrl-P, previous line
+            '\u{e}' => Key::Down,   // Ctrl-N, next line
+            '\u{1}' => Key::Home,   // Ctrl-A
+            '\u{5}' => Key::End,    // Ctrl-E
+            '\u{1a}' => Key::Undo,  // Ctrl-Z
+            '\u{19}' => Key::Redo,  // Ctrl-Y
+            // A tab is still text
+            '\t' => Key::Char(c),
+            c if c.is_control() => return None,
+            c => Key::Char(c),
+        };
+        Some(key)
+    }
+}
+
+// Where the grapheme cluster starting at start ends
+fn cluster_end(text: &str, start: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(start, text.len(), true);
+    // The whole text is one chunk, so the cursor never asks for more
+    cursor.next_boundary(text, 0).ok().flatten().unwrap_or(text.len())
+}
+
+// Start of the line offset is on
+fn line_start(text: &str, offset: usize) -> usize {
+    text[..offset].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+// End of the line offset is on, not counting the "\n"
+fn line_end(text: &str, offset: usize) -> usize {
+    text[offset..].find('\n').map_or(text.len(), |newline| offset + newline)
+}
+
+// The cluster boundary before offset
+fn previous_boundary(text: &str, offset: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(offset, text.len(), true);
+    cursor.prev_boundary(text, 0).ok().flatten().unwrap_or(0)
+}
+
+// How many clusters there are from start to end
+fn cluster_count(text: &str, start: usize, end: usize) -> usize {
+    let mut count = 0;
+    let mut offset = start;
+    while offset < end {
+        offset = cluster_end(text, offset);
+        count += 1;
+    }
+    count
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Byte offset of the cursor in text()
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // Line and column of the cursor, counting from 0, with the column
+    // counted in clusters
+    pub fn position(&self) -> (usize, usize) {
+        let start = line_start(&self.text, self.cursor);
+        let line = self.text[..start].matches('\n').count();
+        (line, cluster_count(&self.text, start, self.cursor))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // Apply a KeyPress or a Paste, anything else isn't for the editor
+    // Returns whether the event was used
+    pub fn apply(&mut self, event: &WebEvent) -> bool {
+        match event {
+            WebEvent::KeyPress(c) => match Key::from_char(*c) {
+                Some(key) => {
+                    self.key(key);
+                    true
+                },
+                None => false,
+            },
+            WebEvent::Paste(text) => {
+                self.paste(text);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn key(&mut self, key: Key) {
+        // Everything but up and down forgets the column to go back to
+        if !matches!(key, Key::Up | Key::Down) {
+            self.preferred_column = None;
+        }
+
+        match key {
+            Key::Char(c) => {
+                let mut buffer = [0; 4];
+                self.replace(self.cursor..self.cursor, c.encode_utf8(&mut buffer), EditKind::Typing);
+            },
+            Key::Newline => self.replace(self.cursor..self.cursor, "\n", EditKind::Typing),
+            Key::Backspace => {
+                let start = previous_boundary(&self.text, self.cursor);
+                if start < self.cursor {
+                    self.replace(start..self.cursor, "", EditKind::Backspace);
+                }
+            },
+            Key::Delete => {
+                let end = cluster_end(&self.text, self.cursor);
+                if end > self.cursor {
+                    self.replace(self.cursor..end, "", EditKind::Delete);
+                }
+            },
+            Key::Left => self.move_to(previous_boundary(&self.text, self.cursor)),
+            Key::Right => self.move_to(cluster_end(&self.text, self.cursor)),
+            Key::Home => self.move_to(line_start(&self.text, self.cursor)),
+            Key::End => self.move_to(line_end(&self.text, self.cursor)),
+            Key::Up => self.move_line(false),
+            Key::Down => self.move_line(true),
+            Key::Undo => {
+                self.undo();
+            },
+            Key::Redo => {
+                self.redo();
+            },
+        }
+    }
+
+    // Insert text at the cursor as one undo step of its own
+    pub fn paste(&mut self, text: &str) {
+        self.preferred_column = None;
+        if text.is_empty() {
+            return;
+        }
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        self.replace(self.cursor..self.cursor, &text, EditKind::Paste);
+        // Whatever comes next shouldn't join the paste's undo step
+        self.last_edit = None;
+    }
+
+    // Returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(group) = self.undo.pop() else {
+            return false;
+        };
+        for edit in group.iter().rev() {
+            let range = edit.offset..edit.offset + edit.inserted.len();
+            self.text.replace_range(range, &edit.removed);
+        }
+        self.cursor = group[0].cursor_before;
+        self.redo.push(group);
+        self.last_edit = None;
+        self.preferred_column = None;
+        true
+    }
+
+    // Returns false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(group) = self.redo.pop() else {
+            return false;
+        };
+        for edit in &group {
+            let range = edit.offset..edit.offset + edit.removed.len();
+            self.text.replace_range(range, &edit.inserted);
+        }
+        self.cursor = group[group.len() - 1].cursor_after;
+        self.undo.push(group);
+        self.last_edit = None;
+        self.preferred_column = None;
+        true
+    }
+
+    fn move_to(&mut self, offset: usize) {
+        self.cursor = offset;
+        self.last_edit = None;
+    }
+
+    // Up or down a line, staying in the same column if the line is long
+    // enough and going to its end if it isn't
+    fn move_line(&mut self, down: bool) {
+        let start = line_start(&self.text, self.cursor);
+        let column = *self
+            .preferred_column
+            .get_or_insert_with(|| cluster_count(&self.text, start, self.cursor));
+
+        let target_start = if down {
+            let end = line_end(&self.text, self.cursor);
+            if end == self.text.len() {
+                // Already on the last line
+                return self.move_to(end);
+            }
+            end + 1
+        } else {
+            if start == 0 {
+                return self.move_to(0);
+            }
+            line_start(&self.text, start - 1)
+        };
+
+        let target_end = line_end(&self.text, target_start);
+        let mut offset = target_start;
+        for _ in 0..column {
+            if offset >= target_end {
+                break;
+            }
+            offset = cluster_end(&self.text, offset);
+        }
+        self.move_to(offset);
+    }
+
+    // Every change to the text goes through here so it can be undone
+    fn replace(&mut self, range: Range<usize>, inserted: &str, kind: EditKind) {
+        let cursor_before = self.cursor;
+        let removed = self.text[range.clone()].to_owned();
+        self.text.replace_range(range.clone(), inserted);
+        // Already a boundary unless the insert joined onto the next cluster
+        let end = range.start + inserted.len();
+        self.cursor = cluster_end(&self.text, previous_boundary(&self.text, end));
+
+        let edit = Edit {
+            offset: range.start,
+            removed,
+            inserted: inserted.to_owned(),
+            cursor_before,
+            cursor_after: self.cursor,
+        };
+        // Anything new makes the old redo history meaningless
+        self.redo.clear();
+        match self.undo.last_mut() {
+            Some(group) if self.last_edit == Some(kind) => group.push(edit),
+            _ => self.undo.push(vec![edit]),
+        }
+        self.last_edit = Some(kind);
+    }
+}
+
+pub fn run() {
+    let mut editor = Editor::new();
+    let type_text = |editor: &mut Editor, text: &str| {
+        for c in text.chars() {
+            editor.apply(&WebEvent::KeyPress(c));
+        }
+    };
+
+    // A burst of typing undoes in one step
+    type_text(&mut editor, "hello");
+    editor.key(Key::Home);
+    type_text(&mut editor, "oh ");
+    println!("{:?}", editor.text());
+    editor.undo();
+    println!("after undo: {:?}", editor.text());
+
+    // Moving around never splits a cluster, so the family emoji is one step
+    editor.key(Key::End);
+    editor.paste(" 👨\u{200d}👩\u{200d}👧 e\u{301}");
+    editor.key(Key::Left);
+    editor.key(Key::Left);
+    editor.key(Key::Backspace);
+    println!("{:?} with the cursor at {:?}", editor.text(), editor.position());
+
+    // Pastes can be several lines, and up and down keep their column
+    let mut editor = Editor::new();
+    editor.apply(&WebEvent::Paste("first line\r\nab\rthird line".to_owned()));
+    editor.key(Key::Up);
+    editor.key(Key::Up);
+    println!("{:?} with the cursor at {:?}", editor.text(), editor.position());
+
+    // Events that aren't typing are left alone
+    println!("used a click: {}", editor.apply(&WebEvent::Click { x: 20, y: 80 }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.apply(&WebEvent::KeyPress(c));
+        }
+    }
+
+    // True if offset is between two clusters of text
+    fn on_boundary(text: &str, offset: usize) -> bool {
+        offset == 0 || cluster_end(text, previous_boundary(text, offset)) == offset
+    }
+
+    #[test]
+    fn typing_undoes_and_redoes_in_one_step() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "hello");
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.position(), (0, 5));
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "");
+        assert!(!editor.can_undo());
+        assert!(editor.redo());
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.cursor(), 5);
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn moving_ends_the_typing_step() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "hello");
+        editor.key(Key::Home);
+        type_text(&mut editor, "oh ");
+        editor.key(Key::End);
+        type_text(&mut editor, " world");
+        assert_eq!(editor.text(), "oh hello world");
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "oh hello");
+        assert_eq!(editor.cursor(), "oh hello".len());
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn new_edits_clear_the_redo_history() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "ab");
+        editor.undo();
+        assert!(editor.can_redo());
+        type_text(&mut editor, "x");
+        assert!(!editor.can_redo());
+        assert!(!editor.redo());
+        assert_eq!(editor.text(), "x");
+    }
+
+    #[test]
+    fn backspace_and_delete_steps() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "abcdef\u{2}\u{2}\u{8}\u{8}\u{7f}");
+        assert_eq!(editor.text(), "abf");
+        assert_eq!(editor.position(), (0, 2));
+        // The two backspaces go back together, then the delete was its own step
+        editor.apply(&WebEvent::KeyPress('\u{1a}'));
+        assert_eq!(editor.text(), "abef");
+        editor.apply(&WebEvent::KeyPress('\u{1a}'));
+        assert_eq!(editor.text(), "abcdef");
+        // Deleting at the end or backspacing at the start does nothing
+        editor.key(Key::End);
+        editor.key(Key::Delete);
+        editor.key(Key::Home);
+        editor.key(Key::Backspace);
+        assert_eq!(editor.text(), "abcdef");
+    }
+
+    #[test]
+    fn a_paste_is_its_own_step() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "ab");
+        editor.paste("!");
+        type_text(&mut editor, "?");
+        assert_eq!(editor.text(), "ab!?");
+        editor.undo();
+        assert_eq!(editor.text(), "ab!");
+        editor.undo();
+        assert_eq!(editor.text(), "ab");
+    }
+
+    #[test]
+    fn pasted_line_breaks_become_newlines() {
+        let mut editor = Editor::new();
+        editor.apply(&WebEvent::Paste("first line\r\nab\rthird line\r".to_owned()));
+        assert_eq!(editor.text(), "first line\nab\nthird line\n");
+        assert_eq!(editor.position(), (3, 0));
+        editor.apply(&WebEvent::KeyPress('\r'));
+        assert_eq!(editor.text(), "first line\nab\nthird line\n\n");
+    }
+
+    #[test]
+    fn up_and_down_remember_the_column() {
+        let mut editor = Editor::new();
+        editor.paste("first line\nab\nthird line");
+        assert_eq!(editor.position(), (2, 10));
+        editor.key(Key::Up);
+        assert_eq!(editor.position(), (1, 2));
+        editor.key(Key::Up);
+        assert_eq!(editor.position(), (0, 10));
+        editor.key(Key::Down);
+        editor.key(Key::Down);
+        assert_eq!(editor.position(), (2, 10));
+        // Off either end goes to the very start or end
+        editor.key(Key::Down);
+        assert_eq!(editor.cursor(), editor.text().len());
+        editor.key(Key::Left);
+        editor.key(Key::Up);
+        editor.key(Key::Up);
+        editor.key(Key::Up);
+        assert_eq!(editor.cursor(), 0);
+        // Anything else forgets the column
+        editor.key(Key::End);
+        editor.key(Key::Left);
+        editor.key(Key::Down);
+        assert_eq!(editor.position(), (1, 2));
+        editor.key(Key::Left);
+        editor.key(Key::Down);
+        assert_eq!(editor.position(), (2, 1));
+    }
+
+    // Columns count clusters, not chars or bytes
+    #[test]
+    fn up_and_down_count_clusters() {
+        let mut editor = Editor::new();
+        editor.paste("e\u{301}e\u{301}x\nabc");
+        editor.key(Key::Up);
+        assert_eq!(editor.position(), (0, 3));
+        assert_eq!(editor.cursor(), "e\u{301}e\u{301}x".len());
+    }
+
+    #[test]
+    fn typing_a_newline_splits_the_line() {
+        let mut editor = Editor::new();
+        editor.paste("first\nthird");
+        editor.key(Key::Home);
+        editor.apply(&WebEvent::KeyPress('\n'));
+        assert_eq!(editor.text(), "first\n\nthird");
+        assert_eq!(editor.position(), (2, 0));
+        editor.key(Key::Backspace);
+        assert_eq!(editor.text(), "first\nthird");
+    }
+
+    #[test]
+    fn inserting_before_a_mark_keeps_the_cursor_between_clusters() {
+        let mut editor = Editor::new();
+        editor.paste("\u{301}");
+        editor.key(Key::Home);
+        type_text(&mut editor, "e");
+        assert_eq!(editor.text(), "e\u{301}");
+        assert_eq!(editor.cursor(), "e\u{301}".len());
+        editor.key(Key::Backspace);
+        assert_eq!(editor.text(), "");
+
+        // Undo and redo put the cursor back on a boundary too
+        editor.undo();
+        assert_eq!(editor.text(), "e\u{301}");
+        assert_eq!(editor.cursor(), "e\u{301}".len());
+        editor.undo();
+        assert_eq!((editor.text(), editor.cursor()), ("\u{301}", 0));
+        editor.redo();
+        assert_eq!(editor.cursor(), "e\u{301}".len());
+    }
+
+    // Pasting a regional indicator in front of a flag pairs it up with the
+    // first half of the flag instead
+    #[test]
+    fn inserting_before_a_flag_keeps_the_cursor_between_clusters() {
+        let mut editor = Editor::new();
+        editor.paste("🇳🇿");
+        editor.key(Key::Home);
+        editor.paste("🇯");
+        assert_eq!(editor.text(), "🇯🇳🇿");
+        assert_eq!(editor.cursor(), "🇯🇳".len());
+        assert!(on_boundary(editor.text(), editor.cursor()));
+    }
+
+    #[test]
+    fn the_cursor_never_lands_inside_a_cluster() {
+        let pieces = ["e", "\u{301}", "\u{200d}", "👨", "🇳", "क", "\u{94d}", "ि", "\n", "\r", "x"];
+        let mut editor = Editor::new();
+        let mut random = crate::random::XorShift::new(0xed17_0c0f_fee5_0001);
+        for _ in 0..2000 {
+            match random.below(6) {
+                0 => editor.paste(pieces[random.below(pieces.len() as u64) as usize]),
+                1 => editor.key(Key::Left),
+                2 => editor.key(Key::Right),
+                3 => editor.key(Key::Backspace),
+                4 => editor.key(Key::Undo),
+                _ => {
+                    for c in pieces[random.below(pieces.len() as u64) as usize].chars() {
+                        editor.apply(&WebEvent::KeyPress(c));
+                    }
+                },
+            }
+            assert!(on_boundary(editor.text(), editor.cursor()), "{:?} at {}", editor.text(), editor.cursor());
+        }
+    }
+
+    #[test]
+    fn other_events_are_left_alone() {
+        let mut editor = Editor::new();
+        assert!(!editor.apply(&WebEvent::Click { x: 20, y: 80 }));
+        assert!(!editor.apply(&WebEvent::PageLoad));
+        assert!(editor.apply(&WebEvent::Paste(String::new())));
+        assert_eq!(editor, Editor::new());
+    }
+
+    // Where Left stops going from the end of text back to the start
+    fn left_stops(text: &str) -> Vec<usize> {
+        let mut editor = Editor::new();
+        editor.paste(text);
+        let mut stops = vec![editor.cursor()];
+        while editor.cursor() > 0 {
+            editor.key(Key::Left);
+            stops.push(editor.cursor());
+        }
+        stops.reverse();
+        stops
+    }
+
+    // Where Right stops going from the start of text to the end
+    fn right_stops(text: &str) -> Vec<usize> {
+        let mut editor = Editor::new();
+        editor.paste(text);
+        while editor.cursor() > 0 {
+            editor.key(Key::Left);
+        }
+        let mut stops = vec![editor.cursor()];
+        while editor.cursor() < text.len() {
+            editor.key(Key::Right);
+            stops.push(editor.cursor());
+        }
+        stops
+    }
+
+    fn assert_clusters(text: &str, clusters: &[&str]) {
+        let mut boundaries = vec![0];
+        for cluster in clusters {
+            boundaries.push(boundaries.last().unwrap() + cluster.len());
+        }
+        assert_eq!(clusters.concat(), text);
+        assert_eq!(left_stops(text), boundaries, "left through {:?}", text);
+        assert_eq!(right_stops(text), boundaries, "right through {:?}", text);
+    }
+
+    #[test]
+    fn latin_and_emoji_clusters() {
+        assert_clusters(
+            "ae\u{301}👨\u{200d}👩\u{200d}👧🇳🇿🇯🇵👍🏽z",
+            &["a", "e\u{301}", "👨\u{200d}👩\u{200d}👧", "🇳🇿", "🇯🇵", "👍🏽", "z"],
+        );
+    }
+
+    // A vowel sign is a spacing mark that belongs to the consonant before it
+    #[test]
+    fn devanagari_vowel_signs() {
+        assert_clusters("कि", &["कि"]);
+        assert_clusters("हिन्दी", &["हि", "न्दी"]);
+    }
+
+    // A virama joins two consonants into one conjunct
+    #[test]
+    fn devanagari_conjuncts() {
+        assert_clusters("क्षमा", &["क्ष", "मा"]);
+    }
+
+    #[test]
+    fn thai_spacing_marks() {
+        assert_clusters("กำไร", &["กำ", "ไ", "ร"]);
+    }
+
+    // Conjoining jamo make one syllable, just like the precomposed 한
+    #[test]
+    fn hangul_jamo() {
+        assert_clusters("\u{1112}\u{1161}\u{11ab}\u{1100}\u{1173}", &["\u{1112}\u{1161}\u{11ab}", "\u{1100}\u{1173}"]);
+        assert_clusters("한글", &["한", "글"]);
+    }
+
+    #[test]
+    fn backspace_removes_a_whole_cluster() {
+        let mut editor = Editor::new();
+        // स्ते is one conjunct, joined by the virama
+        editor.paste("नमस्ते");
+        editor.key(Key::Backspace);
+        assert_eq!(editor.text(), "नम");
+        editor.key(Key::Home);
+        editor.key(Key::Delete);
+        assert_eq!(editor.text(), "म");
+    }
+
+    // A line break is always a cluster on its own, even before a mark
+    #[test]
+    fn marks_never_join_a_line_break() {
+        assert_clusters("a\n\u{301}", &["a", "\n", "\u{301}"]);
+    }
+
+    #[test]
+    fn other_control_characters_are_ignored() {
+        let mut editor = Editor::new();
+        for c in "ab\u{3}\u{1b}\u{0}c\td".chars() {
+            editor.apply(&WebEvent::KeyPress(c));
+        }
+        assert_eq!(editor.text(), "abc\td");
+        assert!(!editor.apply(&WebEvent::KeyPress('\u{3}')));
+        assert_eq!(Key::from_char('\u{3}'), None);
+        assert_eq!(Key::from_char('\u{8}'), Some(Key::Backspace));
+    }
+}
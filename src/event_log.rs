@@ -0,0 +1,574 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::custom_types::WebEvent;
+
+// A text format for WebEvent logs, one event per line:
+//
+//     load
+//     unload
+//     key x
+//     paste "my test"
+//     click 20 80
+//
+// Strings are quoted with the usual backslash escapes (\n \r \t \0 \\ \"
+// and \u{...} for any other control character), so a multi-line paste still
+// fits on one line
+// A key is written bare unless it's whitespace, a control character, a quote
+// or a backslash, in which case it's quoted like a one-character string
+// When reading, blank lines and lines starting with # are skipped
+// Columns count characters, not bytes, and start at 1 like line numbers
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEventErrorKind {
+    // The line doesn't start with load, unload, key, paste or click
+    UnknownEvent(String),
+    // The line ended where something else was needed
+    Missing(&'static str),
+    InvalidNumber(String),
+    UnterminatedString,
+    InvalidEscape(String),
+    // key "..." with more or less than one character in the quotes
+    NotOneChar,
+    TrailingInput,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEventError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseEventErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ReadEventError {
+    Io(io::Error),
+    Parse(ParseEventError),
+}
+
+// Streams events out of anything line based, one line at a time, so a log
+// never has to fit in memory
+pub struct EventReader<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+// Should this char be written as key "..." instead of key x
+fn needs_quotes(c: char) -> bool {
+    c.is_whitespace() || c.is_control() || c == '"' || c == '\\'
+}
+
+fn write_quoted(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\0' => f.write_str("\\0")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl fmt::Display for WebEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebEvent::PageLoad => write!(f, "load"),
+            WebEvent::PageUnload => write!(f, "unload"),
+            WebEvent::KeyPress(c) if needs_quotes(*c) => {
+                write!(f, "key ")?;
+                write_quoted(f, c.encode_utf8(&mut [0; 4]))
+            },
+            WebEvent::KeyPress(c) => write!(f, "key {}", c),
+            WebEvent::Paste(text) => {
+                write!(f, "paste ")?;
+                write_quoted(f, text)
+            },
+            WebEvent::Click { x, y } => write!(f, "click {} {}", x, y),
+        }
+    }
+}
+
+impl fmt::Display for ParseEventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseEventErrorKind::UnknownEvent(word) => write!(f, "unknown event \"{}\"", word),
+            ParseEventErrorKind::Missing(what) => write!(f, "expected {}", what),
+            ParseEventErrorKind::InvalidNumber(word) => write!(f, "\"{}\" is not a whole number", word),
+            ParseEventErrorKind::UnterminatedString => write!(f, "string has no closing quote"),
+            ParseEventErrorKind::InvalidEscape(escape) => write!(f, "invalid escape \"{}\"", escape),
+            ParseEventErrorKind::NotOneChar => write!(f, "a key is exactly one character"),
+            ParseEventErrorKind::TrailingInput => write!(f, "unexpected input after the event"),
+        }
+    }
+}
+
+impl Error for ParseEventError {}
+
+impl fmt::Display for ReadEventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadEventError::Io(error) => write!(f, "couldn't read events: {}", error),
+            ReadEventError::Parse(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for ReadEventError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadEventError::Io(error) => Some(error),
+            ReadEventError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for ReadEventError {
+    fn from(error: io::Error) -> Self {
+        ReadEventError::Io(error)
+    }
+}
+
+impl From<ParseEventError> for ReadEventError {
+    fn from(error: ParseEventError) -> Self {
+        ReadEventError::Parse(error)
+    }
+}
+
+// Walks one line char by char, keeping track of the column for errors
+struct Cursor {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn new(text: &str, line: usize) -> Cursor {
+        Cursor {
+            chars: text.chars().collect(),
+            position: 0,
+            line,
+        }
+    }
+
+    fn error_at(&self, position: usize, kind: ParseEventErrorKind) -> ParseEventError {
+        ParseEventError {
+            line: self.line,
+            column: position + 1,
+            kind,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+            self.position += 1;
+        }
+    }
+
+    // Everything up to the next space, or None at the end of the line
+    fn word(&mut self) -> Option<(usize, String)> {
+        self.skip_spaces();
+        let start = self.position;
+        while self.peek().is_some_and(|c| c != ' ' && c != '\t') {
+            self.position += 1;
+        }
+        if start == self.position {
+            None
+        } else {
+            Some((start, self.chars[start..self.position].iter().collect()))
+        }
+    }
+
+    fn number(&mut self, what: &'static str) -> Result<i64, ParseEventError> {
+        let (start, word) = self
+            .word()
+            .ok_or_else(|| self.error_at(self.position, ParseEventErrorKind::Missing(what)))?;
+        word.parse()
+            .map_err(|_| self.error_at(start, ParseEventErrorKind::InvalidNumber(word)))
+    }
+
+    // A quoted string, starting at the opening quote
+    fn quoted(&mut self) -> Result<String, ParseEventError> {
+        let start = self.position;
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            let escape_start = self.position;
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error_at(start, ParseEventErrorKind::UnterminatedString))?;
+            self.position += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => text.push(self.escape(escape_start)?),
+                c => text.push(c),
+            }
+        }
+    }
+
+    // The char after a backslash, which is at start
+    fn escape(&mut self, start: usize) -> Result<char, ParseEventError> {
+        let invalid = |cursor: &Cursor| {
+            let end = (cursor.position + 1).min(cursor.chars.len());
+            let escape = cursor.chars[start..end].iter().collect();
+            cursor.error_at(start, ParseEventErrorKind::InvalidEscape(escape))
+        };
+        let c = self.peek().ok_or_else(|| self.error_at(start, ParseEventErrorKind::UnterminatedString))?;
+        let escaped = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            'u' => {
+                // \u{...} with one to six hex digits
+                self.position += 1;
+                if self.peek() != Some('{') {
+                    return Err(invalid(self));
+                }
+                let digits_start = self.position + 1;
+                let close = self.chars[digits_start..]
+                    .iter()
+                    .position(|&c| c == '}')
+                    .map(|offset| digits_start + offset)
+                    .ok_or_else(|| invalid(self))?;
+                let digits: String = self.chars[digits_start..close].iter().collect();
+                self.position = close;
+                // from_str_radix would also take a leading +
+                if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(invalid(self));
+                }
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| invalid(self))?
+            },
+            _ => return Err(invalid(self)),
+        };
+        self.position += 1;
+        Ok(escaped)
+    }
+
+    fn end(&mut self) -> Result<(), ParseEventError> {
+        self.skip_spaces();
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error_at(self.position, ParseEventErrorKind::TrailingInput)),
+        }
+    }
+
+    fn event(&mut self) -> Result<WebEvent, ParseEventError> {
+        let (start, word) = self
+            .word()
+            .ok_or_else(|| self.error_at(self.position, ParseEventErrorKind::Missing("an event")))?;
+        let event = match word.as_str() {
+            "load" => WebEvent::PageLoad,
+            "unload" => WebEvent::PageUnload,
+            "key" => {
+                self.skip_spaces();
+                let key_start = self.position;
+                match self.peek() {
+                    None => return Err(self.error_at(key_start, ParseEventErrorKind::Missing("a key"))),
+                    Some('"') => {
+                        let text = self.quoted()?;
+                        let mut chars = text.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => WebEvent::KeyPress(c),
+                            _ => return Err(self.error_at(key_start, ParseEventErrorKind::NotOneChar)),
+                        }
+                    },
+                    Some(c) => {
+                        self.position += 1;
+                        WebEvent::KeyPress(c)
+                    },
+                }
+            },
+            "paste" => {
+                self.skip_spaces();
+                if self.peek() != Some('"') {
+                    return Err(self.error_at(self.position, ParseEventErrorKind::Missing("a quoted string")));
+                }
+                WebEvent::Paste(self.quoted()?)
+            },
+            "click" => {
+                let x = self.number("an x coordinate")?;
+                let y = self.number("a y coordinate")?;
+                WebEvent::Click { x, y }
+            },
+            _ => return Err(self.error_at(start, ParseEventErrorKind::UnknownEvent(word))),
+        };
+        self.end()?;
+        Ok(event)
+    }
+}
+
+// Parse a single event on its own, errors say line 1
+impl FromStr for WebEvent {
+    type Err = ParseEventError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Cursor::new(s, 1).event()
+    }
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            reader,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+}
+
+impl EventReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(EventReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = Result<WebEvent, ReadEventError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(error) => return Some(Err(error.into())),
+            }
+            self.line_number += 1;
+
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            let content = line.trim_start_matches([' ', '\t']);
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            return Some(Cursor::new(line, self.line_number).event().map_err(ReadEventError::from));
+        }
+    }
+}
+
+// One event per line, the way EventReader reads them back
+pub fn write_events<'a, W, I>(mut writer: W, events: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a WebEvent>,
+{
+    for event in events {
+        writeln!(writer, "{}", event)?;
+    }
+    writer.flush()
+}
+
+pub fn run() {
+    let events = vec![
+        WebEvent::PageLoad,
+        WebEvent::KeyPress('x'),
+        WebEvent::KeyPress('🦀'),
+        WebEvent::KeyPress('\n'),
+        WebEvent::Paste("line one\nline \"two\"\t\\ end".to_owned()),
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::PageUnload,
+    ];
+
+    // Everything fits on one line, even the multi-line paste
+    for event in &events {
+        println!("{}", event);
+    }
+    println!("{:?}", "key \"\\u{1F980}\"".parse::<WebEvent>());
+    // Errors point at where the problem starts
+    println!("{}", "click 20 eighty".parse::<WebEvent>().unwrap_err());
+
+    // Write a log to a file and stream it back
+    let path = std::env::temp_dir().join(format!("rbe-events-{}.log", std::process::id()));
+    write_events(File::create(&path).unwrap(), &events).unwrap();
+    let read: Vec<WebEvent> = EventReader::open(&path)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    println!("Wrote and read back {} events from {}", read.len(), path.display());
+    let _ = std::fs::remove_file(&path);
+
+    // Reading carries on after a bad line, and comments and blank lines
+    // still count for line numbers
+    let log = "# a session\nload\n\n  key x\r\nclick 1 two\nunload\n";
+    for result in EventReader::new(log.as_bytes()) {
+        match result {
+            Ok(event) => println!("{:?}", event),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+
+    // Chars that are easy to get wrong: controls, every kind of whitespace,
+    // combining marks, quotes and backslashes, and things outside the BMP
+    fn tricky_chars() -> Vec<char> {
+        let mut chars: Vec<char> = (0..=0x2ff).filter_map(char::from_u32).collect();
+        chars.extend(['\u{301}', '\u{308}', '\u{20dd}', '\u{94d}', '\u{200b}', '\u{200d}']);
+        chars.extend(['\u{1680}', '\u{2000}', '\u{2028}', '\u{2029}', '\u{3000}', '\u{feff}', '\u{fffd}']);
+        chars.extend(['🦀', '👍', '🏽', '\u{1f1fa}', '\u{e0001}', '\u{10ffff}']);
+        chars
+    }
+
+    fn round_trip(event: &WebEvent) {
+        let line = event.to_string();
+        assert!(!line.contains(['\n', '\r']), "{:?} took more than a line", line);
+        assert_eq!(line.parse::<WebEvent>().as_ref(), Ok(event), "{:?}", line);
+    }
+
+    #[test]
+    fn every_key_round_trips() {
+        for c in tricky_chars() {
+            round_trip(&WebEvent::KeyPress(c));
+        }
+    }
+
+    #[test]
+    fn pastes_round_trip() {
+        round_trip(&WebEvent::Paste(String::new()));
+        round_trip(&WebEvent::Paste("line one\nline \"two\"\t\\ end\r\n\u{7}\0 ünïcødé ✓".to_owned()));
+        // A combining mark right after the opening quote has nothing to
+        // combine with but must still come back
+        round_trip(&WebEvent::Paste("\u{301}e\u{301}".to_owned()));
+
+        let chars = tricky_chars();
+        let mut random = XorShift::new(0x5eed_1e55_c0ff_ee11);
+        for _ in 0..500 {
+            let len = random.below(20);
+            let text = (0..len)
+                .map(|_| chars[random.below(chars.len() as u64) as usize])
+                .collect();
+            round_trip(&WebEvent::Paste(text));
+        }
+    }
+
+    #[test]
+    fn logs_round_trip_through_the_reader() {
+        let chars = tricky_chars();
+        let mut events = vec![WebEvent::PageLoad, WebEvent::Click { x: -5, y: i64::MAX }, WebEvent::PageUnload];
+        events.extend(chars.iter().map(|&c| WebEvent::KeyPress(c)));
+        events.push(WebEvent::Paste(chars.iter().collect()));
+
+        let mut log = Vec::new();
+        write_events(&mut log, &events).unwrap();
+        let read: Vec<WebEvent> = EventReader::new(log.as_slice()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, events);
+    }
+
+    #[test]
+    fn escapes_are_only_used_where_needed() {
+        assert_eq!(WebEvent::KeyPress('x').to_string(), "key x");
+        assert_eq!(WebEvent::KeyPress('é').to_string(), "key é");
+        assert_eq!(WebEvent::KeyPress(' ').to_string(), "key \" \"");
+        assert_eq!(WebEvent::KeyPress('\n').to_string(), "key \"\\n\"");
+        assert_eq!(WebEvent::KeyPress('"').to_string(), "key \"\\\"\"");
+        assert_eq!(WebEvent::KeyPress('\\').to_string(), "key \"\\\\\"");
+        assert_eq!(WebEvent::Paste("a\u{1b}b".to_owned()).to_string(), "paste \"a\\u{1b}b\"");
+        assert_eq!(WebEvent::Paste("\u{85}".to_owned()).to_string(), "paste \"\\u{85}\"");
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let key = |text: &str| text.parse::<WebEvent>();
+        assert_eq!(key("key \"\\u{1F980}\""), Ok(WebEvent::KeyPress('🦀')));
+        assert_eq!(key("key \"\\u{1f980}\""), Ok(WebEvent::KeyPress('🦀')));
+        assert_eq!(key("key \"\\u{0}\""), Ok(WebEvent::KeyPress('\0')));
+        assert_eq!(key("key \"\\u{000041}\""), Ok(WebEvent::KeyPress('A')));
+        assert_eq!(key("key \"\\u{10FFFF}\""), Ok(WebEvent::KeyPress('\u{10ffff}')));
+
+        let invalid = |text: &str, escape: &str| {
+            assert_eq!(
+                key(text).map_err(|error| error.kind),
+                Err(ParseEventErrorKind::InvalidEscape(escape.to_owned())),
+                "{}",
+                text
+            );
+        };
+        invalid("key \"\\u{+41}\"", "\\u{+41}");
+        invalid("key \"\\u{-41}\"", "\\u{-41}");
+        invalid("key \"\\u{ 41}\"", "\\u{ 41}");
+        invalid("key \"\\u{}\"", "\\u{}");
+        invalid("key \"\\u{0000041}\"", "\\u{0000041}");
+        invalid("key \"\\u{110000}\"", "\\u{110000}");
+        // Surrogates aren't chars
+        invalid("key \"\\u{d800}\"", "\\u{d800}");
+        invalid("key \"\\u41\"", "\\u4");
+        assert_eq!(
+            key("key \"\\u{41"),
+            Err(ParseEventError {
+                line: 1,
+                column: 6,
+                kind: ParseEventErrorKind::InvalidEscape("\\u{".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn extra_spaces_are_fine() {
+        assert_eq!("  click   1\t2  ".parse(), Ok(WebEvent::Click { x: 1, y: 2 }));
+        assert_eq!("key\t\tx".parse(), Ok(WebEvent::KeyPress('x')));
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = |text: &str| text.parse::<WebEvent>().unwrap_err();
+        let at = |line, column, kind| ParseEventError { line, column, kind };
+        assert_eq!(error("jump"), at(1, 1, ParseEventErrorKind::UnknownEvent("jump".to_owned())));
+        assert_eq!(error(""), at(1, 1, ParseEventErrorKind::Missing("an event")));
+        assert_eq!(error("click 20"), at(1, 9, ParseEventErrorKind::Missing("a y coordinate")));
+        assert_eq!(error("click 20 eighty"), at(1, 10, ParseEventErrorKind::InvalidNumber("eighty".to_owned())));
+        assert_eq!(error("key xy"), at(1, 6, ParseEventErrorKind::TrailingInput));
+        assert_eq!(error("key \"xy\""), at(1, 5, ParseEventErrorKind::NotOneChar));
+        assert_eq!(error("key \"\""), at(1, 5, ParseEventErrorKind::NotOneChar));
+        assert_eq!(error("key"), at(1, 4, ParseEventErrorKind::Missing("a key")));
+        assert_eq!(error("paste my test"), at(1, 7, ParseEventErrorKind::Missing("a quoted string")));
+        assert_eq!(error("paste \"my test"), at(1, 7, ParseEventErrorKind::UnterminatedString));
+        assert_eq!(error("paste \"ab\\"), at(1, 10, ParseEventErrorKind::UnterminatedString));
+        // Columns count chars, so the é before it doesn't count twice
+        assert_eq!(error("paste \"é\\q\""), at(1, 9, ParseEventErrorKind::InvalidEscape("\\q".to_owned())));
+        assert_eq!(error("load now"), at(1, 6, ParseEventErrorKind::TrailingInput));
+        assert_eq!(
+            error("click 20 eighty").to_string(),
+            "line 1, column 10: \"eighty\" is not a whole number"
+        );
+    }
+
+    #[test]
+    fn the_reader_counts_every_line_and_carries_on() {
+        let log = "# a session\nload\n\n  key x\r\nclick 1 two\nunload\n";
+        let results: Vec<_> = EventReader::new(log.as_bytes()).collect();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap(), &WebEvent::PageLoad);
+        assert_eq!(results[1].as_ref().unwrap(), &WebEvent::KeyPress('x'));
+        match &results[2] {
+            Err(ReadEventError::Parse(error)) => assert_eq!((error.line, error.column), (5, 9)),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert_eq!(results[3].as_ref().unwrap(), &WebEvent::PageUnload);
+    }
+}
@@ -0,0 +1,362 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt;
+
+use crate::custom_types::{inspect, WebEvent};
+
+// An event bus for WebEvent
+// Instead of one function with a println! per variant, any number of
+// handlers can listen for one kind of event, or for every event
+// Handlers run from the highest priority down, in the order they subscribed
+// when priorities are equal, and any of them can stop the rest from running
+
+// Which variant an event is, without the data inside it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    PageLoad,
+    PageUnload,
+    KeyPress,
+    Paste,
+    Click,
+}
+
+// What a handler wants to happen after it ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    // Let the next handler see the event too
+    Continue,
+    // This handler dealt with it, nobody after it gets the event
+    Stop,
+}
+
+// Returned by subscribe so the handler can be taken off the bus again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerId(u64);
+
+type Handler = Box<dyn FnMut(&WebEvent) -> Propagation>;
+
+struct Subscription {
+    id: HandlerId,
+    // None listens to every kind
+    kind: Option<EventKind>,
+    priority: i32,
+    handler: Handler,
+}
+
+// Kept sorted so dispatch can just walk the list
+#[derive(Default)]
+pub struct EventBus {
+    subscriptions: Vec<Subscription>,
+    next_id: u64,
+}
+
+impl WebEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            WebEvent::PageLoad => EventKind::PageLoad,
+            WebEvent::PageUnload => EventKind::PageUnload,
+            WebEvent::KeyPress(_) => EventKind::KeyPress,
+            WebEvent::Paste(_) => EventKind::Paste,
+            WebEvent::Click { .. } => EventKind::Click,
+        }
+    }
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    // A bus with inspect already listening to everything, which is what
+    // custom_types used to do by hand
+    pub fn inspecting() -> EventBus {
+        let mut bus = EventBus::new();
+        bus.subscribe_all(0, |event| {
+            inspect(event);
+            Propagation::Continue
+        });
+        bus
+    }
+
+    // Listen for one kind of event
+    pub fn subscribe<F>(&mut self, kind: EventKind, priority: i32, handler: F) -> HandlerId
+    where
+        F: FnMut(&WebEvent) -> Propagation + 'static,
+    {
+        self.insert(Some(kind), priority, Box::new(handler))
+    }
+
+    // Listen for every event
+    pub fn subscribe_all<F>(&mut self, priority: i32, handler: F) -> HandlerId
+    where
+        F: FnMut(&WebEvent) -> Propagation + 'static,
+    {
+        self.insert(None, priority, Box::new(handler))
+    }
+
+    // False if there was no such handler, maybe because it's already gone
+    pub fn unsubscribe(&mut self, id: HandlerId) -> bool {
+        match self.subscriptions.iter().position(|subscription| subscription.id == id) {
+            Some(index) => {
+                // remove rather than swap_remove to keep the order
+                self.subscriptions.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Run every handler listening for this event until one says Stop
+    // Returns how many handlers ran
+    pub fn dispatch(&mut self, event: &WebEvent) -> usize {
+        let kind = event.kind();
+        let mut ran = 0;
+        for subscription in &mut self.subscriptions {
+            if subscription.kind.is_some_and(|wanted| wanted != kind) {
+                continue;
+            }
+            ran += 1;
+            if (subscription.handler)(event) == Propagation::Stop {
+                break;
+            }
+        }
+        ran
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    fn insert(&mut self, kind: Option<EventKind>, priority: i32, handler: Handler) -> HandlerId {
+        let id = HandlerId(self.next_id);
+        self.next_id += 1;
+        // After everything with the same or a higher priority, so equal
+        // priorities run in the order they subscribed
+        let index = self
+            .subscriptions
+            .partition_point(|subscription| subscription.priority >= priority);
+        self.subscriptions.insert(
+            index,
+            Subscription {
+                id,
+                kind,
+                priority,
+                handler,
+            },
+        );
+        id
+    }
+}
+
+// Handlers are closures, so all there is to show is who listens for what
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.subscriptions
+                    .iter()
+                    .map(|subscription| (subscription.id, subscription.kind, subscription.priority)),
+            )
+            .finish()
+    }
+}
+
+pub fn run() {
+    let mut bus = EventBus::inspecting();
+    bus.subscribe(EventKind::KeyPress, 0, |event| {
+        println!("    the key handler saw {:?}", event);
+        Propagation::Continue
+    });
+    bus.subscribe_all(-10, |event| {
+        println!("    the audit log saw {:?}", event);
+        Propagation::Continue
+    });
+    // A modal dialog eats clicks before anything else sees them
+    let modal = bus.subscribe(EventKind::Click, 100, |_| {
+        println!("    the modal dialog ate a click");
+        Propagation::Stop
+    });
+    println!("{:?}", bus);
+
+    let events = [
+        WebEvent::KeyPress('x'),
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::PageLoad,
+    ];
+    for event in &events {
+        let ran = bus.dispatch(event);
+        println!("{} handlers ran", ran);
+    }
+
+    // Closing the dialog lets clicks through again
+    bus.unsubscribe(modal);
+    let ran = bus.dispatch(&WebEvent::Click { x: 1, y: 2 });
+    println!("{} handlers ran once the dialog closed", ran);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // A handler that writes its name and the event's kind into log
+    fn logger(log: &Log, name: &'static str, propagation: Propagation) -> impl FnMut(&WebEvent) -> Propagation {
+        let log = Rc::clone(log);
+        move |event: &WebEvent| {
+            log.borrow_mut().push(format!("{} {:?}", name, event.kind()));
+            propagation
+        }
+    }
+
+    fn taken(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    fn click() -> WebEvent {
+        WebEvent::Click { x: 1, y: 2 }
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(WebEvent::PageLoad.kind(), EventKind::PageLoad);
+        assert_eq!(WebEvent::PageUnload.kind(), EventKind::PageUnload);
+        assert_eq!(WebEvent::KeyPress('x').kind(), EventKind::KeyPress);
+        assert_eq!(WebEvent::Paste(String::new()).kind(), EventKind::Paste);
+        assert_eq!(click().kind(), EventKind::Click);
+    }
+
+    // Highest priority first, whatever order they subscribed in
+    #[test]
+    fn higher_priorities_run_first() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        bus.subscribe(EventKind::Click, 0, logger(&log, "zero", Propagation::Continue));
+        bus.subscribe(EventKind::Click, -5, logger(&log, "minus five", Propagation::Continue));
+        bus.subscribe(EventKind::Click, 10, logger(&log, "ten", Propagation::Continue));
+        bus.subscribe(EventKind::Click, i32::MIN, logger(&log, "min", Propagation::Continue));
+        bus.subscribe(EventKind::Click, i32::MAX, logger(&log, "max", Propagation::Continue));
+        assert_eq!(bus.dispatch(&click()), 5);
+        assert_eq!(taken(&log), ["max Click", "ten Click", "zero Click", "minus five Click", "min Click"]);
+    }
+
+    // Equal priorities run in the order they subscribed
+    #[test]
+    fn equal_priorities_keep_their_order() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        for name in ["first", "second", "third", "fourth"] {
+            bus.subscribe(EventKind::Paste, 1, logger(&log, name, Propagation::Continue));
+        }
+        bus.subscribe(EventKind::Paste, 2, logger(&log, "jumps the queue", Propagation::Continue));
+        bus.dispatch(&WebEvent::Paste("my test".to_owned()));
+        assert_eq!(
+            taken(&log),
+            ["jumps the queue Paste", "first Paste", "second Paste", "third Paste", "fourth Paste"]
+        );
+    }
+
+    // subscribe_all handlers line up with the per-kind ones by the same rules,
+    // priority first and then the order they subscribed
+    #[test]
+    fn subscribe_all_is_ordered_with_the_rest() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        bus.subscribe_all(1, logger(&log, "all first", Propagation::Continue));
+        bus.subscribe(EventKind::Paste, 1, logger(&log, "paste", Propagation::Continue));
+        bus.subscribe_all(1, logger(&log, "all second", Propagation::Continue));
+        bus.subscribe(EventKind::Paste, 5, logger(&log, "urgent paste", Propagation::Continue));
+        bus.subscribe_all(-1, logger(&log, "audit", Propagation::Continue));
+
+        assert_eq!(bus.dispatch(&WebEvent::Paste("x".to_owned())), 5);
+        assert_eq!(
+            taken(&log),
+            ["urgent paste Paste", "all first Paste", "paste Paste", "all second Paste", "audit Paste"]
+        );
+        // The per-kind handlers sit out other kinds
+        assert_eq!(bus.dispatch(&WebEvent::PageLoad), 3);
+        assert_eq!(taken(&log), ["all first PageLoad", "all second PageLoad", "audit PageLoad"]);
+    }
+
+    #[test]
+    fn stop_ends_the_dispatch() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        bus.subscribe(EventKind::KeyPress, 0, logger(&log, "keys", Propagation::Continue));
+        bus.subscribe(EventKind::Click, 0, logger(&log, "clicks", Propagation::Continue));
+        bus.subscribe_all(-10, logger(&log, "audit", Propagation::Continue));
+        // A modal dialog eats clicks before anything else sees them
+        let modal = bus.subscribe(EventKind::Click, 100, logger(&log, "modal", Propagation::Stop));
+
+        // Only the modal runs for the click, and key presses don't see it at all
+        assert_eq!(bus.dispatch(&click()), 1);
+        assert_eq!(bus.dispatch(&WebEvent::KeyPress('x')), 2);
+        assert_eq!(taken(&log), ["modal Click", "keys KeyPress", "audit KeyPress"]);
+
+        // Closing the dialog lets clicks through again
+        assert!(bus.unsubscribe(modal));
+        assert_eq!(bus.dispatch(&click()), 2);
+        assert_eq!(taken(&log), ["clicks Click", "audit Click"]);
+    }
+
+    // A Stop from a subscribe_all handler ends it for every kind, and
+    // handlers before it have already run
+    #[test]
+    fn stop_from_subscribe_all() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        bus.subscribe(EventKind::Click, 10, logger(&log, "before", Propagation::Continue));
+        bus.subscribe_all(5, logger(&log, "gate", Propagation::Stop));
+        bus.subscribe(EventKind::Click, 0, logger(&log, "after", Propagation::Continue));
+        assert_eq!(bus.dispatch(&click()), 2);
+        assert_eq!(bus.dispatch(&WebEvent::PageUnload), 1);
+        assert_eq!(taken(&log), ["before Click", "gate Click", "gate PageUnload"]);
+    }
+
+    #[test]
+    fn unsubscribe() {
+        let log = Log::default();
+        let mut bus = EventBus::new();
+        let first = bus.subscribe_all(0, logger(&log, "first", Propagation::Continue));
+        let second = bus.subscribe_all(0, logger(&log, "second", Propagation::Continue));
+        bus.subscribe_all(0, logger(&log, "third", Propagation::Continue));
+        assert_eq!(bus.len(), 3);
+
+        // The rest keep their order
+        assert!(bus.unsubscribe(second));
+        assert!(!bus.unsubscribe(second));
+        bus.dispatch(&click());
+        assert_eq!(taken(&log), ["first Click", "third Click"]);
+
+        // Ids aren't reused, so an old one can't take off a new handler
+        let fourth = bus.subscribe_all(0, logger(&log, "fourth", Propagation::Continue));
+        assert_ne!(fourth, second);
+        assert!(!bus.unsubscribe(second));
+        assert!(bus.unsubscribe(first));
+        assert_eq!(bus.len(), 2);
+        bus.dispatch(&click());
+        assert_eq!(taken(&log), ["third Click", "fourth Click"]);
+    }
+
+    #[test]
+    fn empty_buses() {
+        let mut bus = EventBus::new();
+        assert!(bus.is_empty());
+        assert_eq!(bus.dispatch(&WebEvent::PageUnload), 0);
+        assert_eq!(EventBus::inspecting().len(), 1);
+    }
+
+    #[test]
+    fn debug_shows_who_listens_for_what() {
+        let mut bus = EventBus::new();
+        bus.subscribe(EventKind::Click, 3, |_| Propagation::Continue);
+        bus.subscribe_all(7, |_| Propagation::Continue);
+        assert_eq!(format!("{:?}", bus), "[(HandlerId(1), None, 7), (HandlerId(0), Some(Click), 3)]");
+    }
+}
@@ -1,10 +1,20 @@
 #![allow(unreachable_code)]
 // ^ We use this for the funny unreachable sections of loops in this example
 #![allow(dead_code)]
+// The examples below copy Rust By Example as-is, so a few of them are
+// written the long way on purpose
+#![allow(
+    clippy::never_loop,
+    clippy::toplevel_ref_arg,
+    clippy::upper_case_acronyms,
+    clippy::redundant_guards,
+    clippy::match_single_binding,
+    clippy::disallowed_names
+)]
 
 use std::str::FromStr;
 
-fn main() {
+pub fn run() {
 
     // if-else statements don't need the boolean condition to be
     // surrounded by parentheses
@@ -537,7 +547,7 @@ fn main() {
     // scope like a normal let, or else diverge (e.g. break, return, panic!) when the pattern doesn't match
 
     fn get_count_item(s: &str) -> (u64, &str) {
-        let mut it = s.split("");
+        let mut it = s.split(' ');
         let (Some(count_str), Some(item)) = (it.next(), it.next()) else {
             panic!("Can't segment count item pair: '{s}'");
         };
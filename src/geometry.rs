@@ -0,0 +1,1041 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+// Points and rectangles that grew up out of custom_types
+//
+// Coordinate convention: x grows to the right and y grows DOWNWARD, the same
+// way screen pixels are numbered
+// So a rectangle's top left corner is the one with the smallest x and y, and
+// its bottom right corner the one with the biggest
+//
+// The coordinates can be any Scalar: i32 or i64 for exact pixel grids, f32
+// or f64 for everything else
+// The length and angle methods need square roots and so only exist for the
+// float types
+//
+// Every point and rectangle is also tagged with the coordinate space it
+// lives in
+// The tag is a unit struct, so it takes up no room at runtime, but the
+// compiler won't let a Point<f32, Screen> be added to a Point<f32, World> or
+// passed where a Point<f32, World> is wanted
+// Moving between spaces has to go through a Viewport, an Affine2 from one
+// space to the other, or cast_space when the two spaces really do line up
+//
+// The number type defaults to f32 and the space to World, so plain Point and
+// Rectangle mean f32 world space
+
+// Model coordinates, whatever units the scene is built in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct World;
+
+// Pixels on screen, where WebEvent::Click happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Screen;
+
+// Any coordinate widened so nothing is lost on the way from one Scalar
+// type to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wide {
+    Integer(i128),
+    Float(f64),
+}
+
+// The number types a Point can be made of
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + fmt::Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    // Big enough to hold the width, perimeter and area of any rectangle
+    // For the integer types this is a wider unsigned type, so even a
+    // rectangle spanning the whole range can't overflow
+    type Area: Copy + PartialOrd + fmt::Debug + Add<Output = Self::Area> + Mul<Output = Self::Area>;
+
+    const ZERO: Self;
+    const TWO: Self;
+
+    // high - low as an Area, where high is never below low
+    fn span(low: Self, high: Self) -> Self::Area;
+
+    // Halfway from low to high, rounded towards low for the integer types
+    fn midpoint(low: Self, high: Self) -> Self;
+
+    // Integers stop at their MIN and MAX instead of overflowing
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_sub(self, other: Self) -> Self;
+
+    fn widen(self) -> Wide;
+
+    // None if value doesn't fit
+    // Integers only take whole numbers in their range, floats round to the
+    // nearest value they can hold but refuse to turn a finite number into
+    // infinity
+    fn narrow(value: Wide) -> Option<Self>;
+}
+
+// The Scalars with square roots and angles
+pub trait Float: Scalar {
+    fn abs(self) -> Self;
+    fn hypot(self, other: Self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+}
+
+macro_rules! integer_scalar {
+    ($integer:ty, $area:ty, $wider:ty) => {
+        impl Scalar for $integer {
+            type Area = $area;
+
+            const ZERO: Self = 0;
+            const TWO: Self = 2;
+
+            fn span(low: Self, high: Self) -> $area {
+                (high as $wider - low as $wider) as $area
+            }
+
+            // Lands between low and high, so it always fits
+            fn midpoint(low: Self, high: Self) -> Self {
+                (low as $wider + (high as $wider - low as $wider) / 2) as $integer
+            }
+
+            fn saturating_add(self, other: Self) -> Self {
+                <$integer>::saturating_add(self, other)
+            }
+
+            fn saturating_sub(self, other: Self) -> Self {
+                <$integer>::saturating_sub(self, other)
+            }
+
+            fn widen(self) -> Wide {
+                Wide::Integer(self as i128)
+            }
+
+            fn narrow(value: Wide) -> Option<Self> {
+                match value {
+                    Wide::Integer(integer) => <$integer>::try_from(integer).ok(),
+                    // MAX as f64 can round up past MAX, so compare against
+                    // -MIN (one past MAX) which is always exact
+                    Wide::Float(float) => {
+                        let in_range = float >= <$integer>::MIN as f64 && float < -(<$integer>::MIN as f64);
+                        if float.fract() == 0.0 && in_range {
+                            Some(float as $integer)
+                        } else {
+                            None
+                        }
+                    },
+                }
+            }
+        }
+
+        impl<S> Mul<Point<$integer, S>> for $integer {
+            type Output = Point<$integer, S>;
+
+            fn mul(self, point: Point<$integer, S>) -> Point<$integer, S> {
+                point * self
+            }
+        }
+    };
+}
+
+macro_rules! float_scalar {
+    ($float:ty) => {
+        impl Scalar for $float {
+            type Area = $float;
+
+            const ZERO: Self = 0.0;
+            const TWO: Self = 2.0;
+
+            fn span(low: Self, high: Self) -> $float {
+                high - low
+            }
+
+            // Halving first keeps MIN..MAX from going through infinity
+            fn midpoint(low: Self, high: Self) -> Self {
+                low / 2.0 + high / 2.0
+            }
+
+            // Floats go to infinity rather than overflowing
+            fn saturating_add(self, other: Self) -> Self {
+                self + other
+            }
+
+            fn saturating_sub(self, other: Self) -> Self {
+                self - other
+            }
+
+            fn widen(self) -> Wide {
+                Wide::Float(self as f64)
+            }
+
+            fn narrow(value: Wide) -> Option<Self> {
+                match value {
+                    Wide::Integer(integer) => Some(integer as $float),
+                    Wide::Float(float) => {
+                        let narrowed = float as $float;
+                        if float.is_finite() && !narrowed.is_finite() {
+                            None
+                        } else {
+                            Some(narrowed)
+                        }
+                    },
+                }
+            }
+        }
+
+        impl Float for $float {
+            fn abs(self) -> Self {
+                <$float>::abs(self)
+            }
+
+            fn hypot(self, other: Self) -> Self {
+                <$float>::hypot(self, other)
+            }
+
+            fn atan2(self, other: Self) -> Self {
+                <$float>::atan2(self, other)
+            }
+        }
+
+        impl<S> Mul<Point<$float, S>> for $float {
+            type Output = Point<$float, S>;
+
+            fn mul(self, point: Point<$float, S>) -> Point<$float, S> {
+                point * self
+            }
+        }
+    };
+}
+
+// The difference of two i32s always fits in an i64 and is never negative
+// here, and its square fits in a u64 (likewise i64, i128 and u128)
+integer_scalar!(i32, u64, i64);
+integer_scalar!(i64, u128, i128);
+float_scalar!(f32);
+float_scalar!(f64);
+
+// min and max for PartialOrd, since only the float types have their own
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a { b } else { a }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a { b } else { a }
+}
+
+// A point in 2D space
+// It doubles as a 2D vector (the offset from the origin to the point), so
+// points can be added, subtracted and scaled like vectors
+pub struct Point<T = f32, S = World> {
+    pub x: T,
+    pub y: T,
+    pub space: PhantomData<S>,
+}
+
+// An axis-aligned rectangle
+// The corners are private so they can't end up the wrong way round:
+// whatever two opposite corners you give new(), top_left always ends up
+// above and to the left of bottom_right
+// A rectangle with zero width or height is allowed, it's just degenerate
+pub struct Rectangle<T = f32, S = World> {
+    top_left: Point<T, S>,
+    bottom_right: Point<T, S>,
+}
+
+// Maps a rectangle of the world onto a rectangle of the screen, scaling each
+// axis separately
+// For rotating or skewing on the way, use an Affine2<World, Screen> instead
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    world: Rectangle<f32, World>,
+    screen: Rectangle<f32, Screen>,
+}
+
+// These are written out by hand instead of derived because a derive would
+// only implement them when the space marker implements them too, and the
+// marker is never actually stored
+impl<T: Copy, S> Clone for Point<T, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Copy, S> Copy for Point<T, S> {}
+
+impl<T: PartialEq, S> PartialEq for Point<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+// Integer points can be compared exactly
+impl<T: Eq, S> Eq for Point<T, S> {}
+
+impl<T: Scalar, S> Default for Point<T, S> {
+    fn default() -> Self {
+        Point::ORIGIN
+    }
+}
+
+// Leave the marker out, it says nothing
+impl<T: fmt::Debug, S> fmt::Debug for Point<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Point").field("x", &self.x).field("y", &self.y).finish()
+    }
+}
+
+impl<T, S> Point<T, S> {
+    pub const fn new(x: T, y: T) -> Point<T, S> {
+        Point { x, y, space: PhantomData }
+    }
+
+    // Reinterpret the same numbers in another space
+    // Only right when the two spaces share an origin and units, otherwise
+    // go through a Viewport
+    pub fn cast_space<U>(self) -> Point<T, U> {
+        Point::new(self.x, self.y)
+    }
+}
+
+impl<T: Scalar, S> Point<T, S> {
+    pub const ORIGIN: Point<T, S> = Point::new(T::ZERO, T::ZERO);
+
+    // The same point made of another number type, or None if either
+    // coordinate doesn't fit (see Scalar::narrow)
+    pub fn checked_cast<U: Scalar>(self) -> Option<Point<U, S>> {
+        Some(Point::new(U::narrow(self.x.widen())?, U::narrow(self.y.widen())?))
+    }
+
+    pub fn dot(self, other: Point<T, S>) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    // The z part of the 3D cross product, which is the signed area of the
+    // parallelogram the two vectors span
+    // Positive when other is clockwise from self on screen (y grows downward),
+    // negative when it's counterclockwise and zero when they're parallel
+    pub fn cross(self, other: Point<T, S>) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length_squared(self) -> T {
+        self.dot(self)
+    }
+}
+
+impl<T: Float, S> Point<T, S> {
+    pub fn length(self) -> T {
+        self.x.hypot(self.y)
+    }
+
+    // Same direction with length 1, or None for the zero vector
+    // which doesn't have a direction
+    pub fn normalize(self) -> Option<Point<T, S>> {
+        let length = self.length();
+        if length == T::ZERO {
+            None
+        } else {
+            Some(self / length)
+        }
+    }
+
+    pub fn distance(self, other: Point<T, S>) -> T {
+        (other - self).length()
+    }
+
+    // Linear interpolation: t = 0 gives self, t = 1 gives other,
+    // and anything in between is that far along the line between them
+    pub fn lerp(self, other: Point<T, S>, t: T) -> Point<T, S> {
+        self + (other - self) * t
+    }
+
+    // Angle from the positive x axis in radians, in (-pi, pi]
+    // Positive angles turn towards positive y, which is clockwise on screen
+    pub fn angle(self) -> T {
+        self.y.atan2(self.x)
+    }
+
+    // Signed angle to turn self onto other, in (-pi, pi]
+    pub fn angle_to(self, other: Point<T, S>) -> T {
+        self.cross(other).atan2(self.dot(other))
+    }
+
+    // Floats rarely come out exactly equal after some math, so compare
+    // each coordinate within tolerance instead
+    pub fn approx_eq(self, other: Point<T, S>, tolerance: T) -> bool {
+        (self.x - other.x).abs() <= tolerance && (self.y - other.y).abs() <= tolerance
+    }
+}
+
+impl Point<f32, Screen> {
+    // WebEvent::Click reports whole pixels
+    pub fn from_pixel(x: i64, y: i64) -> Point<f32, Screen> {
+        Point::new(x as f32, y as f32)
+    }
+}
+
+impl<T: Scalar, S> Add for Point<T, S> {
+    type Output = Point<T, S>;
+
+    fn add(self, other: Point<T, S>) -> Point<T, S> {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<T: Scalar, S> Sub for Point<T, S> {
+    type Output = Point<T, S>;
+
+    fn sub(self, other: Point<T, S>) -> Point<T, S> {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl<T: Scalar, S> AddAssign for Point<T, S> {
+    fn add_assign(&mut self, other: Point<T, S>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Scalar, S> SubAssign for Point<T, S> {
+    fn sub_assign(&mut self, other: Point<T, S>) {
+        *self = *self - other;
+    }
+}
+
+impl<T: Scalar, S> Neg for Point<T, S> {
+    type Output = Point<T, S>;
+
+    fn neg(self) -> Point<T, S> {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+// Scaling by a number works from either side, point * 2.0 or 2.0 * point
+// The number-first side is in the scalar macros above, since it has to be
+// written out for each number type
+impl<T: Scalar, S> Mul<T> for Point<T, S> {
+    type Output = Point<T, S>;
+
+    fn mul(self, scale: T) -> Point<T, S> {
+        Point::new(self.x * scale, self.y * scale)
+    }
+}
+
+// Integer division rounds towards zero like it always does
+impl<T: Scalar, S> Div<T> for Point<T, S> {
+    type Output = Point<T, S>;
+
+    fn div(self, scale: T) -> Point<T, S> {
+        Point::new(self.x / scale, self.y / scale)
+    }
+}
+
+impl<T: Copy, S> Clone for Rectangle<T, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Copy, S> Copy for Rectangle<T, S> {}
+
+impl<T: PartialEq, S> PartialEq for Rectangle<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.top_left == other.top_left && self.bottom_right == other.bottom_right
+    }
+}
+
+impl<T: Eq, S> Eq for Rectangle<T, S> {}
+
+impl<T: fmt::Debug, S> fmt::Debug for Rectangle<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rectangle")
+            .field("top_left", &self.top_left)
+            .field("bottom_right", &self.bottom_right)
+            .finish()
+    }
+}
+
+// The measurements are all widened to T::Area first, so even an integer
+// rectangle from MIN to MAX can't overflow measuring itself
+// center and inset only ever come back as corners, so those are worked out
+// to land in T instead
+impl<T: Scalar, S> Rectangle<T, S> {
+    // Build a rectangle from any two opposite corners
+    pub fn new(corner: Point<T, S>, opposite: Point<T, S>) -> Rectangle<T, S> {
+        Rectangle {
+            top_left: Point::new(min(corner.x, opposite.x), min(corner.y, opposite.y)),
+            bottom_right: Point::new(max(corner.x, opposite.x), max(corner.y, opposite.y)),
+        }
+    }
+
+    // Build a rectangle from one corner and a size
+    // Negative sizes go left/up from the corner instead of right/down
+    pub fn from_size(corner: Point<T, S>, width: T, height: T) -> Rectangle<T, S> {
+        Rectangle::new(corner, corner + Point::new(width, height))
+    }
+
+    // The smallest rectangle holding every point, or None if there are no points
+    pub fn bounding_box<I: IntoIterator<Item = Point<T, S>>>(points: I) -> Option<Rectangle<T, S>> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Rectangle::new(first, first), |bounds, point| {
+            bounds.union(&Rectangle::new(point, point))
+        }))
+    }
+
+    // Same corners in another space, see Point::cast_space
+    pub fn cast_space<U>(self) -> Rectangle<T, U> {
+        Rectangle {
+            top_left: self.top_left.cast_space(),
+            bottom_right: self.bottom_right.cast_space(),
+        }
+    }
+
+    // The same rectangle made of another number type, or None if a corner
+    // doesn't fit
+    pub fn checked_cast<U: Scalar>(self) -> Option<Rectangle<U, S>> {
+        Some(Rectangle {
+            top_left: self.top_left.checked_cast()?,
+            bottom_right: self.bottom_right.checked_cast()?,
+        })
+    }
+
+    pub fn top_left(&self) -> Point<T, S> {
+        self.top_left
+    }
+
+    pub fn bottom_right(&self) -> Point<T, S> {
+        self.bottom_right
+    }
+
+    // All four corners, going clockwise on screen from the top left
+    pub fn corners(&self) -> [Point<T, S>; 4] {
+        [
+            self.top_left,
+            Point::new(self.right(), self.top()),
+            self.bottom_right,
+            Point::new(self.left(), self.bottom()),
+        ]
+    }
+
+    pub fn left(&self) -> T {
+        self.top_left.x
+    }
+
+    pub fn top(&self) -> T {
+        self.top_left.y
+    }
+
+    pub fn right(&self) -> T {
+        self.bottom_right.x
+    }
+
+    pub fn bottom(&self) -> T {
+        self.bottom_right.y
+    }
+
+    // Never negative thanks to the normalized corners
+    pub fn width(&self) -> T::Area {
+        T::span(self.left(), self.right())
+    }
+
+    pub fn height(&self) -> T::Area {
+        T::span(self.top(), self.bottom())
+    }
+
+    // Width and height as a vector from top_left to bottom_right
+    pub fn size(&self) -> Point<T::Area, S> {
+        Point::new(self.width(), self.height())
+    }
+
+    pub fn area(&self) -> T::Area {
+        self.width() * self.height()
+    }
+
+    pub fn perimeter(&self) -> T::Area {
+        let half = self.width() + self.height();
+        half + half
+    }
+
+    // Integer rectangles round the center towards the top left
+    pub fn center(&self) -> Point<T, S> {
+        Point::new(T::midpoint(self.left(), self.right()), T::midpoint(self.top(), self.bottom()))
+    }
+
+    // True for zero width or zero height
+    pub fn is_degenerate(&self) -> bool {
+        self.left() == self.right() || self.top() == self.bottom()
+    }
+
+    // Points on the edges count as inside
+    pub fn contains(&self, point: Point<T, S>) -> bool {
+        point.x >= self.left() && point.x <= self.right() && point.y >= self.top() && point.y <= self.bottom()
+    }
+
+    // True if the rectangles share at least one point, so touching edges count
+    pub fn intersects(&self, other: &Rectangle<T, S>) -> bool {
+        self.left() <= other.right()
+            && other.left() <= self.right()
+            && self.top() <= other.bottom()
+            && other.top() <= self.bottom()
+    }
+
+    // The overlapping part of both rectangles
+    // Rectangles that only touch overlap in a degenerate rectangle (a line or a point)
+    pub fn intersection(&self, other: &Rectangle<T, S>) -> Option<Rectangle<T, S>> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(Rectangle {
+            top_left: Point::new(max(self.left(), other.left()), max(self.top(), other.top())),
+            bottom_right: Point::new(min(self.right(), other.right()), min(self.bottom(), other.bottom())),
+        })
+    }
+
+    // The smallest rectangle holding both rectangles (their bounding box)
+    pub fn union(&self, other: &Rectangle<T, S>) -> Rectangle<T, S> {
+        Rectangle {
+            top_left: Point::new(min(self.left(), other.left()), min(self.top(), other.top())),
+            bottom_right: Point::new(max(self.right(), other.right()), max(self.bottom(), other.bottom())),
+        }
+    }
+
+    // Move every edge inwards by amount, or outwards if amount is negative
+    // If the rectangle isn't big enough to shrink that much, a side collapses
+    // onto the center line instead of turning inside out
+    // Integer rectangles growing past MIN or MAX stop there
+    pub fn inset(&self, amount: T) -> Rectangle<T, S> {
+        let edges = |low: T, high: T| {
+            let (low_edge, high_edge) = (low.saturating_add(amount), high.saturating_sub(amount));
+            if low_edge > high_edge {
+                let middle = T::midpoint(low, high);
+                (middle, middle)
+            } else {
+                (low_edge, high_edge)
+            }
+        };
+        let (left, right) = edges(self.left(), self.right());
+        let (top, bottom) = edges(self.top(), self.bottom());
+        Rectangle {
+            top_left: Point::new(left, top),
+            bottom_right: Point::new(right, bottom),
+        }
+    }
+}
+
+impl Viewport {
+    // None if either rectangle is degenerate, since a line can't be
+    // stretched back out into an area
+    pub fn new(world: Rectangle<f32, World>, screen: Rectangle<f32, Screen>) -> Option<Viewport> {
+        if world.is_degenerate() || screen.is_degenerate() {
+            None
+        } else {
+            Some(Viewport { world, screen })
+        }
+    }
+
+    pub fn world_to_screen(&self, point: Point<f32, World>) -> Point<f32, Screen> {
+        let scale_x = self.screen.width() / self.world.width();
+        let scale_y = self.screen.height() / self.world.height();
+        Point::new(
+            self.screen.left() + (point.x - self.world.left()) * scale_x,
+            self.screen.top() + (point.y - self.world.top()) * scale_y,
+        )
+    }
+
+    pub fn screen_to_world(&self, point: Point<f32, Screen>) -> Point<f32, World> {
+        let scale_x = self.world.width() / self.screen.width();
+        let scale_y = self.world.height() / self.screen.height();
+        Point::new(
+            self.world.left() + (point.x - self.screen.left()) * scale_x,
+            self.world.top() + (point.y - self.screen.top()) * scale_y,
+        )
+    }
+
+    pub fn rectangle_to_screen(&self, rectangle: &Rectangle<f32, World>) -> Rectangle<f32, Screen> {
+        Rectangle::new(self.world_to_screen(rectangle.top_left), self.world_to_screen(rectangle.bottom_right))
+    }
+
+    pub fn rectangle_to_world(&self, rectangle: &Rectangle<f32, Screen>) -> Rectangle<f32, World> {
+        Rectangle::new(self.screen_to_world(rectangle.top_left), self.screen_to_world(rectangle.bottom_right))
+    }
+}
+
+pub fn run() {
+    let point = |x, y| Point::<f32, World>::new(x, y);
+
+    let rectangle = Rectangle::new(point(0.0, 0.0), point(4.0, 3.0));
+    println!("{:?}", rectangle);
+    println!(
+        "{} wide, {} high, area {}, perimeter {}, centered on {:?}",
+        rectangle.width(),
+        rectangle.height(),
+        rectangle.area(),
+        rectangle.perimeter(),
+        rectangle.center()
+    );
+
+    // Corners given the wrong way round still give the same rectangle
+    // (this used to be a negative area)
+    let inverted = Rectangle::new(point(4.0, 3.0), point(0.0, 0.0));
+    println!("{:?} has area {}", inverted, inverted.area());
+
+    let other = Rectangle::new(point(2.0, 1.0), point(6.0, 5.0));
+    println!("Overlap with {:?}: {:?}", other, rectangle.intersection(&other));
+    println!("Both fit in {:?}", rectangle.union(&other));
+
+    let points = [point(3.0, -1.0), point(-2.0, 4.0), point(0.5, 0.5)];
+    println!("Bounding box: {:?}", Rectangle::bounding_box(points).unwrap());
+
+    // Points as vectors
+    let a = point(3.0, 4.0);
+    let b = point(1.0, -2.0);
+    assert_eq!(a + b, point(4.0, 2.0));
+    assert_eq!(a - b, point(2.0, 6.0));
+    assert_eq!(-a, point(-3.0, -4.0));
+    assert_eq!(a * 2.0, point(6.0, 8.0));
+    assert_eq!(2.0 * a, point(6.0, 8.0));
+    assert_eq!(a / 2.0, point(1.5, 2.0));
+    let mut moved = a;
+    moved += b;
+    moved -= point(1.0, 1.0);
+    assert_eq!(moved, point(3.0, 1.0));
+
+    assert_eq!(a.dot(b), -5.0);
+    assert_eq!(a.cross(b), -10.0);
+    assert_eq!(a.cross(a * 3.0), 0.0);
+    assert_eq!(a.length(), 5.0);
+    assert_eq!(a.length_squared(), 25.0);
+    assert_eq!(a.distance(Point::ORIGIN), 5.0);
+    assert_eq!(a.normalize(), Some(point(0.6, 0.8)));
+    assert_eq!(Point::<f32, World>::ORIGIN.normalize(), None);
+    assert_eq!(Point::ORIGIN.lerp(a, 0.5), point(1.5, 2.0));
+    assert_eq!(a.lerp(b, 0.0), a);
+    assert_eq!(a.lerp(b, 1.0), b);
+
+    // Angles are in radians, positive towards +y
+    let right = point(1.0, 0.0);
+    let down = point(0.0, 1.0);
+    assert_eq!(right.angle(), 0.0);
+    assert_eq!(down.angle(), std::f32::consts::FRAC_PI_2);
+    assert_eq!(right.angle_to(down), std::f32::consts::FRAC_PI_2);
+    assert_eq!(down.angle_to(right), -std::f32::consts::FRAC_PI_2);
+
+    // Approximate equality for results that pick up rounding errors
+    let third = point(1.0, 1.0) / 3.0 * 3.0;
+    assert!(third.approx_eq(point(1.0, 1.0), 1e-6));
+    assert!(!point(1.0, 1.0).approx_eq(point(1.1, 1.0), 1e-6));
+    println!("{:?} is {} long at {} radians", a, a.length(), a.angle());
+
+    assert_eq!(rectangle.size(), point(4.0, 3.0));
+
+    // Screen and world points can't be mixed up
+    // Adding a Point<f32, Screen> to a Point<World> doesn't compile, the click has
+    // to go through the viewport first
+    // Here the world is 100x50 units drawn into an 800x400 window
+    let viewport = Viewport::new(
+        Rectangle::new(point(0.0, 0.0), point(100.0, 50.0)),
+        Rectangle::new(Point::new(0.0, 0.0), Point::new(800.0, 400.0)),
+    )
+    .unwrap();
+    let click: Point<f32, Screen> = Point::from_pixel(400, 100);
+    println!("Click at {:?} on screen is {:?} in the world", click, viewport.screen_to_world(click));
+
+    // Integer rectangles for exact pixel grids
+    // Measuring them is done in a wider type, so this one spanning all of
+    // i32 has a width, center and area without overflowing
+    let pixels: Rectangle<i32> = Rectangle::new(Point::new(10, 20), Point::new(0, 0));
+    println!("Pixel grid {:?} has area {}", pixels, pixels.area());
+    let huge: Rectangle<i32> = Rectangle::new(Point::new(i32::MIN, i32::MIN), Point::new(i32::MAX, i32::MAX));
+    println!("{:?} is {} wide, centered on {:?}", huge, huge.width(), huge.center());
+
+    // Checked conversions between the number types
+    println!("{:?} as i32 is {:?}", point(7.5, 0.0), point(7.5, 0.0).checked_cast::<i32>());
+    println!("{:?} as f32 is {:?}", pixels, pixels.checked_cast::<f32>());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Affine2;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn rectangle() -> Rectangle {
+        Rectangle::new(point(0.0, 0.0), point(4.0, 3.0))
+    }
+
+    #[test]
+    fn measurements() {
+        let rectangle = rectangle();
+        assert_eq!(rectangle.width(), 4.0);
+        assert_eq!(rectangle.height(), 3.0);
+        assert_eq!(rectangle.area(), 12.0);
+        assert_eq!(rectangle.perimeter(), 14.0);
+        assert_eq!(rectangle.center(), point(2.0, 1.5));
+        assert_eq!(rectangle.top_left(), point(0.0, 0.0));
+        assert_eq!(rectangle.bottom_right(), point(4.0, 3.0));
+    }
+
+    // Corners given the wrong way round still give the same rectangle
+    #[test]
+    fn inverted_corners_are_normalized() {
+        let rectangle = rectangle();
+        let inverted = Rectangle::new(point(4.0, 3.0), point(0.0, 0.0));
+        assert_eq!(inverted, rectangle);
+        assert_eq!(inverted.area(), 12.0);
+        let flipped = Rectangle::new(point(0.0, 3.0), point(4.0, 0.0));
+        assert_eq!(flipped, rectangle);
+        assert_eq!(Rectangle::from_size(point(4.0, 3.0), -4.0, -3.0), rectangle);
+    }
+
+    // Edges count as inside
+    #[test]
+    fn contains_includes_edges() {
+        let rectangle = rectangle();
+        assert!(rectangle.contains(point(0.0, 0.0)));
+        assert!(rectangle.contains(point(4.0, 1.0)));
+        assert!(!rectangle.contains(point(4.1, 1.0)));
+        assert!(!rectangle.contains(point(2.0, -0.1)));
+    }
+
+    #[test]
+    fn overlaps() {
+        let rectangle = rectangle();
+        let other = Rectangle::new(point(2.0, 1.0), point(6.0, 5.0));
+        assert!(rectangle.intersects(&other));
+        assert_eq!(rectangle.intersection(&other), Some(Rectangle::new(point(2.0, 1.0), point(4.0, 3.0))));
+        assert_eq!(rectangle.union(&other), Rectangle::new(point(0.0, 0.0), point(6.0, 5.0)));
+
+        let far_away = Rectangle::new(point(10.0, 10.0), point(11.0, 11.0));
+        assert!(!rectangle.intersects(&far_away));
+        assert_eq!(rectangle.intersection(&far_away), None);
+    }
+
+    // Touching edges intersect in a line with no area
+    #[test]
+    fn touching_edges_intersect_in_a_line() {
+        let neighbour = Rectangle::new(point(4.0, 0.0), point(8.0, 3.0));
+        let edge = rectangle().intersection(&neighbour).unwrap();
+        assert!(edge.is_degenerate());
+        assert_eq!(edge.area(), 0.0);
+        assert_eq!(edge.height(), 3.0);
+    }
+
+    #[test]
+    fn degenerate_rectangles_still_behave() {
+        let dot = Rectangle::new(point(1.0, 1.0), point(1.0, 1.0));
+        assert!(dot.is_degenerate());
+        assert_eq!(dot.area(), 0.0);
+        assert_eq!(dot.perimeter(), 0.0);
+        assert!(dot.contains(point(1.0, 1.0)));
+        assert!(rectangle().intersects(&dot));
+        assert_eq!(rectangle().intersection(&dot), Some(dot));
+        let line = Rectangle::new(point(0.0, 2.0), point(5.0, 2.0));
+        assert!(line.is_degenerate());
+        assert_eq!(line.perimeter(), 10.0);
+    }
+
+    #[test]
+    fn inset_never_inverts() {
+        let rectangle = rectangle();
+        assert_eq!(rectangle.inset(1.0), Rectangle::new(point(1.0, 1.0), point(3.0, 2.0)));
+        assert_eq!(rectangle.inset(-1.0), Rectangle::new(point(-1.0, -1.0), point(5.0, 4.0)));
+        // Too much inset collapses onto the center instead of inverting
+        let collapsed = rectangle.inset(10.0);
+        assert_eq!(collapsed, Rectangle::new(point(2.0, 1.5), point(2.0, 1.5)));
+        let squashed = rectangle.inset(1.75);
+        assert_eq!(squashed.height(), 0.0);
+        assert_eq!(squashed.width(), 0.5);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let points = [point(3.0, -1.0), point(-2.0, 4.0), point(0.5, 0.5)];
+        assert_eq!(
+            Rectangle::bounding_box(points),
+            Some(Rectangle::new(point(-2.0, -1.0), point(3.0, 4.0)))
+        );
+        assert_eq!(Rectangle::<f32, World>::bounding_box([]), None);
+        // A single point gives a degenerate box on it
+        assert_eq!(Rectangle::bounding_box([point(1.0, 2.0)]), Some(Rectangle::new(point(1.0, 2.0), point(1.0, 2.0))));
+    }
+
+    fn viewport() -> Viewport {
+        // The world is 100x50 units drawn into an 800x400 window
+        Viewport::new(
+            Rectangle::new(point(0.0, 0.0), point(100.0, 50.0)),
+            Rectangle::new(Point::new(0.0, 0.0), Point::new(800.0, 400.0)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn viewport_maps_points_both_ways() {
+        let viewport = viewport();
+        let click: Point<f32, Screen> = Point::from_pixel(400, 100);
+        let clicked: Point<f32, World> = viewport.screen_to_world(click);
+        assert_eq!(clicked, point(50.0, 12.5));
+        assert_eq!(viewport.world_to_screen(clicked), click);
+        assert!(rectangle().contains(viewport.screen_to_world(Point::from_pixel(16, 16))));
+        assert_eq!(viewport.world_to_screen(point(100.0, 50.0)), Point::new(800.0, 400.0));
+    }
+
+    #[test]
+    fn viewport_maps_rectangles_both_ways() {
+        let viewport = viewport();
+        let on_screen: Rectangle<f32, Screen> = viewport.rectangle_to_screen(&rectangle());
+        assert_eq!(on_screen, Rectangle::new(Point::new(0.0, 0.0), Point::new(32.0, 24.0)));
+        assert_eq!(viewport.rectangle_to_world(&on_screen), rectangle());
+    }
+
+    // A screen upside down compared to the world flips y on the way, and
+    // the rectangles still come out normalized
+    #[test]
+    fn flipped_viewport() {
+        let world = Rectangle::new(point(0.0, 0.0), point(10.0, 10.0));
+        let screen = Rectangle::new(Point::new(0.0, 0.0), Point::new(100.0, 100.0));
+        let flip: Affine2<World, World> = Affine2::scale(1.0, -1.0).then(Affine2::translate(point(0.0, 10.0)));
+        let viewport = Viewport::new(world, screen).unwrap();
+        let flipped = viewport.rectangle_to_screen(&flip.rectangle_bounds(&rectangle()));
+        assert_eq!(flipped, Rectangle::new(Point::new(0.0, 70.0), Point::new(40.0, 100.0)));
+        assert!(flipped.top_left().y <= flipped.bottom_right().y);
+    }
+
+    // A squashed viewport can't map back
+    #[test]
+    fn degenerate_viewports_are_refused() {
+        let screen = Rectangle::new(Point::new(0.0, 0.0), Point::new(32.0, 24.0));
+        assert_eq!(Viewport::new(Rectangle::new(point(0.0, 0.0), point(0.0, 1.0)), screen), None);
+        let line: Rectangle<f32, Screen> = Rectangle::new(Point::new(0.0, 5.0), Point::new(10.0, 5.0));
+        assert_eq!(Viewport::new(rectangle(), line), None);
+    }
+
+    // When the spaces really do match up, cast_space says so out loud
+    #[test]
+    fn cast_space_keeps_the_numbers() {
+        let same_numbers: Point<f32, Screen> = point(3.0, 4.0).cast_space();
+        assert_eq!(same_numbers, Point::new(3.0, 4.0));
+        let same_rectangle: Rectangle<f32, Screen> = rectangle().cast_space();
+        assert_eq!(same_rectangle.cast_space::<World>(), rectangle());
+    }
+
+    // The marker costs nothing
+    #[test]
+    fn spaces_take_no_room() {
+        assert_eq!(std::mem::size_of::<Point<f32, Screen>>(), std::mem::size_of::<[f32; 2]>());
+        assert_eq!(std::mem::size_of::<Rectangle<f32, World>>(), std::mem::size_of::<[f32; 4]>());
+        assert_eq!(std::mem::size_of::<Rectangle<i64, Screen>>(), std::mem::size_of::<[i64; 4]>());
+    }
+
+    #[test]
+    fn integer_rectangles() {
+        let pixels: Rectangle<i32> = Rectangle::new(Point::new(10, 20), Point::new(0, 0));
+        assert_eq!(pixels.top_left(), Point::new(0, 0));
+        assert_eq!((pixels.width(), pixels.height()), (10, 20));
+        assert_eq!(pixels.area(), 200u64);
+        assert_eq!(pixels.perimeter(), 60);
+        assert_eq!(pixels.center(), Point::new(5, 10));
+        assert!(pixels.contains(Point::new(10, 20)));
+        assert_eq!(pixels.inset(2), Rectangle::new(Point::new(2, 2), Point::new(8, 18)));
+        assert_eq!(Point::<i32>::new(3, 4) * 2, 2 * Point::new(3, 4));
+        assert_eq!(Point::<i32>::new(3, 4).cross(Point::new(1, -2)), -10);
+    }
+
+    // Odd sizes round the center towards the top left, and insetting by
+    // nothing leaves the rectangle alone
+    #[test]
+    fn odd_integer_rectangles() {
+        let odd: Rectangle<i64> = Rectangle::new(Point::new(-3, 0), Point::new(2, 5));
+        assert_eq!(odd.center(), Point::new(-1, 2));
+        assert_eq!(odd.inset(0), odd);
+        assert_eq!(odd.inset(2), Rectangle::new(Point::new(-1, 2), Point::new(0, 3)));
+        assert_eq!(odd.inset(3), Rectangle::new(Point::new(-1, 2), Point::new(-1, 2)));
+    }
+
+    // The area of a rectangle spanning all of i32 doesn't fit in an i32,
+    // or even an i64 if it were done there signed, but it does in the u64
+    // that area() works in
+    #[test]
+    fn measuring_never_overflows() {
+        let huge: Rectangle<i32> = Rectangle::new(Point::new(i32::MIN, i32::MIN), Point::new(i32::MAX, i32::MAX));
+        assert_eq!(huge.width(), u32::MAX as u64);
+        assert_eq!(huge.height(), u32::MAX as u64);
+        assert_eq!(huge.size(), Point::new(u32::MAX as u64, u32::MAX as u64));
+        assert_eq!(huge.area(), u32::MAX as u64 * u32::MAX as u64);
+        assert_eq!(huge.perimeter(), 4 * u32::MAX as u64);
+        assert_eq!(huge.center(), Point::new(-1, -1));
+
+        let huge: Rectangle<i64> = Rectangle::new(Point::new(i64::MIN, 0), Point::new(i64::MAX, i64::MAX));
+        assert_eq!(huge.width(), u64::MAX as u128);
+        assert_eq!(huge.area(), u64::MAX as u128 * i64::MAX as u128);
+        assert_eq!(huge.perimeter(), 2 * (u64::MAX as u128 + i64::MAX as u128));
+        assert_eq!(huge.center(), Point::new(-1, i64::MAX / 2));
+
+        // Right up against MAX, where adding half the size to the top left
+        // would have gone past it
+        let corner: Rectangle<i32> =
+            Rectangle::new(Point::new(i32::MAX - 1, i32::MAX - 3), Point::new(i32::MAX, i32::MAX));
+        assert_eq!(corner.center(), Point::new(i32::MAX - 1, i32::MAX - 2));
+
+        let huge: Rectangle<f32> = Rectangle::new(point(f32::MIN, f32::MIN), point(f32::MAX, f32::MAX));
+        assert_eq!(huge.center(), point(0.0, 0.0));
+    }
+
+    #[test]
+    fn inset_stops_at_the_edge_of_the_type() {
+        let huge: Rectangle<i32> = Rectangle::new(Point::new(i32::MIN, i32::MIN), Point::new(i32::MAX, i32::MAX));
+        assert_eq!(huge.inset(-1), huge);
+        assert_eq!(huge.inset(i32::MIN), huge);
+        let shrunk = Rectangle::new(Point::new(i32::MIN + 1, i32::MIN + 1), Point::new(i32::MAX - 1, i32::MAX - 1));
+        assert_eq!(huge.inset(1), shrunk);
+        assert_eq!(huge.inset(i32::MAX), Rectangle::new(Point::new(-1, -1), Point::new(0, 0)));
+
+        let small: Rectangle<i64> = Rectangle::new(Point::new(-1, -1), Point::new(1, 1));
+        let grown = small.inset(i64::MIN);
+        assert_eq!(grown, Rectangle::new(Point::new(i64::MIN, i64::MIN), Point::new(i64::MAX, i64::MAX)));
+    }
+
+    // f64 keeps precision f32 loses far from the origin
+    // 2^24 is where f32 stops being able to count in ones
+    #[test]
+    fn f64_keeps_precision_far_out() {
+        let f32_step = Point::<f32>::new(16_777_216.0, 0.0) + Point::new(1.0, 0.0);
+        let f64_step = Point::<f64>::new(16_777_216.0, 0.0) + Point::new(1.0, 0.0);
+        assert_eq!(f32_step.x, 16_777_216.0);
+        assert_eq!(f64_step.x, 16_777_217.0);
+        assert_eq!(Point::<f64>::new(3.0, 4.0).length(), 5.0);
+    }
+
+    #[test]
+    fn checked_casts() {
+        let exact: Point<i32> = Point::new(7, -3);
+        assert_eq!(exact.checked_cast::<f32>(), Some(point(7.0, -3.0)));
+        assert_eq!(point(7.0, -3.0).checked_cast::<i64>(), Some(Point::new(7, -3)));
+        // Fractions, out of range numbers and NaN don't turn into integers
+        assert_eq!(point(7.5, 0.0).checked_cast::<i32>(), None);
+        assert_eq!(Point::<i64>::new(i64::MAX, 0).checked_cast::<i32>(), None);
+        assert_eq!(Point::<f64>::new(2_147_483_648.0, 0.0).checked_cast::<i32>(), None);
+        assert_eq!(Point::<f64>::new(-2_147_483_648.0, 0.0).checked_cast::<i32>(), Some(Point::new(i32::MIN, 0)));
+        assert_eq!(Point::<f64>::new(-2_147_483_649.0, 0.0).checked_cast::<i32>(), None);
+        assert_eq!(point(f32::NAN, 0.0).checked_cast::<i64>(), None);
+        assert_eq!(point(f32::INFINITY, 0.0).checked_cast::<i64>(), None);
+        // Floats round to the nearest value, but never overflow to infinity
+        assert_eq!(Point::<f64>::new(0.1, 0.0).checked_cast::<f32>(), Some(point(0.1, 0.0)));
+        assert_eq!(Point::<f64>::new(1e300, 0.0).checked_cast::<f32>(), None);
+        assert_eq!(Point::<f64>::new(f64::INFINITY, 0.0).checked_cast::<f32>(), Some(point(f32::INFINITY, 0.0)));
+        assert_eq!(Point::<i64>::new(i64::MAX, 0).checked_cast::<f64>(), Some(Point::new(i64::MAX as f64, 0.0)));
+
+        let pixels: Rectangle<i32> = Rectangle::new(Point::new(0, 0), Point::new(10, 20));
+        assert_eq!(pixels.checked_cast::<f32>(), Some(Rectangle::new(point(0.0, 0.0), point(10.0, 20.0))));
+        assert_eq!(rectangle().inset(0.5).checked_cast::<i32>(), None);
+    }
+}
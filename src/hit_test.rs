@@ -0,0 +1,456 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::rc::Rc;
+
+use crate::custom_types::WebEvent;
+use crate::events::{EventBus, EventKind, Propagation};
+use crate::geometry::{Point, Rectangle, Screen};
+
+// Work out which element of a UI a WebEvent::Click landed on
+//
+// A scene is a tree of named rectangles in screen pixels
+// Each element's bounds are relative to its parent's top left corner, and
+// children are clipped to their parent, so a click outside a parent never
+// reaches its children
+// Children are drawn over their parent, and siblings are stacked by z, with
+// the one added later on top when two have the same z
+//
+// Pixels are whole numbers like the click's, so the rectangles are
+// Rectangle<i64, Screen> and a pixel counts as inside when it's in
+// left..right and top..bottom, so two elements that share an edge never
+// both claim the pixels along it
+
+pub type Pixel = Point<i64, Screen>;
+pub type Bounds = Rectangle<i64, Screen>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ElementId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    // Relative to the parent, or to the screen for top level elements
+    bounds: Bounds,
+    z: i32,
+    // A hidden element and everything in it can't be clicked, clicks go
+    // straight through to whatever is underneath
+    visible: bool,
+    // A disabled element still catches clicks, it just doesn't act on them
+    enabled: bool,
+    parent: Option<ElementId>,
+    children: Vec<ElementId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Scene {
+    elements: Vec<Element>,
+    // Top level elements
+    roots: Vec<ElementId>,
+}
+
+// What a click hit
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickTarget {
+    Element {
+        id: ElementId,
+        name: String,
+        // Names from the top level element down to this one
+        path: Vec<String>,
+        // Where the click was relative to the element's top left corner
+        local: Pixel,
+    },
+    // The topmost thing under the click is disabled, or inside something
+    // disabled, so the click stops there without doing anything
+    Disabled { id: ElementId, name: String },
+    // Nothing was under the click
+    Background,
+}
+
+// left..right and top..bottom, see the top of the file
+fn covers(bounds: &Bounds, pixel: Pixel) -> bool {
+    pixel.x >= bounds.left() && pixel.x < bounds.right() && pixel.y >= bounds.top() && pixel.y < bounds.bottom()
+}
+
+// Moving between parent and child coordinates sticks at the ends of i64
+// instead of overflowing
+// Nothing that far out is on a real screen, but a scene built from bad
+// numbers still shouldn't be able to panic
+fn offset(pixel: Pixel, by: Pixel) -> Pixel {
+    Point::new(pixel.x.saturating_add(by.x), pixel.y.saturating_add(by.y))
+}
+
+fn relative_to(pixel: Pixel, origin: Pixel) -> Pixel {
+    Point::new(pixel.x.saturating_sub(origin.x), pixel.y.saturating_sub(origin.y))
+}
+
+impl ClickTarget {
+    // The name of what was clicked, if it can act on the click
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ClickTarget::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    // Add a top level element
+    pub fn add(&mut self, name: &str, bounds: Bounds, z: i32) -> ElementId {
+        self.insert(None, name, bounds, z)
+    }
+
+    // Add an element inside parent, bounds are relative to the parent
+    pub fn add_child(&mut self, parent: ElementId, name: &str, bounds: Bounds, z: i32) -> ElementId {
+        self.insert(Some(parent), name, bounds, z)
+    }
+
+    pub fn set_visible(&mut self, id: ElementId, visible: bool) {
+        self.elements[id.0].visible = visible;
+    }
+
+    pub fn set_enabled(&mut self, id: ElementId, enabled: bool) {
+        self.elements[id.0].enabled = enabled;
+    }
+
+    // Changing z moves the element in front of or behind its siblings
+    pub fn set_z(&mut self, id: ElementId, z: i32) {
+        self.elements[id.0].z = z;
+    }
+
+    pub fn name(&self, id: ElementId) -> &str {
+        &self.elements[id.0].name
+    }
+
+    // The first element with this name, in the order they were added
+    pub fn find(&self, name: &str) -> Option<ElementId> {
+        self.elements
+            .iter()
+            .position(|element| element.name == name)
+            .map(ElementId)
+    }
+
+    // Where the element is on screen, before any clipping
+    pub fn screen_bounds(&self, id: ElementId) -> Bounds {
+        let element = &self.elements[id.0];
+        match element.parent {
+            Some(parent) => {
+                let origin = self.screen_bounds(parent).top_left();
+                Rectangle::new(offset(origin, element.bounds.top_left()), offset(origin, element.bounds.bottom_right()))
+            },
+            None => element.bounds,
+        }
+    }
+
+    // The topmost element under the pixel
+    pub fn hit_test(&self, pixel: Pixel) -> ClickTarget {
+        let mut path = Vec::new();
+        self.hit_in(&self.roots, pixel, &mut path).unwrap_or(ClickTarget::Background)
+    }
+
+    // Hit test a Click, None for any other event
+    pub fn click(&self, event: &WebEvent) -> Option<ClickTarget> {
+        match event {
+            WebEvent::Click { x, y } => Some(self.hit_test(Point::new(*x, *y))),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, parent: Option<ElementId>, name: &str, bounds: Bounds, z: i32) -> ElementId {
+        let id = ElementId(self.elements.len());
+        self.elements.push(Element {
+            name: name.to_owned(),
+            bounds,
+            z,
+            visible: true,
+            enabled: true,
+            parent,
+            children: Vec::new(),
+        });
+        match parent {
+            Some(parent) => self.elements[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    // Look through siblings from the top down
+    // pixel is relative to their parent, and path holds the names down to it
+    fn hit_in(&self, siblings: &[ElementId], pixel: Pixel, path: &mut Vec<String>) -> Option<ClickTarget> {
+        let mut order: Vec<(usize, ElementId)> = siblings.iter().copied().enumerate().collect();
+        // Highest z first, and later siblings before earlier ones on a tie
+        order.sort_by(|(a_index, a), (b_index, b)| {
+            let (a_z, b_z) = (self.elements[a.0].z, self.elements[b.0].z);
+            b_z.cmp(&a_z).then(b_index.cmp(a_index))
+        });
+
+        for (_, id) in order {
+            let element = &self.elements[id.0];
+            if !element.visible || !covers(&element.bounds, pixel) {
+                continue;
+            }
+            if !element.enabled {
+                return Some(ClickTarget::Disabled {
+                    id,
+                    name: element.name.clone(),
+                });
+            }
+
+            let local = relative_to(pixel, element.bounds.top_left());
+            path.push(element.name.clone());
+            // Children sit on top of their parent so they get the first go
+            let target = self.hit_in(&element.children, local, path).unwrap_or_else(|| ClickTarget::Element {
+                id,
+                name: element.name.clone(),
+                path: path.clone(),
+                local,
+            });
+            path.pop();
+            return Some(target);
+        }
+        None
+    }
+}
+
+fn bounds(left: i64, top: i64, right: i64, bottom: i64) -> Bounds {
+    Rectangle::new(Point::new(left, top), Point::new(right, bottom))
+}
+
+// An 800x600 window with a toolbar, a document and a dialog on top
+fn window() -> Scene {
+    let mut scene = Scene::new();
+    let window = scene.add("window", bounds(0, 0, 800, 600), 0);
+    let toolbar = scene.add_child(window, "toolbar", bounds(0, 0, 800, 40), 1);
+    scene.add_child(toolbar, "open", bounds(10, 5, 90, 35), 0);
+    scene.add_child(toolbar, "save", bounds(100, 5, 180, 35), 0);
+    let document = scene.add_child(window, "document", bounds(0, 40, 800, 600), 0);
+    // Sticks out past the document, which clips it
+    scene.add_child(document, "image", bounds(700, 100, 900, 200), 0);
+    scene.add_child(window, "tooltip", bounds(10, 35, 200, 60), 5);
+    let dialog = scene.add("dialog", bounds(200, 150, 600, 450), 10);
+    scene.add_child(dialog, "ok", bounds(300, 250, 380, 280), 0);
+    scene
+}
+
+pub fn run() {
+    let mut scene = window();
+    for (x, y) in [(20, 10), (20, 37), (520, 410), (850, 200)] {
+        println!("({}, {}) hits {:?}", x, y, scene.hit_test(Point::new(x, y)));
+    }
+
+    // Disabled elements catch the click but don't act on it
+    let save = scene.find("save").unwrap();
+    scene.set_enabled(save, false);
+
+    // Handlers on the bus can use the target
+    let scene = Rc::new(scene);
+    let mut bus = EventBus::new();
+    {
+        let scene = Rc::clone(&scene);
+        bus.subscribe(EventKind::Click, 0, move |event| {
+            if let Some(target) = scene.click(event) {
+                println!("{:?} -> {:?}", event, target);
+            }
+            Propagation::Continue
+        });
+    }
+    for event in [
+        WebEvent::Click { x: 20, y: 10 },
+        WebEvent::KeyPress('x'),
+        WebEvent::Click { x: 120, y: 10 },
+        WebEvent::Click { x: -5, y: 80 },
+    ] {
+        bus.dispatch(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn hit(scene: &Scene, x: i64, y: i64) -> ClickTarget {
+        scene.hit_test(Point::new(x, y))
+    }
+
+    fn name_at(scene: &Scene, x: i64, y: i64) -> Option<String> {
+        hit(scene, x, y).name().map(str::to_owned)
+    }
+
+    fn id(scene: &Scene, name: &str) -> ElementId {
+        scene.find(name).unwrap()
+    }
+
+    // Nested children win over their parents
+    #[test]
+    fn children_are_on_top_of_their_parents() {
+        let scene = window();
+        assert_eq!(
+            hit(&scene, 20, 10),
+            ClickTarget::Element {
+                id: id(&scene, "open"),
+                name: "open".to_owned(),
+                path: vec!["window".to_owned(), "toolbar".to_owned(), "open".to_owned()],
+                local: Point::new(10, 5),
+            }
+        );
+        assert_eq!(name_at(&scene, 95, 10).as_deref(), Some("toolbar"));
+        assert_eq!(name_at(&scene, 400, 300).as_deref(), Some("dialog"));
+        assert_eq!(name_at(&scene, 100, 500).as_deref(), Some("document"));
+    }
+
+    #[test]
+    fn higher_z_is_on_top() {
+        let scene = window();
+        // The tooltip covers the bottom of the toolbar
+        assert_eq!(name_at(&scene, 20, 37).as_deref(), Some("tooltip"));
+        // The dialog is on top of everything in the window
+        assert_eq!(name_at(&scene, 300, 200).as_deref(), Some("dialog"));
+        assert_eq!(name_at(&scene, 520, 410).as_deref(), Some("ok"));
+    }
+
+    // Same z, so the one added later is on top
+    #[test]
+    fn later_siblings_win_ties() {
+        let mut scene = Scene::new();
+        scene.add("first", bounds(0, 0, 10, 10), 0);
+        scene.add("second", bounds(5, 5, 15, 15), 0);
+        assert_eq!(name_at(&scene, 7, 7).as_deref(), Some("second"));
+        scene.set_z(id(&scene, "first"), 1);
+        assert_eq!(name_at(&scene, 7, 7).as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn screen_bounds_add_up_the_parents() {
+        let scene = window();
+        assert_eq!(scene.screen_bounds(id(&scene, "ok")), bounds(500, 400, 580, 430));
+        assert_eq!(scene.screen_bounds(id(&scene, "save")), bounds(100, 5, 180, 35));
+        assert_eq!(scene.screen_bounds(id(&scene, "image")), bounds(700, 140, 900, 240));
+    }
+
+    // Clipped children can't be clicked outside their parent
+    #[test]
+    fn children_are_clipped() {
+        let scene = window();
+        assert_eq!(name_at(&scene, 750, 200).as_deref(), Some("image"));
+        assert_eq!(name_at(&scene, 850, 200), None);
+        assert_eq!(hit(&scene, 850, 200), ClickTarget::Background);
+    }
+
+    // Right and bottom edges belong to the next pixel over
+    #[test]
+    fn edges_belong_to_one_element() {
+        let scene = window();
+        assert_eq!(name_at(&scene, 90, 10).as_deref(), Some("toolbar"));
+        assert_eq!(name_at(&scene, 10, 10).as_deref(), Some("open"));
+        assert_eq!(name_at(&scene, 800, 10), None);
+        assert_eq!(name_at(&scene, 799, 599).as_deref(), Some("document"));
+        assert_eq!(hit(&scene, -1, 0), ClickTarget::Background);
+    }
+
+    // Hidden elements let clicks through to what's underneath
+    #[test]
+    fn hidden_elements_let_clicks_through() {
+        let mut scene = window();
+        scene.set_visible(id(&scene, "tooltip"), false);
+        assert_eq!(name_at(&scene, 20, 37).as_deref(), Some("toolbar"));
+        let dialog = id(&scene, "dialog");
+        scene.set_visible(dialog, false);
+        assert_eq!(name_at(&scene, 300, 200).as_deref(), Some("document"));
+        // And so do their children
+        assert_eq!(name_at(&scene, 520, 410).as_deref(), Some("document"));
+
+        // Moving an element down the stack
+        scene.set_visible(dialog, true);
+        scene.set_z(dialog, -1);
+        assert_eq!(name_at(&scene, 300, 200).as_deref(), Some("document"));
+    }
+
+    // Disabled elements catch the click but don't act on it
+    #[test]
+    fn disabled_elements_stop_clicks() {
+        let mut scene = window();
+        let save = id(&scene, "save");
+        scene.set_enabled(save, false);
+        assert_eq!(
+            hit(&scene, 120, 10),
+            ClickTarget::Disabled {
+                id: save,
+                name: "save".to_owned(),
+            }
+        );
+        assert_eq!(hit(&scene, 120, 10).name(), None);
+
+        // Disabling a parent disables everything in it
+        let toolbar = id(&scene, "toolbar");
+        scene.set_enabled(toolbar, false);
+        assert!(matches!(hit(&scene, 20, 10), ClickTarget::Disabled { id, .. } if id == toolbar));
+        scene.set_enabled(toolbar, true);
+        assert_eq!(name_at(&scene, 20, 10).as_deref(), Some("open"));
+    }
+
+    // Handlers on the bus can use the target
+    #[test]
+    fn clicks_from_the_bus() {
+        let mut scene = window();
+        scene.set_enabled(id(&scene, "save"), false);
+        let scene = Rc::new(scene);
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::new();
+        {
+            let scene = Rc::clone(&scene);
+            let clicked = Rc::clone(&clicked);
+            bus.subscribe(EventKind::Click, 0, move |event| {
+                if let Some(target) = scene.click(event) {
+                    clicked.borrow_mut().push(target.name().map(str::to_owned));
+                }
+                Propagation::Continue
+            });
+        }
+        for event in [
+            WebEvent::Click { x: 20, y: 10 },
+            WebEvent::KeyPress('x'),
+            WebEvent::Click { x: 120, y: 10 },
+            WebEvent::Click { x: -5, y: 80 },
+        ] {
+            bus.dispatch(&event);
+        }
+        assert_eq!(*clicked.borrow(), vec![Some("open".to_owned()), None, None]);
+        assert_eq!(scene.click(&WebEvent::PageLoad), None);
+    }
+
+    // Bounds at the ends of i64 saturate instead of overflowing
+    #[test]
+    fn huge_coordinates_dont_overflow() {
+        let mut scene = Scene::new();
+        let everything = scene.add("everything", bounds(i64::MIN, i64::MIN, i64::MAX, i64::MAX), 0);
+        let far = scene.add_child(everything, "far", bounds(i64::MAX - 10, 0, i64::MAX, 10), 0);
+        let further = scene.add_child(far, "further", bounds(5, 0, i64::MAX, 5), 0);
+
+        // The far corner is MIN + MAX - 10 = -11 on screen
+        assert_eq!(scene.screen_bounds(far), bounds(-11, i64::MIN, -1, i64::MIN + 10));
+        assert_eq!(scene.screen_bounds(further), bounds(-6, i64::MIN, i64::MAX - 11, i64::MIN + 5));
+
+        // Far from the origin the local coordinates stick at i64::MAX
+        match hit(&scene, i64::MAX - 1, i64::MAX - 1) {
+            ClickTarget::Element { name, local, .. } => {
+                assert_eq!(name, "everything");
+                assert_eq!(local, Point::new(i64::MAX, i64::MAX));
+            },
+            other => panic!("expected the big element, got {:?}", other),
+        }
+        assert_eq!(name_at(&scene, i64::MIN, i64::MIN).as_deref(), Some("everything"));
+        assert_eq!(name_at(&scene, i64::MAX, 0), None);
+
+        // Children right up against i64::MAX on their own
+        let mut scene = Scene::new();
+        let edge = scene.add("edge", bounds(i64::MAX - 10, i64::MAX - 10, i64::MAX, i64::MAX), 0);
+        let inner = scene.add_child(edge, "inner", bounds(5, 5, i64::MAX, i64::MAX), 0);
+        assert_eq!(scene.screen_bounds(inner), bounds(i64::MAX - 5, i64::MAX - 5, i64::MAX, i64::MAX));
+        assert_eq!(name_at(&scene, i64::MAX - 1, i64::MAX - 1).as_deref(), Some("inner"));
+        assert_eq!(name_at(&scene, i64::MAX - 8, i64::MAX - 1).as_deref(), Some("edge"));
+    }
+}
@@ -0,0 +1,1054 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::num::IntErrorKind;
+use std::rc::Rc;
+
+// A tiny Lisp, grown out of the Cons/Nil List in custom_types
+// Everything is a Value, and lists are chains of Cons cells ending in Nil,
+// exactly like List, except both halves of a cell can be any Value
+// The cells sit behind Rcs so car and cdr can hand back parts of a list
+// without copying it
+
+// How deep eval (and the reader) may nest before giving up with an error
+// instead of overflowing the stack
+// A debug build uses about 1.5KB of stack per level, so this fits in a 1MB
+// thread with room to spare (1_000 didn't, it needed about 1.5MB)
+const MAX_DEPTH: usize = 500;
+
+type Builtin = fn(&[Value]) -> Result<Value, LispError>;
+
+#[derive(Clone)]
+pub enum Value {
+    // Nil: The empty list, which also ends every proper list
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Symbol(String),
+    // Cons: A pair of values, the car and the cdr
+    Cons(Rc<Value>, Rc<Value>),
+    Builtin(&'static str, Builtin),
+    Lambda(Rc<Lambda>),
+}
+
+// A user defined function, along with the environment it was defined in
+// That's an Rc cycle as soon as the lambda is stored back into the same
+// environment, which is what every define of a function does: the Env owns
+// the Lambda and the Lambda owns the Env, so neither is ever freed
+// There's no cycle collector, so every define of a lambda leaks its Env
+pub struct Lambda {
+    params: Vec<String>,
+    body: Vec<Value>,
+    env: Env,
+}
+
+// Everything that can go wrong while reading or evaluating
+// None of these panic, they all come back as an Err
+#[derive(Debug, PartialEq)]
+pub enum LispError {
+    // The input ended in the middle of an expression
+    UnexpectedEof,
+    // A ) with no ( to go with it, at this byte offset
+    UnexpectedCloseParen { position: usize },
+    // An integer literal that doesn't fit in an i64
+    IntegerTooLarge { position: usize, token: String },
+    UnboundSymbol(String),
+    TypeError { expected: &'static str, found: String },
+    ArityMismatch { name: String, expected: usize, found: usize },
+    NotCallable(String),
+    // A special form that doesn't look right, like (if) or (let x)
+    BadSyntax(String),
+    DivisionByZero,
+    Overflow,
+    RecursionLimit,
+}
+
+impl fmt::Display for LispError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LispError::UnexpectedEof => write!(f, "unexpected end of input"),
+            LispError::UnexpectedCloseParen { position } => {
+                write!(f, "unexpected ) at position {}", position)
+            },
+            LispError::IntegerTooLarge { position, token } => {
+                write!(f, "integer {} at position {} is too large", token, position)
+            },
+            LispError::UnboundSymbol(name) => write!(f, "unbound symbol: {}", name),
+            LispError::TypeError { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            },
+            LispError::ArityMismatch { name, expected, found } => write!(
+                f,
+                "{} takes {} argument(s) but was given {}",
+                name, expected, found
+            ),
+            LispError::NotCallable(value) => write!(f, "{} is not a function", value),
+            LispError::BadSyntax(form) => write!(f, "bad syntax: {}", form),
+            LispError::DivisionByZero => write!(f, "division by zero"),
+            LispError::Overflow => write!(f, "integer overflow"),
+            LispError::RecursionLimit => write!(f, "recursion limit of {} reached", MAX_DEPTH),
+        }
+    }
+}
+
+impl Error for LispError {}
+
+impl Value {
+    pub fn cons(car: Value, cdr: Value) -> Value {
+        Value::Cons(Rc::new(car), Rc::new(cdr))
+    }
+
+    pub fn symbol(name: &str) -> Value {
+        Value::Symbol(name.to_owned())
+    }
+
+    // Build a proper list out of the values, back to front like List::prepend
+    pub fn list(values: Vec<Value>) -> Value {
+        values
+            .into_iter()
+            .rev()
+            .fold(Value::Nil, |tail, head| Value::cons(head, tail))
+    }
+
+    // Collect the elements of a proper list
+    // Anything that doesn't end in Nil is a type error
+    pub fn to_vec(&self) -> Result<Vec<Value>, LispError> {
+        let mut values = Vec::new();
+        let mut next = self;
+        loop {
+            match next {
+                Value::Cons(car, cdr) => {
+                    values.push((**car).clone());
+                    next = cdr;
+                },
+                Value::Nil => return Ok(values),
+                other => {
+                    return Err(LispError::TypeError {
+                        expected: "a list",
+                        found: other.to_string(),
+                    })
+                },
+            }
+        }
+    }
+
+    // Only #f and () count as false, everything else is true
+    fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Bool(false) | Value::Nil)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "()"),
+            Value::Bool(true) => write!(f, "#t"),
+            Value::Bool(false) => write!(f, "#f"),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Symbol(name) => write!(f, "{}", name),
+            Value::Cons(car, cdr) => {
+                write!(f, "({}", car)?;
+                // Walk down the cdrs so long lists don't recurse
+                let mut rest = &**cdr;
+                loop {
+                    match rest {
+                        Value::Cons(car, cdr) => {
+                            write!(f, " {}", car)?;
+                            rest = cdr;
+                        },
+                        Value::Nil => break,
+                        // An improper list like (cons 1 2) prints as (1 . 2)
+                        other => {
+                            write!(f, " . {}", other)?;
+                            break;
+                        },
+                    }
+                }
+                write!(f, ")")
+            },
+            Value::Builtin(name, _) => write!(f, "#<builtin {}>", name),
+            Value::Lambda(_) => write!(f, "#<lambda>"),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Data compares by value, functions only equal themselves
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        // Walk down the cdrs side by side so long lists don't recurse
+        let (mut a, mut b) = (self, other);
+        loop {
+            return match (a, b) {
+                (Value::Cons(a_car, a_cdr), Value::Cons(b_car, b_cdr)) => {
+                    if a_car != b_car {
+                        return false;
+                    }
+                    a = a_cdr;
+                    b = b_cdr;
+                    continue;
+                },
+                (Value::Nil, Value::Nil) => true,
+                (Value::Bool(a), Value::Bool(b)) => a == b,
+                (Value::Int(a), Value::Int(b)) => a == b,
+                (Value::Symbol(a), Value::Symbol(b)) => a == b,
+                (Value::Builtin(a, _), Value::Builtin(b, _)) => a == b,
+                (Value::Lambda(a), Value::Lambda(b)) => Rc::ptr_eq(a, b),
+                _ => false,
+            };
+        }
+    }
+}
+
+thread_local! {
+    // Left behind in a cell whose cdr has been taken by drop, so taking it
+    // doesn't have to allocate a fresh Nil every time
+    static NIL: Rc<Value> = Rc::new(Value::Nil);
+}
+
+// The default drop would recurse once per cell down the cdrs, and overflow
+// the stack on a long enough list
+// Like Link's drop in custom_types, this unlinks the cells one at a time
+// instead, but only while nothing else holds on to the rest of the list
+impl Drop for Value {
+    fn drop(&mut self) {
+        let Value::Cons(_, cdr) = self else {
+            return;
+        };
+        if !matches!(**cdr, Value::Cons(..)) {
+            return;
+        }
+        let mut next = mem::replace(cdr, NIL.with(Rc::clone));
+        // A tail that's shared is dropped by whoever holds it last
+        while let Ok(mut cell) = Rc::try_unwrap(next) {
+            match &mut cell {
+                Value::Cons(_, cdr) => next = mem::replace(cdr, NIL.with(Rc::clone)),
+                _ => break,
+            }
+        }
+    }
+}
+
+// The reader turns text like (+ 1 (* 2 3)) into Values
+
+enum Token {
+    Open,
+    Close,
+    Quote,
+    Atom(String),
+}
+
+// Split the source into parens, quotes and atoms, remembering where each one started
+// A ; starts a comment that runs to the end of the line
+fn tokenize(src: &str) -> Vec<(usize, Token)> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '(' => tokens.push((position, Token::Open)),
+            ')' => tokens.push((position, Token::Close)),
+            '\'' => tokens.push((position, Token::Quote)),
+            ';' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            },
+            c if c.is_whitespace() => {},
+            _ => {
+                let mut atom = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '\'' || c == ';' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                tokens.push((position, Token::Atom(atom)));
+            },
+        }
+    }
+    tokens
+}
+
+struct Reader {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+}
+
+impl Reader {
+    fn read_expr(&mut self, depth: usize) -> Result<Value, LispError> {
+        if depth > MAX_DEPTH {
+            return Err(LispError::RecursionLimit);
+        }
+
+        let Some((position, token)) = self.tokens.get(self.next) else {
+            return Err(LispError::UnexpectedEof);
+        };
+        let position = *position;
+        self.next += 1;
+
+        match token {
+            Token::Open => {
+                let mut items = Vec::new();
+                loop {
+                    match self.tokens.get(self.next) {
+                        Some((_, Token::Close)) => {
+                            self.next += 1;
+                            return Ok(Value::list(items));
+                        },
+                        Some(_) => items.push(self.read_expr(depth + 1)?),
+                        None => return Err(LispError::UnexpectedEof),
+                    }
+                }
+            },
+            Token::Close => Err(LispError::UnexpectedCloseParen { position }),
+            // 'x is just shorthand for (quote x)
+            Token::Quote => {
+                let quoted = self.read_expr(depth + 1)?;
+                Ok(Value::list(vec![Value::symbol("quote"), quoted]))
+            },
+            Token::Atom(atom) => parse_atom(position, atom),
+        }
+    }
+}
+
+fn parse_atom(position: usize, atom: &str) -> Result<Value, LispError> {
+    match atom {
+        "#t" => return Ok(Value::Bool(true)),
+        "#f" => return Ok(Value::Bool(false)),
+        "nil" => return Ok(Value::Nil),
+        _ => {},
+    }
+
+    match atom.parse::<i64>() {
+        Ok(n) => Ok(Value::Int(n)),
+        Err(err) if matches!(err.kind(), IntErrorKind::PosOverflow | IntErrorKind::NegOverflow) => {
+            Err(LispError::IntegerTooLarge {
+                position,
+                token: atom.to_owned(),
+            })
+        },
+        // Anything that isn't a number is a symbol, including + and -
+        Err(_) => Ok(Value::Symbol(atom.to_owned())),
+    }
+}
+
+// Read every expression in the source
+pub fn read(src: &str) -> Result<Vec<Value>, LispError> {
+    let mut reader = Reader {
+        tokens: tokenize(src),
+        next: 0,
+    };
+    let mut exprs = Vec::new();
+    while reader.next < reader.tokens.len() {
+        exprs.push(reader.read_expr(0)?);
+    }
+    Ok(exprs)
+}
+
+// Environments map names to values
+// Each one points at the environment it was created in, so lookups walk
+// outwards until they find the name (that's lexical scoping)
+#[derive(Clone)]
+pub struct Env(Rc<RefCell<Frame>>);
+
+struct Frame {
+    vars: HashMap<String, Value>,
+    parent: Option<Env>,
+}
+
+impl Env {
+    // A fresh top level environment with every builtin defined
+    pub fn global() -> Env {
+        let env = Env(Rc::new(RefCell::new(Frame {
+            vars: HashMap::new(),
+            parent: None,
+        })));
+        for &(name, builtin) in BUILTINS {
+            env.define(name, Value::Builtin(name, builtin));
+        }
+        env
+    }
+
+    fn child(&self) -> Env {
+        Env(Rc::new(RefCell::new(Frame {
+            vars: HashMap::new(),
+            parent: Some(self.clone()),
+        })))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let mut env = self.clone();
+        loop {
+            let parent = {
+                let frame = env.0.borrow();
+                if let Some(value) = frame.vars.get(name) {
+                    return Some(value.clone());
+                }
+                frame.parent.clone()
+            };
+            env = parent?;
+        }
+    }
+
+    pub fn define(&self, name: &str, value: Value) {
+        self.0.borrow_mut().vars.insert(name.to_owned(), value);
+    }
+}
+
+// Evaluate one expression in env
+pub fn eval(expr: &Value, env: &Env) -> Result<Value, LispError> {
+    eval_at(expr, env, 0)
+}
+
+// Read and evaluate every expression in src, returning the last result
+pub fn eval_str(src: &str, env: &Env) -> Result<Value, LispError> {
+    let mut result = Value::Nil;
+    for expr in read(src)? {
+        result = eval(&expr, env)?;
+    }
+    Ok(result)
+}
+
+fn eval_at(expr: &Value, env: &Env, depth: usize) -> Result<Value, LispError> {
+    if depth > MAX_DEPTH {
+        return Err(LispError::RecursionLimit);
+    }
+
+    match expr {
+        Value::Symbol(name) => env
+            .get(name)
+            .ok_or_else(|| LispError::UnboundSymbol(name.clone())),
+        Value::Cons(head, tail) => {
+            let args = tail.to_vec()?;
+
+            // Special forms get their arguments unevaluated
+            if let Value::Symbol(name) = &**head {
+                match name.as_str() {
+                    "quote" => return eval_quote(expr, &args),
+                    "if" => return eval_if(expr, &args, env, depth),
+                    "define" => return eval_define(expr, &args, env, depth),
+                    "lambda" => return eval_lambda(expr, &args, env),
+                    "let" => return eval_let(expr, &args, env, depth),
+                    "begin" => return eval_body(&args, env, depth),
+                    _ => {},
+                }
+            }
+
+            // Anything else is a function call
+            let function = eval_at(head, env, depth + 1)?;
+            let mut values = Vec::with_capacity(args.len());
+            for arg in &args {
+                values.push(eval_at(arg, env, depth + 1)?);
+            }
+            apply(&function, &values, depth + 1)
+        },
+        // Numbers, booleans and () evaluate to themselves
+        _ => Ok(expr.clone()),
+    }
+}
+
+// Evaluate a sequence of expressions and return the last one
+fn eval_body(body: &[Value], env: &Env, depth: usize) -> Result<Value, LispError> {
+    let mut result = Value::Nil;
+    for expr in body {
+        result = eval_at(expr, env, depth + 1)?;
+    }
+    Ok(result)
+}
+
+// (quote x)
+fn eval_quote(form: &Value, args: &[Value]) -> Result<Value, LispError> {
+    match args {
+        [quoted] => Ok(quoted.clone()),
+        _ => Err(LispError::BadSyntax(form.to_string())),
+    }
+}
+
+// (if condition then else), the else is optional
+fn eval_if(form: &Value, args: &[Value], env: &Env, depth: usize) -> Result<Value, LispError> {
+    let (condition, then, otherwise) = match args {
+        [condition, then] => (condition, then, None),
+        [condition, then, otherwise] => (condition, then, Some(otherwise)),
+        _ => return Err(LispError::BadSyntax(form.to_string())),
+    };
+
+    if eval_at(condition, env, depth + 1)?.is_truthy() {
+        eval_at(then, env, depth + 1)
+    } else if let Some(otherwise) = otherwise {
+        eval_at(otherwise, env, depth + 1)
+    } else {
+        Ok(Value::Nil)
+    }
+}
+
+// (define name value) or (define (name params...) body...)
+fn eval_define(form: &Value, args: &[Value], env: &Env, depth: usize) -> Result<Value, LispError> {
+    match args {
+        [Value::Symbol(name), value] => {
+            let value = eval_at(value, env, depth + 1)?;
+            env.define(name, value);
+            Ok(Value::Symbol(name.clone()))
+        },
+        // The second form is sugar for (define name (lambda (params...) body...))
+        [Value::Cons(name, params), body @ ..] if !body.is_empty() => {
+            let Value::Symbol(name) = &**name else {
+                return Err(LispError::BadSyntax(form.to_string()));
+            };
+            // This closes the Env -> Lambda -> Env cycle, see Lambda
+            let lambda = make_lambda(form, params, body, env)?;
+            env.define(name, lambda);
+            Ok(Value::Symbol(name.clone()))
+        },
+        _ => Err(LispError::BadSyntax(form.to_string())),
+    }
+}
+
+// (lambda (params...) body...)
+fn eval_lambda(form: &Value, args: &[Value], env: &Env) -> Result<Value, LispError> {
+    match args {
+        [params, body @ ..] if !body.is_empty() => make_lambda(form, params, body, env),
+        _ => Err(LispError::BadSyntax(form.to_string())),
+    }
+}
+
+fn make_lambda(form: &Value, params: &Value, body: &[Value], env: &Env) -> Result<Value, LispError> {
+    let mut names = Vec::new();
+    for param in params.to_vec()? {
+        match &param {
+            Value::Symbol(name) => names.push(name.clone()),
+            _ => return Err(LispError::BadSyntax(form.to_string())),
+        }
+    }
+
+    Ok(Value::Lambda(Rc::new(Lambda {
+        params: names,
+        body: body.to_vec(),
+        env: env.clone(),
+    })))
+}
+
+// (let ((name value) ...) body...)
+// Every value is evaluated in the outer environment, then the body runs in a
+// new one with the names bound
+fn eval_let(form: &Value, args: &[Value], env: &Env, depth: usize) -> Result<Value, LispError> {
+    let [bindings, body @ ..] = args else {
+        return Err(LispError::BadSyntax(form.to_string()));
+    };
+    if body.is_empty() {
+        return Err(LispError::BadSyntax(form.to_string()));
+    }
+
+    let scope = env.child();
+    for binding in bindings.to_vec()? {
+        match binding.to_vec()?.as_slice() {
+            [Value::Symbol(name), value] => {
+                let value = eval_at(value, env, depth + 1)?;
+                scope.define(name, value);
+            },
+            _ => return Err(LispError::BadSyntax(form.to_string())),
+        }
+    }
+    eval_body(body, &scope, depth)
+}
+
+fn apply(function: &Value, args: &[Value], depth: usize) -> Result<Value, LispError> {
+    match function {
+        Value::Builtin(_, builtin) => builtin(args),
+        Value::Lambda(lambda) => {
+            if lambda.params.len() != args.len() {
+                return Err(LispError::ArityMismatch {
+                    name: function.to_string(),
+                    expected: lambda.params.len(),
+                    found: args.len(),
+                });
+            }
+
+            let scope = lambda.env.child();
+            for (param, arg) in lambda.params.iter().zip(args) {
+                scope.define(param, arg.clone());
+            }
+            eval_body(&lambda.body, &scope, depth)
+        },
+        other => Err(LispError::NotCallable(other.to_string())),
+    }
+}
+
+// Builtins
+
+const BUILTINS: &[(&str, Builtin)] = &[
+    ("+", add),
+    ("-", subtract),
+    ("*", multiply),
+    ("/", divide),
+    ("=", equal),
+    ("<", less),
+    (">", greater),
+    ("<=", less_or_equal),
+    (">=", greater_or_equal),
+    ("cons", cons),
+    ("car", car),
+    ("cdr", cdr),
+    ("list", list),
+    ("null?", is_null),
+];
+
+fn int(value: &Value) -> Result<i64, LispError> {
+    match value {
+        Value::Int(n) => Ok(*n),
+        other => Err(LispError::TypeError {
+            expected: "an integer",
+            found: other.to_string(),
+        }),
+    }
+}
+
+fn ints(args: &[Value]) -> Result<Vec<i64>, LispError> {
+    args.iter().map(int).collect()
+}
+
+fn check_arity(name: &str, args: &[Value], expected: usize) -> Result<(), LispError> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(LispError::ArityMismatch {
+            name: name.to_owned(),
+            expected,
+            found: args.len(),
+        })
+    }
+}
+
+fn add(args: &[Value]) -> Result<Value, LispError> {
+    let mut total: i64 = 0;
+    for n in ints(args)? {
+        total = total.checked_add(n).ok_or(LispError::Overflow)?;
+    }
+    Ok(Value::Int(total))
+}
+
+fn multiply(args: &[Value]) -> Result<Value, LispError> {
+    let mut total: i64 = 1;
+    for n in ints(args)? {
+        total = total.checked_mul(n).ok_or(LispError::Overflow)?;
+    }
+    Ok(Value::Int(total))
+}
+
+// (- x) negates, (- x y z) subtracts y and z from x
+fn subtract(args: &[Value]) -> Result<Value, LispError> {
+    match ints(args)?.as_slice() {
+        [] => Err(LispError::ArityMismatch {
+            name: "-".to_owned(),
+            expected: 1,
+            found: 0,
+        }),
+        [n] => n.checked_neg().map(Value::Int).ok_or(LispError::Overflow),
+        [first, rest @ ..] => {
+            let mut total = *first;
+            for n in rest {
+                total = total.checked_sub(*n).ok_or(LispError::Overflow)?;
+            }
+            Ok(Value::Int(total))
+        },
+    }
+}
+
+// Integer division, (/ x y z) is x / y / z
+fn divide(args: &[Value]) -> Result<Value, LispError> {
+    match ints(args)?.as_slice() {
+        [first, rest @ ..] if !rest.is_empty() => {
+            let mut total = *first;
+            for n in rest {
+                if *n == 0 {
+                    return Err(LispError::DivisionByZero);
+                }
+                total = total.checked_div(*n).ok_or(LispError::Overflow)?;
+            }
+            Ok(Value::Int(total))
+        },
+        _ => Err(LispError::ArityMismatch {
+            name: "/".to_owned(),
+            expected: 2,
+            found: args.len(),
+        }),
+    }
+}
+
+// (< a b c) is true if every neighbouring pair is in order
+fn compare(name: &str, args: &[Value], ordered: fn(i64, i64) -> bool) -> Result<Value, LispError> {
+    let numbers = ints(args)?;
+    if numbers.len() < 2 {
+        return Err(LispError::ArityMismatch {
+            name: name.to_owned(),
+            expected: 2,
+            found: numbers.len(),
+        });
+    }
+    Ok(Value::Bool(numbers.windows(2).all(|pair| ordered(pair[0], pair[1]))))
+}
+
+fn equal(args: &[Value]) -> Result<Value, LispError> {
+    compare("=", args, |a, b| a == b)
+}
+
+fn less(args: &[Value]) -> Result<Value, LispError> {
+    compare("<", args, |a, b| a < b)
+}
+
+fn greater(args: &[Value]) -> Result<Value, LispError> {
+    compare(">", args, |a, b| a > b)
+}
+
+fn less_or_equal(args: &[Value]) -> Result<Value, LispError> {
+    compare("<=", args, |a, b| a <= b)
+}
+
+fn greater_or_equal(args: &[Value]) -> Result<Value, LispError> {
+    compare(">=", args, |a, b| a >= b)
+}
+
+fn cons(args: &[Value]) -> Result<Value, LispError> {
+    check_arity("cons", args, 2)?;
+    Ok(Value::cons(args[0].clone(), args[1].clone()))
+}
+
+fn car(args: &[Value]) -> Result<Value, LispError> {
+    check_arity("car", args, 1)?;
+    match &args[0] {
+        Value::Cons(car, _) => Ok((**car).clone()),
+        other => Err(LispError::TypeError {
+            expected: "a pair",
+            found: other.to_string(),
+        }),
+    }
+}
+
+fn cdr(args: &[Value]) -> Result<Value, LispError> {
+    check_arity("cdr", args, 1)?;
+    match &args[0] {
+        Value::Cons(_, cdr) => Ok((**cdr).clone()),
+        other => Err(LispError::TypeError {
+            expected: "a pair",
+            found: other.to_string(),
+        }),
+    }
+}
+
+fn list(args: &[Value]) -> Result<Value, LispError> {
+    Ok(Value::list(args.to_vec()))
+}
+
+fn is_null(args: &[Value]) -> Result<Value, LispError> {
+    check_arity("null?", args, 1)?;
+    Ok(Value::Bool(matches!(args[0], Value::Nil)))
+}
+
+// Read-eval-print loop on stdin
+// Input is collected until the parens balance, so expressions can span lines
+pub fn repl() {
+    let env = Env::global();
+    let stdin = io::stdin();
+    let mut pending = String::new();
+
+    loop {
+        print!("{}", if pending.is_empty() { "lisp> " } else { "...   " });
+        // The prompt has no newline so it has to be flushed by hand
+        // If stdout is gone there is nobody left to talk to anyway
+        if io::stdout().flush().is_err() {
+            return;
+        }
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            // Ctrl-D
+            Ok(0) => {
+                println!();
+                return;
+            },
+            Ok(_) => pending.push_str(&line),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return;
+            },
+        }
+
+        match read(&pending) {
+            // Not finished yet, keep reading lines
+            Err(LispError::UnexpectedEof) => continue,
+            Err(err) => println!("error: {}", err),
+            Ok(exprs) => {
+                for expr in exprs {
+                    match eval(&expr, &env) {
+                        Ok(value) => println!("{}", value),
+                        Err(err) => {
+                            println!("error: {}", err);
+                            break;
+                        },
+                    }
+                }
+            },
+        }
+        pending.clear();
+    }
+}
+
+pub fn run() {
+    let env = Env::global();
+
+    // The reader turns text into cons cells
+    let exprs = read("(+ 1 (* 2 3))").unwrap();
+    println!("read: {}", exprs[0]);
+
+    let programs = [
+        "(+ 1 (* 2 3))",
+        "(define x 10) (* x x)",
+        "(define (square n) (* n n)) (square 12)",
+        "(define (fact n) (if (<= n 1) 1 (* n (fact (- n 1))))) (fact 20)",
+        "(let ((a 3) (b 4)) (+ (square a) (square b)))",
+        "(cons 1 (cons 2 (cons 3 nil)))",
+        "(car (cdr '(1 2 3)))",
+        "(cons 1 2)",
+        // Closures remember the environment they were made in
+        "(define (adder n) (lambda (x) (+ x n))) (define add5 (adder 5)) (add5 37)",
+        "(define (map f xs) (if (null? xs) nil (cons (f (car xs)) (map f (cdr xs))))) (map square (list 1 2 3 4))",
+    ];
+    for program in programs {
+        println!("{} => {}", program, eval_str(program, &env).unwrap());
+    }
+
+    // Mistakes come back as errors instead of panics
+    let mistakes = ["(+ 1", ")", "(undefined 1)", "(car 1)", "(/ 1 0)", "(fact 21)", "(square)", "(if)"];
+    for program in mistakes {
+        println!("{} => error: {}", program, eval_str(program, &env).unwrap_err());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // The depth limit has to hold on a small stack, not just the 8MB main thread
+    const SMALL_STACK: usize = 1024 * 1024;
+
+    fn eval_ok(program: &str) -> Value {
+        eval_str(program, &Env::global()).unwrap()
+    }
+
+    fn eval_err(program: &str) -> LispError {
+        eval_str(program, &Env::global()).unwrap_err()
+    }
+
+    // Values hold Rcs and can't leave the thread, so this hands back the printed result
+    fn on_small_stack(program: String) -> Result<String, LispError> {
+        thread::Builder::new()
+            .stack_size(SMALL_STACK)
+            .spawn(move || eval_str(&program, &Env::global()).map(|value| value.to_string()))
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    fn numbers(count: usize) -> String {
+        let items: Vec<String> = (0..count).map(|n| n.to_string()).collect();
+        format!("'({})", items.join(" "))
+    }
+
+    #[test]
+    fn long_lists_drop_without_overflowing() {
+        let env = Env::global();
+        eval_str(&format!("(define xs {})", numbers(1_000_000)), &env).unwrap();
+        drop(env);
+    }
+
+    #[test]
+    fn long_lists_compare_without_overflowing() {
+        let env = Env::global();
+        let a = eval_str(&numbers(1_000_000), &env).unwrap();
+        let b = eval_str(&numbers(1_000_000), &env).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, eval_str(&numbers(999_999), &env).unwrap());
+    }
+
+    #[test]
+    fn shared_tails_survive_dropping_one_list() {
+        let env = Env::global();
+        eval_str(&format!("(define xs {})", numbers(1_000)), &env).unwrap();
+        let tail = eval_str("(cdr xs)", &env).unwrap();
+        eval_str("(define xs 0)", &env).unwrap();
+        assert_eq!(tail.to_vec().unwrap().len(), 999);
+    }
+
+    #[test]
+    fn reading() {
+        assert_eq!(
+            read("(+ 1 (* 2 3))").unwrap(),
+            vec![Value::list(vec![
+                Value::symbol("+"),
+                Value::Int(1),
+                Value::list(vec![Value::symbol("*"), Value::Int(2), Value::Int(3)]),
+            ])]
+        );
+        assert_eq!(read("'x").unwrap()[0].to_string(), "(quote x)");
+        assert_eq!(read("1 2").unwrap(), vec![Value::Int(1), Value::Int(2)]);
+    }
+
+    #[test]
+    fn reader_errors() {
+        assert_eq!(eval_err("(+ 1"), LispError::UnexpectedEof);
+        assert_eq!(eval_err("(1))"), LispError::UnexpectedCloseParen { position: 3 });
+        assert_eq!(eval_err(")"), LispError::UnexpectedCloseParen { position: 0 });
+        assert_eq!(
+            eval_err("99999999999999999999"),
+            LispError::IntegerTooLarge { position: 0, token: "99999999999999999999".to_owned() }
+        );
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(eval_ok("'(1 2 3)").to_string(), "(1 2 3)");
+        assert_eq!(eval_ok("(quote undefined)"), Value::symbol("undefined"));
+        assert_eq!(eval_err("(quote)"), LispError::BadSyntax("(quote)".to_owned()));
+        assert_eq!(eval_err("(quote 1 2)"), LispError::BadSyntax("(quote 1 2)".to_owned()));
+    }
+
+    #[test]
+    fn if_only_evaluates_one_branch() {
+        assert_eq!(eval_ok("(if #t 1 undefined)"), Value::Int(1));
+        assert_eq!(eval_ok("(if #f undefined 2)"), Value::Int(2));
+        assert_eq!(eval_ok("(if #f 1)"), Value::Nil);
+        assert_eq!(eval_err("(if)"), LispError::BadSyntax("(if)".to_owned()));
+        assert_eq!(eval_err("(if #t 1 2 3)"), LispError::BadSyntax("(if #t 1 2 3)".to_owned()));
+    }
+
+    #[test]
+    fn define() {
+        let env = Env::global();
+        assert_eq!(eval_str("(define x 10)", &env), Ok(Value::symbol("x")));
+        assert_eq!(eval_str("(* x x)", &env), Ok(Value::Int(100)));
+        eval_str("(define (fact n) (if (<= n 1) 1 (* n (fact (- n 1)))))", &env).unwrap();
+        assert_eq!(eval_str("(fact 20)", &env), Ok(Value::Int(2_432_902_008_176_640_000)));
+        assert_eq!(eval_str("(fact 21)", &env), Err(LispError::Overflow));
+        assert_eq!(eval_err("(define)"), LispError::BadSyntax("(define)".to_owned()));
+        assert_eq!(eval_err("(define (f))"), LispError::BadSyntax("(define (f))".to_owned()));
+        assert_eq!(eval_err("(define (1) 2)"), LispError::BadSyntax("(define (1) 2)".to_owned()));
+    }
+
+    #[test]
+    fn lambdas_close_over_their_environment() {
+        let env = Env::global();
+        eval_str("(define (adder n) (lambda (x) (+ x n))) (define add5 (adder 5))", &env).unwrap();
+        assert_eq!(eval_str("(add5 37)", &env), Ok(Value::Int(42)));
+        assert_eq!(eval_str("((lambda (a b) (- a b)) 5 3)", &env), Ok(Value::Int(2)));
+        assert_eq!(eval_err("(lambda (x))"), LispError::BadSyntax("(lambda (x))".to_owned()));
+        assert_eq!(eval_err("(lambda (1) 1)"), LispError::BadSyntax("(lambda (1) 1)".to_owned()));
+    }
+
+    #[test]
+    fn let_shadows_without_touching_the_outer_scope() {
+        let env = Env::global();
+        eval_str("(define x 10)", &env).unwrap();
+        assert_eq!(eval_str("(let ((x 1)) x)", &env), Ok(Value::Int(1)));
+        assert_eq!(eval_str("x", &env), Ok(Value::Int(10)));
+        // Every value is evaluated outside, so b sees the outer x
+        assert_eq!(eval_str("(let ((x 1) (b x)) b)", &env), Ok(Value::Int(10)));
+        assert_eq!(eval_err("(let x)"), LispError::BadSyntax("(let x)".to_owned()));
+        assert_eq!(eval_err("(let ((x 1)))"), LispError::BadSyntax("(let ((x 1)))".to_owned()));
+        assert_eq!(eval_err("(let ((x)) x)"), LispError::BadSyntax("(let ((x)) x)".to_owned()));
+    }
+
+    #[test]
+    fn begin_returns_the_last_value() {
+        assert_eq!(eval_ok("(begin (define x 1) (+ x 1))"), Value::Int(2));
+        assert_eq!(eval_ok("(begin)"), Value::Nil);
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(eval_ok("(cons 1 (cons 2 (cons 3 nil)))").to_string(), "(1 2 3)");
+        assert_eq!(eval_ok("(cons 1 2)").to_string(), "(1 . 2)");
+        assert_eq!(eval_ok("(cdr '(1 2 3))").to_string(), "(2 3)");
+        assert_eq!(eval_ok("(car (cdr '(1 2 3)))"), Value::Int(2));
+        assert_eq!(eval_ok("(null? (list))"), Value::Bool(true));
+    }
+
+    #[test]
+    fn arity_errors() {
+        let env = Env::global();
+        eval_str("(define (square n) (* n n))", &env).unwrap();
+        assert_eq!(
+            eval_str("(square)", &env),
+            Err(LispError::ArityMismatch { name: "#<lambda>".to_owned(), expected: 1, found: 0 })
+        );
+        assert_eq!(
+            eval_str("(square 1 2)", &env),
+            Err(LispError::ArityMismatch { name: "#<lambda>".to_owned(), expected: 1, found: 2 })
+        );
+        assert_eq!(eval_err("(-)"), LispError::ArityMismatch { name: "-".to_owned(), expected: 1, found: 0 });
+        assert_eq!(eval_err("(/ 1)"), LispError::ArityMismatch { name: "/".to_owned(), expected: 2, found: 1 });
+        assert_eq!(eval_err("(< 1)"), LispError::ArityMismatch { name: "<".to_owned(), expected: 2, found: 1 });
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(eval_err("(car 1)"), LispError::TypeError { expected: "a pair", found: "1".to_owned() });
+        assert_eq!(
+            eval_err("(+ 1 #t)"),
+            LispError::TypeError { expected: "an integer", found: "#t".to_owned() }
+        );
+        assert_eq!(eval_err("(1 2)"), LispError::NotCallable("1".to_owned()));
+        assert_eq!(eval_err("(undefined 1)"), LispError::UnboundSymbol("undefined".to_owned()));
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert_eq!(eval_err("(/ 1 0)"), LispError::DivisionByZero);
+        assert_eq!(eval_err("(- (- 0 9223372036854775807 1))"), LispError::Overflow);
+        assert_eq!(eval_err("(* 9223372036854775807 2)"), LispError::Overflow);
+    }
+
+    #[test]
+    fn runaway_recursion_is_an_error_on_a_small_stack() {
+        let down = "(define (down n) (if (= n 0) 0 (+ 1 (down (- n 1)))))";
+        assert_eq!(on_small_stack("(define (loop n) (loop n)) (loop 1)".to_owned()), Err(LispError::RecursionLimit));
+        assert_eq!(on_small_stack(format!("{} (down 100000)", down)), Err(LispError::RecursionLimit));
+        // Shallow recursion still works
+        assert_eq!(on_small_stack(format!("{} (down 50)", down)), Ok("50".to_owned()));
+    }
+
+    #[test]
+    fn deep_nesting_is_a_reader_error_on_a_small_stack() {
+        let nested = format!("{}1{}", "(".repeat(5_000), ")".repeat(5_000));
+        assert_eq!(on_small_stack(nested), Err(LispError::RecursionLimit));
+    }
+
+    #[test]
+    fn defining_a_function_leaks_its_environment() {
+        let env = Env::global();
+        let frame = Rc::downgrade(&env.0);
+        eval_str("(define x 1)", &env).unwrap();
+        drop(env);
+        assert!(frame.upgrade().is_none());
+
+        // The lambda holds the Env that holds the lambda, so it outlives us
+        let env = Env::global();
+        let frame = Rc::downgrade(&env.0);
+        eval_str("(define (f) 1)", &env).unwrap();
+        drop(env);
+        assert!(frame.upgrade().is_some());
+    }
+}
@@ -1,3 +1,264 @@
+use std::env;
+use std::fmt;
+use std::process;
+use std::time::Instant;
+
+// Every chapter lives in its own module and exposes a run() that does
+// what its old main() used to do
+mod arena_list;
+mod computational_geometry;
+mod custom_types;
+mod editor;
+mod event_log;
+mod events;
+mod flow_of_control;
+mod geometry;
+mod hit_test;
+mod lisp;
+mod packing;
+mod persistent_list;
+mod pipeline;
+mod print;
+mod quadtree;
+mod random;
+mod raster;
+mod replay;
+mod shapes;
+mod svg;
+mod transform;
+mod tree;
+mod types;
+mod var_bindings;
+
+// A chapter is just a name we can type on the command line
+// paired with the function that runs it
+struct Chapter {
+    name: &'static str,
+    run: fn(),
+}
+
+// Listed in the order Rust By Example goes through them
+const CHAPTERS: &[Chapter] = &[
+    Chapter { name: "print", run: print::run },
+    Chapter { name: "types", run: types::run },
+    Chapter { name: "var_bindings", run: var_bindings::run },
+    Chapter { name: "custom_types", run: custom_types::run },
+    Chapter { name: "flow_of_control", run: flow_of_control::run },
+    Chapter { name: "persistent_list", run: persistent_list::run },
+    Chapter { name: "arena_list", run: arena_list::run },
+    Chapter { name: "lisp", run: lisp::run },
+    Chapter { name: "tree", run: tree::run },
+    Chapter { name: "geometry", run: geometry::run },
+    Chapter { name: "shapes", run: shapes::run },
+    Chapter { name: "computational_geometry", run: computational_geometry::run },
+    Chapter { name: "transform", run: transform::run },
+    Chapter { name: "quadtree", run: quadtree::run },
+    Chapter { name: "packing", run: packing::run },
+    Chapter { name: "svg", run: svg::run },
+    Chapter { name: "raster", run: raster::run },
+    Chapter { name: "events", run: events::run },
+    Chapter { name: "pipeline", run: pipeline::run },
+    Chapter { name: "event_log", run: event_log::run },
+    Chapter { name: "replay", run: replay::run },
+    Chapter { name: "editor", run: editor::run },
+    Chapter { name: "hit_test", run: hit_test::run },
+];
+
+fn find_chapter(name: &str) -> Option<&'static Chapter> {
+    CHAPTERS.iter().find(|chapter| chapter.name == name)
+}
+
+// Everything rbe knows how to do
+enum Command {
+    List,
+    Run(Vec<&'static Chapter>),
+    Repl,
+    Help,
+}
+
+// Everything that can go wrong while reading the command line
+#[derive(Debug)]
+enum CliError {
+    MissingCommand,
+    UnknownCommand(String),
+    UnknownChapter(String),
+    NothingToRun,
+    AllWithNames,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::MissingCommand => write!(f, "no command given"),
+            CliError::UnknownCommand(command) => write!(f, "unknown command \"{}\"", command),
+            CliError::UnknownChapter(name) => {
+                let names: Vec<&str> = CHAPTERS.iter().map(|chapter| chapter.name).collect();
+                write!(
+                    f,
+                    "unknown chapter \"{}\" (available chapters: {})",
+                    name,
+                    names.join(", ")
+                )
+            },
+            CliError::NothingToRun => write!(f, "\"run\" needs a chapter name or --all"),
+            CliError::AllWithNames => write!(f, "\"run --all\" doesn't take chapter names"),
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let Some(command) = args.first() else {
+        return Err(CliError::MissingCommand);
+    };
+
+    match command.as_str() {
+        "list" => Ok(Command::List),
+        "repl" => Ok(Command::Repl),
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "run" => {
+            let rest = &args[1..];
+
+            // --all means every chapter, so naming some as well is a mistake
+            // and rejecting it beats quietly ignoring the names
+            if rest.iter().any(|arg| arg == "--all") {
+                if rest.len() > 1 {
+                    return Err(CliError::AllWithNames);
+                }
+                return Ok(Command::Run(CHAPTERS.iter().collect()));
+            }
+
+            if rest.is_empty() {
+                return Err(CliError::NothingToRun);
+            }
+
+            // Check every name before running anything so a typo at the
+            // end doesn't show up after the other chapters already ran
+            let mut chapters = Vec::new();
+            for name in rest {
+                match find_chapter(name) {
+                    Some(chapter) => chapters.push(chapter),
+                    None => return Err(CliError::UnknownChapter(name.clone())),
+                }
+            }
+            Ok(Command::Run(chapters))
+        },
+        other => Err(CliError::UnknownCommand(other.to_owned())),
+    }
+}
+
+fn print_usage() {
+    println!("Usage:");
+    println!("    rbe list                  List every chapter");
+    println!("    rbe run <chapter>...      Run one or more chapters");
+    println!("    rbe run --all             Run every chapter in order");
+    println!("    rbe repl                  Start the Lisp REPL");
+}
+
+fn run_chapters(chapters: &[&Chapter]) {
+    for chapter in chapters {
+        println!("==> {}", chapter.name);
+
+        let start = Instant::now();
+        (chapter.run)();
+        let elapsed = start.elapsed();
+
+        println!("<== {} finished in {:.2?}", chapter.name, elapsed);
+        println!();
+    }
+}
+
 fn main() {
-    
-}
\ No newline at end of file
+    // The first argument is the path to the binary itself so skip it
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match parse_args(&args) {
+        Ok(Command::List) => {
+            for chapter in CHAPTERS {
+                println!("{}", chapter.name);
+            }
+        },
+        Ok(Command::Run(chapters)) => run_chapters(&chapters),
+        Ok(Command::Repl) => lisp::repl(),
+        Ok(Command::Help) => print_usage(),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            eprintln!();
+            print_usage();
+            process::exit(2);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    fn names(command: Result<Command, CliError>) -> Vec<&'static str> {
+        match command {
+            Ok(Command::Run(chapters)) => chapters.iter().map(|chapter| chapter.name).collect(),
+            Err(err) => panic!("expected chapters to run, got error: {}", err),
+            Ok(_) => panic!("expected chapters to run"),
+        }
+    }
+
+    #[test]
+    fn simple_commands() {
+        assert!(matches!(parse(&["list"]), Ok(Command::List)));
+        assert!(matches!(parse(&["repl"]), Ok(Command::Repl)));
+        for help in ["help", "--help", "-h"] {
+            assert!(matches!(parse(&[help]), Ok(Command::Help)));
+        }
+    }
+
+    #[test]
+    fn missing_arguments() {
+        assert!(matches!(parse(&[]), Err(CliError::MissingCommand)));
+        assert!(matches!(parse(&["run"]), Err(CliError::NothingToRun)));
+    }
+
+    #[test]
+    fn unknown_command() {
+        assert!(matches!(parse(&["walk"]), Err(CliError::UnknownCommand(command)) if command == "walk"));
+    }
+
+    #[test]
+    fn named_chapters_run_in_the_order_given() {
+        assert_eq!(names(parse(&["run", "types"])), ["types"]);
+        assert_eq!(names(parse(&["run", "tree", "print", "tree"])), ["tree", "print", "tree"]);
+    }
+
+    // A typo anywhere fails the whole command, even after good names
+    #[test]
+    fn unknown_chapter() {
+        let typo = parse(&["run", "nosuchchapter"]);
+        assert!(matches!(typo, Err(CliError::UnknownChapter(name)) if name == "nosuchchapter"));
+        assert!(matches!(parse(&["run", "print", "typse"]), Err(CliError::UnknownChapter(name)) if name == "typse"));
+        // Chapter names are exact
+        assert!(matches!(parse(&["run", "Print"]), Err(CliError::UnknownChapter(_))));
+    }
+
+    #[test]
+    fn all_runs_every_chapter_in_order() {
+        let every: Vec<&str> = CHAPTERS.iter().map(|chapter| chapter.name).collect();
+        assert_eq!(names(parse(&["run", "--all"])), every);
+    }
+
+    #[test]
+    fn all_doesnt_mix_with_names() {
+        assert!(matches!(parse(&["run", "nosuchchapter", "--all"]), Err(CliError::AllWithNames)));
+        assert!(matches!(parse(&["run", "--all", "print"]), Err(CliError::AllWithNames)));
+        assert!(matches!(parse(&["run", "--all", "--all"]), Err(CliError::AllWithNames)));
+    }
+
+    #[test]
+    fn chapter_names_are_unique() {
+        for (index, chapter) in CHAPTERS.iter().enumerate() {
+            assert!(CHAPTERS[..index].iter().all(|earlier| earlier.name != chapter.name), "{}", chapter.name);
+        }
+    }
+}
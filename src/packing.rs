@@ -0,0 +1,430 @@
+#![allow(dead_code)] // Allow unused code
+
+use crate::geometry::{Point, Rectangle};
+use crate::random::XorShift;
+
+// Rectangle packing: fit a list of sizes into a fixed container without
+// any of them overlapping, for things like sprite sheets and dashboard tiles
+// Items are never rotated, and y grows downward like everywhere else, so
+// items pile up from the top of the container
+// A size with a negative, infinite or NaN side never fits, and neither does
+// anything in a container like that
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+// The two ways we know how to pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packer {
+    // Keep track of the bottom edge of everything placed so far (the
+    // skyline) and drop each item wherever it ends up highest
+    // Good when items have similar heights
+    Skyline,
+    // Keep a list of free rectangles, put each item in the corner of the
+    // one it fits best and cut what's left into two new free rectangles
+    Guillotine,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packing {
+    // Where each input ended up, in the same order as the input
+    // None for the ones that didn't fit
+    pub placed: Vec<Option<Rectangle>>,
+    // Indexes of the inputs that didn't fit
+    pub unplaced: Vec<usize>,
+    // Container area that nothing was placed on
+    pub wasted_area: f32,
+}
+
+impl Size {
+    pub fn new(width: f32, height: f32) -> Size {
+        Size { width, height }
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    // Both sides finite and not negative
+    // Anything else would turn into a rectangle sticking out to the left of
+    // or above where it was placed
+    fn is_usable(&self) -> bool {
+        self.width.is_finite() && self.height.is_finite() && self.width >= 0.0 && self.height >= 0.0
+    }
+}
+
+pub fn pack(container: Size, sizes: &[Size], packer: Packer) -> Packing {
+    if !container.is_usable() {
+        return Packing {
+            placed: vec![None; sizes.len()],
+            unplaced: (0..sizes.len()).collect(),
+            wasted_area: 0.0,
+        };
+    }
+
+    // Big items are the hardest to fit, so place the tallest (then widest)
+    // ones first while there is still plenty of room
+    let mut order: Vec<usize> = (0..sizes.len()).filter(|&index| sizes[index].is_usable()).collect();
+    order.sort_by(|&a, &b| {
+        sizes[b]
+            .height
+            .total_cmp(&sizes[a].height)
+            .then(sizes[b].width.total_cmp(&sizes[a].width))
+    });
+
+    let mut placed = vec![None; sizes.len()];
+    match packer {
+        Packer::Skyline => {
+            let mut skyline = Skyline::new(container);
+            for index in order {
+                placed[index] = skyline.place(sizes[index]);
+            }
+        },
+        Packer::Guillotine => {
+            let mut guillotine = Guillotine::new(container);
+            for index in order {
+                placed[index] = guillotine.place(sizes[index]);
+            }
+        },
+    }
+
+    let unplaced = (0..sizes.len()).filter(|&index| placed[index].is_none()).collect();
+    let used: f32 = placed.iter().flatten().map(|rectangle| rectangle.area()).sum();
+    Packing {
+        placed,
+        unplaced,
+        wasted_area: container.area() - used,
+    }
+}
+
+// One flat stretch of the skyline: from x to x + width, everything above y is taken
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x: f32,
+    y: f32,
+    width: f32,
+}
+
+struct Skyline {
+    container: Size,
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    fn new(container: Size) -> Skyline {
+        Skyline {
+            container,
+            segments: vec![Segment {
+                x: 0.0,
+                y: 0.0,
+                width: container.width,
+            }],
+        }
+    }
+
+    // Where an item of width would sit if its left edge went at segment
+    // index: on top of the lowest point of every segment it spans
+    fn resting_y(&self, index: usize, width: f32) -> Option<f32> {
+        let x = self.segments[index].x;
+        if x + width > self.container.width {
+            return None;
+        }
+
+        let mut y: f32 = 0.0;
+        for segment in &self.segments[index..] {
+            if segment.x >= x + width {
+                break;
+            }
+            y = y.max(segment.y);
+        }
+        Some(y)
+    }
+
+    fn place(&mut self, size: Size) -> Option<Rectangle> {
+        // Try the item at the start of every segment and keep the spot
+        // where its bottom ends up highest, leftmost on ties
+        let mut best: Option<(f32, f32)> = None;
+        for index in 0..self.segments.len() {
+            let Some(y) = self.resting_y(index, size.width) else {
+                continue;
+            };
+            if y + size.height > self.container.height {
+                continue;
+            }
+            let x = self.segments[index].x;
+            if best.is_none_or(|(_, best_y)| y < best_y) {
+                best = Some((x, y));
+            }
+        }
+
+        let (x, y) = best?;
+        self.raise(x, y + size.height, size.width);
+        Some(Rectangle::from_size(Point::new(x, y), size.width, size.height))
+    }
+
+    // Everything from x to x + width is now taken down to y
+    fn raise(&mut self, x: f32, y: f32, width: f32) {
+        // A zero width item covers nothing, and a zero width segment would
+        // only get in the way of what comes next
+        if width == 0.0 {
+            return;
+        }
+        let end = x + width;
+        let mut segments = Vec::with_capacity(self.segments.len() + 2);
+        for segment in &self.segments {
+            let segment_end = segment.x + segment.width;
+            // Keep the bits of old segments either side of the new one
+            if segment.x < x {
+                let kept = segment_end.min(x) - segment.x;
+                segments.push(Segment { width: kept, ..*segment });
+            }
+            if segment.x < end && segment_end > end {
+                segments.push(Segment {
+                    x: end,
+                    y: segment.y,
+                    width: segment_end - end,
+                });
+            }
+            if segment.x >= end {
+                segments.push(*segment);
+            }
+        }
+        segments.push(Segment { x, y, width });
+        segments.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        // Neighbours at the same height are really one segment
+        self.segments.clear();
+        for segment in segments {
+            match self.segments.last_mut() {
+                Some(last) if last.y == segment.y => last.width += segment.width,
+                _ => self.segments.push(segment),
+            }
+        }
+    }
+}
+
+struct Guillotine {
+    free: Vec<Rectangle>,
+}
+
+impl Guillotine {
+    fn new(container: Size) -> Guillotine {
+        Guillotine {
+            free: vec![Rectangle::from_size(Point::ORIGIN, container.width, container.height)],
+        }
+    }
+
+    fn place(&mut self, size: Size) -> Option<Rectangle> {
+        // Best short side fit: the free rectangle that leaves the thinnest sliver
+        let (index, _) = self
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, free)| free.width() >= size.width && free.height() >= size.height)
+            .map(|(index, free)| {
+                let leftover = (free.width() - size.width).min(free.height() - size.height);
+                (index, leftover)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let free = self.free.swap_remove(index);
+        let placed = Rectangle::from_size(free.top_left(), size.width, size.height);
+
+        // Cut what's left of the free rectangle in two
+        // Cutting along the shorter leftover keeps the bigger piece as big as possible
+        let right_width = free.width() - size.width;
+        let below_height = free.height() - size.height;
+        let (right, below) = if right_width < below_height {
+            // Horizontal cut: the piece below runs the full width
+            (
+                Rectangle::from_size(Point::new(placed.right(), free.top()), right_width, size.height),
+                Rectangle::from_size(Point::new(free.left(), placed.bottom()), free.width(), below_height),
+            )
+        } else {
+            // Vertical cut: the piece to the right runs the full height
+            (
+                Rectangle::from_size(Point::new(placed.right(), free.top()), right_width, free.height()),
+                Rectangle::from_size(Point::new(free.left(), placed.bottom()), size.width, below_height),
+            )
+        };
+        for piece in [right, below] {
+            if piece.area() > 0.0 {
+                self.free.push(piece);
+            }
+        }
+
+        Some(placed)
+    }
+}
+
+pub fn run() {
+    let container = Size::new(10.0, 10.0);
+    let sizes = [
+        Size::new(5.0, 5.0),
+        Size::new(5.0, 5.0),
+        Size::new(10.0, 5.0),
+        Size::new(3.0, 3.0),
+    ];
+
+    // The two halves fill the container exactly, so the 3x3 can't fit
+    for packer in [Packer::Skyline, Packer::Guillotine] {
+        let packing = pack(container, &sizes, packer);
+        println!("{:?}:", packer);
+        for (size, placed) in sizes.iter().zip(&packing.placed) {
+            match placed {
+                Some(rectangle) => println!("    {}x{} at {:?}", size.width, size.height, rectangle.top_left()),
+                None => println!("    {}x{} didn't fit", size.width, size.height),
+            }
+        }
+        println!("    wasted area: {}", packing.wasted_area);
+    }
+
+    // A sprite sheet's worth of odd sizes, 1 to 32 pixels on a side
+    // They come from a fixed seed so both packers always get the same sprites
+    let sheet = Size::new(256.0, 256.0);
+    let sprites = sprites(0x1234_5678_9abc_def1, 200);
+    for packer in [Packer::Skyline, Packer::Guillotine] {
+        let packing = pack(sheet, &sprites, packer);
+        println!(
+            "{:?} fit {} of {} sprites, {:.1}% of the sheet wasted",
+            packer,
+            sprites.len() - packing.unplaced.len(),
+            sprites.len(),
+            packing.wasted_area / sheet.area() * 100.0
+        );
+    }
+}
+
+fn sprites(seed: u64, count: usize) -> Vec<Size> {
+    let mut random = XorShift::new(seed);
+    let mut random = move |limit: u64| (random.below(limit) + 1) as f32;
+    (0..count).map(|_| Size::new(random(32), random(32))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKERS: [Packer; 2] = [Packer::Skyline, Packer::Guillotine];
+
+    // Every placed rectangle is inside the container, has the size it was
+    // asked for, and no two of them overlap (touching edges is fine)
+    fn assert_valid(container: Size, sizes: &[Size], packing: &Packing) {
+        let bounds = Rectangle::from_size(Point::ORIGIN, container.width, container.height);
+        assert_eq!(packing.placed.len(), sizes.len());
+        for (size, placed) in sizes.iter().zip(&packing.placed) {
+            let Some(rectangle) = placed else {
+                continue;
+            };
+            assert!(bounds.contains(rectangle.top_left()), "{:?} starts outside", rectangle);
+            assert!(bounds.contains(rectangle.bottom_right()), "{:?} ends outside", rectangle);
+            assert_eq!((rectangle.width(), rectangle.height()), (size.width, size.height));
+        }
+
+        let placed: Vec<&Rectangle> = packing.placed.iter().flatten().collect();
+        for (index, a) in placed.iter().enumerate() {
+            for b in &placed[index + 1..] {
+                let overlap = a.intersection(b).map_or(0.0, |overlap| overlap.area());
+                assert_eq!(overlap, 0.0, "{:?} overlaps {:?}", a, b);
+            }
+        }
+
+        let unplaced: Vec<usize> = (0..sizes.len()).filter(|&index| packing.placed[index].is_none()).collect();
+        assert_eq!(packing.unplaced, unplaced);
+    }
+
+    #[test]
+    fn halves_fill_the_container() {
+        let container = Size::new(10.0, 10.0);
+        let sizes = [
+            Size::new(5.0, 5.0),
+            Size::new(5.0, 5.0),
+            Size::new(10.0, 5.0),
+            Size::new(3.0, 3.0),
+        ];
+        for packer in PACKERS {
+            let packing = pack(container, &sizes, packer);
+            assert_valid(container, &sizes, &packing);
+            assert_eq!(packing.unplaced, vec![3], "{:?}", packer);
+            assert_eq!(packing.wasted_area, 0.0);
+        }
+    }
+
+    #[test]
+    fn too_big_never_fits() {
+        let container = Size::new(10.0, 10.0);
+        for packer in PACKERS {
+            for size in [Size::new(11.0, 1.0), Size::new(1.0, 11.0)] {
+                let packing = pack(container, &[size], packer);
+                assert_eq!(packing.placed, vec![None]);
+                assert_eq!(packing.unplaced, vec![0]);
+                assert_eq!(packing.wasted_area, 100.0);
+            }
+        }
+    }
+
+    #[test]
+    fn nonsense_sizes_are_unplaced() {
+        let container = Size::new(10.0, 10.0);
+        let sizes = [
+            Size::new(4.0, 4.0),
+            Size::new(-2.0, 3.0),
+            Size::new(3.0, -2.0),
+            Size::new(f32::NAN, 1.0),
+            Size::new(1.0, f32::INFINITY),
+            Size::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            Size::new(4.0, 4.0),
+        ];
+        for packer in PACKERS {
+            let packing = pack(container, &sizes, packer);
+            assert_valid(container, &sizes, &packing);
+            assert_eq!(packing.unplaced, vec![1, 2, 3, 4, 5], "{:?}", packer);
+            assert_eq!(packing.wasted_area, 100.0 - 32.0);
+        }
+    }
+
+    #[test]
+    fn nothing_fits_a_nonsense_container() {
+        let sizes = [Size::new(1.0, 1.0), Size::new(0.0, 0.0)];
+        for container in [Size::new(-10.0, 10.0), Size::new(10.0, f32::NAN)] {
+            for packer in PACKERS {
+                let packing = pack(container, &sizes, packer);
+                assert_eq!(packing.unplaced, vec![0, 1]);
+                assert_eq!(packing.wasted_area, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_sized_items_dont_get_in_the_way() {
+        let container = Size::new(10.0, 10.0);
+        let sizes = [
+            Size::new(0.0, 5.0),
+            Size::new(5.0, 0.0),
+            Size::new(10.0, 10.0),
+        ];
+        for packer in PACKERS {
+            let packing = pack(container, &sizes, packer);
+            assert_valid(container, &sizes, &packing);
+            assert!(packing.placed[2].is_some(), "{:?}", packer);
+        }
+    }
+
+    #[test]
+    fn sprite_sheets_pack_validly() {
+        let sheet = Size::new(256.0, 256.0);
+        for seed in [0x1234_5678_9abc_def1, 1, 0xdead_beef] {
+            let sprites = sprites(seed, 200);
+            for packer in PACKERS {
+                let packing = pack(sheet, &sprites, packer);
+                assert_valid(sheet, &sprites, &packing);
+                let placed_area: f32 = packing.placed.iter().flatten().map(|rectangle| rectangle.area()).sum();
+                assert_eq!(placed_area + packing.wasted_area, sheet.area());
+                assert!(packing.unplaced.len() < sprites.len(), "{:?} placed nothing", packer);
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt;
+use std::rc::Rc;
+
+// A persistent (immutable) linked list
+// The List in custom_types owns its tail through a Box, so prepend has to
+// consume the old list and no two lists can ever share nodes
+// Here the tail sits behind an Rc instead, so prepend just points a new node
+// at the old one and bumps a reference count, and the old list stays usable
+// Any number of lists can share the same tail this way
+
+// Unlike List this isn't an enum with a Nil variant
+// An empty list is just a None head, so it doesn't need its own allocation
+// to be shared
+pub struct PersistentList<T> {
+    head: Option<Rc<Node<T>>>,
+}
+
+struct Node<T> {
+    elem: T,
+    tail: PersistentList<T>,
+}
+
+impl<T> PersistentList<T> {
+    // Create an empty list
+    pub fn new() -> PersistentList<T> {
+        PersistentList { head: None }
+    }
+
+    // Return a new list with elem in front of this one
+    // self is only borrowed, so the old list is still there afterwards and
+    // both of them share every node after the new one
+    pub fn prepend(&self, elem: T) -> PersistentList<T> {
+        PersistentList {
+            head: Some(Rc::new(Node {
+                elem,
+                tail: self.clone(),
+            })),
+        }
+    }
+
+    // Look at the first element, if there is one
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.elem)
+    }
+
+    // Everything after the first element, as a list of its own
+    // This is only a reference count bump, nothing gets copied
+    pub fn tail(&self) -> Option<PersistentList<T>> {
+        self.head.as_ref().map(|node| node.tail.clone())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+        }
+    }
+
+    // True if both lists start at the very same node (not just equal values)
+    pub fn ptr_eq(&self, other: &PersistentList<T>) -> bool {
+        match (&self.head, &other.head) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // Return representation of the list as a (heap allocated) string
+    pub fn stringify(&self) -> String
+    where
+        T: fmt::Display,
+    {
+        self.to_string()
+    }
+}
+
+// Cloning only clones the Rc, so it doesn't need T: Clone and it's O(1)
+impl<T> Clone for PersistentList<T> {
+    fn clone(&self) -> Self {
+        PersistentList {
+            head: self.head.clone(),
+        }
+    }
+}
+
+impl<T> Default for PersistentList<T> {
+    fn default() -> Self {
+        PersistentList::new()
+    }
+}
+
+// Same problem as List: the default drop would recurse once per node
+// On top of that we can only free a node if nobody else is pointing at it,
+// so keep unhooking nodes until we reach one that is still shared
+impl<T> Drop for PersistentList<T> {
+    fn drop(&mut self) {
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => head = node.tail.head.take(),
+                // Someone else still uses the rest of the list, leave it be
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+// Prints the same "3, 2, 1, Nil" form as List
+impl<T: fmt::Display> fmt::Display for PersistentList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for elem in self {
+            write!(f, "{}, ", elem)?;
+        }
+        write!(f, "Nil")
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PersistentList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for elem in self {
+            write!(f, "{:?}, ", elem)?;
+        }
+        write!(f, "Nil")
+    }
+}
+
+impl<T: PartialEq> PartialEq for PersistentList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for PersistentList<T> {}
+
+// Collecting keeps the order of the iterator, so the first item ends up at the front
+// We can only add to the front, so the items get buffered and prepended backwards
+impl<T> FromIterator<T> for PersistentList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut list = PersistentList::new();
+        for elem in items.into_iter().rev() {
+            list = list.prepend(elem);
+        }
+        list
+    }
+}
+
+// Borrowing iterator, yields &T from front to back
+// There is no mutable or owning iterator since the nodes may be shared
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| {
+            self.next = node.tail.head.as_deref();
+            &node.elem
+        })
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PersistentList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub fn run() {
+    // Build a list the same way as the boxed one
+    let base = PersistentList::new().prepend(1).prepend(2).prepend(3);
+    println!("base: {}", base);
+
+    // Two lists branching off the same base
+    // Neither of them copies base, they both point straight at it
+    let left = base.prepend(10);
+    let right = base.prepend(20);
+    println!("left: {}", left);
+    println!("right: {}", right);
+    println!("left's tail is base: {}", left.tail().unwrap().ptr_eq(&base));
+
+    // Keeping every version around is a cheap undo history
+    let mut history = vec![PersistentList::new()];
+    for word in ["hello", "there", "general", "kenobi"] {
+        let next = history.last().unwrap().prepend(word);
+        history.push(next);
+    }
+    println!("current: {}", history.last().unwrap());
+
+    // Undo twice by stepping back through the snapshots
+    println!("after two undos: {}", history[history.len() - 3]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strong_count<T>(list: &PersistentList<T>) -> usize {
+        list.head.as_ref().map_or(0, Rc::strong_count)
+    }
+
+    #[test]
+    fn prepend_leaves_the_old_list_alone() {
+        let base = PersistentList::new().prepend(1).prepend(2).prepend(3);
+        let longer = base.prepend(4);
+        assert_eq!(base.stringify(), "3, 2, 1, Nil");
+        assert_eq!(longer.stringify(), "4, 3, 2, 1, Nil");
+        assert_eq!((base.len(), longer.len()), (3, 4));
+        assert_eq!(longer.head(), Some(&4));
+    }
+
+    #[test]
+    fn head_and_tail() {
+        let empty = PersistentList::<u32>::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.head(), None);
+        assert!(empty.tail().is_none());
+        assert_eq!(empty.stringify(), "Nil");
+
+        let one = empty.prepend(1);
+        assert_eq!(one.head(), Some(&1));
+        let rest = one.tail().unwrap();
+        assert!(rest.is_empty());
+        assert!(rest.ptr_eq(&empty));
+    }
+
+    #[test]
+    fn branches_share_their_tail() {
+        let base = PersistentList::new().prepend(1).prepend(2).prepend(3);
+        assert_eq!(strong_count(&base), 1);
+
+        let left = base.prepend(10);
+        let right = base.prepend(20);
+        // base's first node is now pointed at by base, left and right
+        assert_eq!(strong_count(&base), 3);
+        assert!(left.tail().unwrap().ptr_eq(&base));
+        assert!(right.tail().unwrap().ptr_eq(&base));
+        assert!(!left.ptr_eq(&right));
+        assert!(left.tail().unwrap() == right.tail().unwrap());
+
+        drop(left);
+        assert_eq!(strong_count(&base), 2);
+        drop(right);
+        assert_eq!(strong_count(&base), 1);
+        assert_eq!(base.stringify(), "3, 2, 1, Nil");
+    }
+
+    #[test]
+    fn equal_values_are_equal_without_sharing() {
+        let base = PersistentList::new().prepend(1).prepend(2).prepend(3);
+        let collected: PersistentList<u32> = (1..=3).rev().collect();
+        assert!(collected == base);
+        assert!(!collected.ptr_eq(&base));
+        assert!(collected != base.prepend(0));
+    }
+
+    #[test]
+    fn snapshots_make_an_undo_history() {
+        let mut history = vec![PersistentList::new()];
+        for word in ["hello", "there", "general", "kenobi"] {
+            let next = history.last().unwrap().prepend(word);
+            history.push(next);
+        }
+        let current = history.last().unwrap();
+        let undone = &history[history.len() - 3];
+        assert_eq!(undone.len(), 2);
+        assert_eq!(undone.head(), Some(&"there"));
+        assert!(current.tail().unwrap().tail().unwrap().ptr_eq(undone));
+    }
+
+    // Dropping doesn't recurse, even if part of the list is shared
+    #[test]
+    fn long_shared_lists_drop_without_overflowing() {
+        let long_list: PersistentList<u32> = (0..1_000_000).collect();
+        let snapshot = long_list.tail().unwrap();
+        assert_eq!(strong_count(&snapshot), 2);
+        drop(long_list);
+        assert_eq!(strong_count(&snapshot), 1);
+        assert_eq!(snapshot.len(), 999_999);
+        assert_eq!(snapshot.head(), Some(&1));
+        drop(snapshot);
+
+        let long_list: PersistentList<u32> = (0..1_000_000).collect();
+        drop(long_list);
+    }
+}
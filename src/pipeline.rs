@@ -0,0 +1,507 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+
+use crate::custom_types::{inspect, WebEvent};
+
+// Process WebEvents on a pool of worker threads
+//
+// Each worker has its own bounded queue, and every event of a session goes
+// to the same worker, so a session's events are handled in the order they
+// were sent even though different sessions run in parallel
+// When a worker's queue is full, send blocks until there's room again,
+// which slows the producer down instead of piling up events in memory
+// Shutting down puts a stop marker at the back of every queue, so whatever
+// was sent before it is still handled
+// Dropping the pipeline shuts it down the same way, so worker threads never
+// outlive it waiting on senders that are still around
+
+// Which user session an event came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub workers: usize,
+    // How many events each worker can have waiting
+    pub queue_capacity: usize,
+}
+
+// Where handled events end up
+// It's shared by every worker so it has to be Send + Sync
+pub type Sink = Arc<dyn Fn(SessionId, &WebEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendError {
+    // Only from try_send: the session's worker has a full queue
+    Full(WebEvent),
+    // The pipeline has shut down
+    Closed(WebEvent),
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    // A pipeline with no workers would never handle anything
+    NoWorkers,
+    // The OS wouldn't start another thread
+    Spawn(io::Error),
+    // The sink panicked on this worker, so some of the events sent to it
+    // were never handled
+    WorkerPanicked(usize),
+}
+
+// How many events each worker handled, in worker order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub handled: Vec<usize>,
+}
+
+enum Message {
+    Event(SessionId, WebEvent),
+    Stop,
+}
+
+struct Queues {
+    senders: Vec<SyncSender<Message>>,
+    // Sends hold the read lock and shutdown takes the write lock, so no
+    // event can sneak into a queue behind the stop marker and get lost
+    closed: RwLock<bool>,
+}
+
+// Cheap to clone, so any number of threads can feed the same pipeline
+#[derive(Clone)]
+pub struct EventSender {
+    queues: Arc<Queues>,
+}
+
+pub struct Pipeline {
+    sender: EventSender,
+    workers: Vec<JoinHandle<usize>>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> PipelineConfig {
+        PipelineConfig {
+            workers: 4,
+            queue_capacity: 64,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendError::Full(event) => write!(f, "queue is full, {:?} was not sent", event),
+            SendError::Closed(event) => write!(f, "pipeline is shut down, {:?} was not sent", event),
+        }
+    }
+}
+
+impl Error for SendError {}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::NoWorkers => write!(f, "a pipeline needs at least one worker"),
+            PipelineError::Spawn(error) => write!(f, "couldn't start a pipeline worker: {}", error),
+            PipelineError::WorkerPanicked(worker) => write!(f, "pipeline worker {} panicked", worker),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Spawn(error) => Some(error),
+            PipelineError::NoWorkers | PipelineError::WorkerPanicked(_) => None,
+        }
+    }
+}
+
+impl SendError {
+    // Get the event back to retry it or send it somewhere else
+    pub fn into_event(self) -> WebEvent {
+        match self {
+            SendError::Full(event) | SendError::Closed(event) => event,
+        }
+    }
+}
+
+impl EventSender {
+    // Wait for room in the session's queue if it's full
+    pub fn send(&self, session: SessionId, event: WebEvent) -> Result<(), SendError> {
+        let closed = self.queues.closed.read().unwrap();
+        if *closed {
+            return Err(SendError::Closed(event));
+        }
+        self.queue(session)
+            .send(Message::Event(session, event))
+            .map_err(|error| match error.0 {
+                Message::Event(_, event) => SendError::Closed(event),
+                Message::Stop => unreachable!("only shutdown sends Stop"),
+            })
+    }
+
+    // Give up straight away if the session's queue is full
+    pub fn try_send(&self, session: SessionId, event: WebEvent) -> Result<(), SendError> {
+        let closed = self.queues.closed.read().unwrap();
+        if *closed {
+            return Err(SendError::Closed(event));
+        }
+        self.queue(session)
+            .try_send(Message::Event(session, event))
+            .map_err(|error| match error {
+                TrySendError::Full(Message::Event(_, event)) => SendError::Full(event),
+                TrySendError::Disconnected(Message::Event(_, event)) => SendError::Closed(event),
+                _ => unreachable!("only shutdown sends Stop"),
+            })
+    }
+
+    // The same session always lands on the same worker, which is what
+    // keeps its events in order
+    fn queue(&self, session: SessionId) -> &SyncSender<Message> {
+        let senders = &self.queues.senders;
+        &senders[(session.0 % senders.len() as u64) as usize]
+    }
+}
+
+impl Pipeline {
+    pub fn new(config: PipelineConfig, sink: Sink) -> Result<Pipeline, PipelineError> {
+        if config.workers == 0 {
+            return Err(PipelineError::NoWorkers);
+        }
+
+        let mut senders = Vec::with_capacity(config.workers);
+        let mut workers = Vec::with_capacity(config.workers);
+        for index in 0..config.workers {
+            let (sender, receiver) = mpsc::sync_channel(config.queue_capacity);
+            let sink = Arc::clone(&sink);
+            // If this fails, returning drops the senders made so far, which
+            // lets the workers that did start finish on their own
+            let worker = thread::Builder::new()
+                .name(format!("pipeline-worker-{}", index))
+                .spawn(move || work(receiver, sink))
+                .map_err(PipelineError::Spawn)?;
+            senders.push(sender);
+            workers.push(worker);
+        }
+
+        Ok(Pipeline {
+            sender: EventSender {
+                queues: Arc::new(Queues {
+                    senders,
+                    closed: RwLock::new(false),
+                }),
+            },
+            workers,
+        })
+    }
+
+    // inspect every event, like custom_types does on the main thread
+    pub fn inspecting(config: PipelineConfig) -> Result<Pipeline, PipelineError> {
+        Pipeline::new(config, Arc::new(|_, event| inspect(event)))
+    }
+
+    pub fn sender(&self) -> EventSender {
+        self.sender.clone()
+    }
+
+    // Let every worker finish what's already queued, then stop them
+    // Senders handed out earlier get SendError::Closed from then on
+    // Every worker is joined even if one of them panicked, and the first
+    // one that did is the error
+    pub fn shutdown(mut self) -> Result<PipelineStats, PipelineError> {
+        let handled = self
+            .stop()
+            .into_iter()
+            .enumerate()
+            .map(|(worker, handled)| handled.map_err(|_| PipelineError::WorkerPanicked(worker)))
+            .collect::<Result<_, _>>()?;
+        Ok(PipelineStats { handled })
+    }
+
+    // What shutdown and drop both do, leaving workers empty so it only
+    // happens once
+    fn stop(&mut self) -> Vec<thread::Result<usize>> {
+        let workers = mem::take(&mut self.workers);
+        if workers.is_empty() {
+            return Vec::new();
+        }
+        // Waits for any send that's blocked on a full queue to get in first
+        *self.sender.queues.closed.write().unwrap() = true;
+        for queue in &self.sender.queues.senders {
+            // This waits for room like any other send, so nothing queued
+            // before it is lost
+            // A worker that panicked has dropped its receiver, which is
+            // fine to ignore here since join reports it
+            let _ = queue.send(Message::Stop);
+        }
+        workers.into_iter().map(JoinHandle::join).collect()
+    }
+}
+
+// Without this, a pipeline dropped while some EventSender is still around
+// would leave every worker blocked on its queue forever
+// A worker that panicked is ignored here, shutdown is there for anyone who
+// wants to know
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// One worker: handle events until Stop, then count how many there were
+fn work(receiver: Receiver<Message>, sink: Sink) -> usize {
+    let mut handled = 0;
+    // The loop also ends if every sender is gone, which can only happen
+    // once the pipeline itself has been dropped
+    for message in receiver {
+        match message {
+            Message::Event(session, event) => {
+                sink(session, &event);
+                handled += 1;
+            },
+            Message::Stop => break,
+        }
+    }
+    handled
+}
+
+pub fn run() {
+    // A burst of clicks from a few sessions on two producer threads
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink: Sink = {
+        let seen = Arc::clone(&seen);
+        Arc::new(move |session, event| {
+            if let WebEvent::Click { x, .. } = event {
+                seen.lock().unwrap().push((session, *x));
+            }
+        })
+    };
+    let pipeline = Pipeline::new(PipelineConfig { workers: 3, queue_capacity: 8 }, sink).unwrap();
+    let producers: Vec<_> = (0..2)
+        .map(|producer| {
+            let sender = pipeline.sender();
+            thread::spawn(move || {
+                for session in (producer..6).step_by(2) {
+                    for step in 0..5 {
+                        sender.send(SessionId(session), WebEvent::Click { x: step, y: 0 }).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    let stats = pipeline.shutdown().unwrap();
+    println!("Events handled per worker: {:?}", stats.handled);
+    // Sessions are interleaved, but each one's clicks are still in order
+    let first: Vec<i64> = seen
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(session, _)| *session == SessionId(0))
+        .map(|&(_, step)| step)
+        .collect();
+    println!("Session 0 saw clicks {:?}", first);
+
+    // Backpressure: one worker with room for two events, held up on the
+    // first one until the full queue has been shown off
+    let (started, wait_for_start) = mpsc::channel();
+    let (release, wait_for_release) = mpsc::channel::<()>();
+    let (started, wait_for_release) = (Mutex::new(started), Mutex::new(wait_for_release));
+    let held: Sink = Arc::new(move |_, _| {
+        let _ = started.lock().unwrap().send(());
+        // Errs straight away once release is dropped
+        let _ = wait_for_release.lock().unwrap().recv();
+    });
+    let pipeline = Pipeline::new(PipelineConfig { workers: 1, queue_capacity: 2 }, held).unwrap();
+    let sender = pipeline.sender();
+    sender.send(SessionId(1), WebEvent::PageLoad).unwrap();
+    wait_for_start.recv().unwrap();
+    sender.try_send(SessionId(1), WebEvent::KeyPress('a')).unwrap();
+    sender.try_send(SessionId(1), WebEvent::KeyPress('b')).unwrap();
+    if let Err(full) = sender.try_send(SessionId(1), WebEvent::KeyPress('c')) {
+        println!("{}", full);
+    }
+    drop(release);
+    let stats = pipeline.shutdown().unwrap();
+    println!("Events handled once the worker was let go: {:?}", stats.handled);
+
+    // After shutdown the event comes back
+    let closed = sender.send(SessionId(1), WebEvent::PageUnload).unwrap_err();
+    println!("{}", closed);
+
+    // inspect as the sink
+    let pipeline = Pipeline::inspecting(PipelineConfig::default()).unwrap();
+    let sender = pipeline.sender();
+    sender.send(SessionId(7), WebEvent::PageLoad).unwrap();
+    sender.send(SessionId(7), WebEvent::Paste("my test".to_owned())).unwrap();
+    sender.send(SessionId(7), WebEvent::PageUnload).unwrap();
+    pipeline.shutdown().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counting() -> (Sink, Arc<Mutex<usize>>) {
+        let count = Arc::new(Mutex::new(0));
+        let sink: Sink = {
+            let count = Arc::clone(&count);
+            Arc::new(move |_, _| *count.lock().unwrap() += 1)
+        };
+        (sink, count)
+    }
+
+    // A sink that holds its worker on every event until the returned sender
+    // is dropped, and says when it has started on one
+    fn held() -> (Sink, Receiver<()>, mpsc::Sender<()>) {
+        let (started, wait_for_start) = mpsc::channel();
+        let (release, wait_for_release) = mpsc::channel::<()>();
+        let (started, wait_for_release) = (Mutex::new(started), Mutex::new(wait_for_release));
+        let sink: Sink = Arc::new(move |_, _| {
+            let _ = started.lock().unwrap().send(());
+            let _ = wait_for_release.lock().unwrap().recv();
+        });
+        (sink, wait_for_start, release)
+    }
+
+    #[test]
+    fn each_session_keeps_its_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink: Sink = {
+            let seen = Arc::clone(&seen);
+            Arc::new(move |session, event| {
+                if let WebEvent::Click { x, .. } = event {
+                    seen.lock().unwrap().push((session, *x));
+                }
+            })
+        };
+        let pipeline = Pipeline::new(PipelineConfig { workers: 3, queue_capacity: 8 }, sink).unwrap();
+
+        let sessions: u64 = 10;
+        let per_session: i64 = 200;
+        let producers: Vec<_> = (0..2)
+            .map(|producer| {
+                let sender = pipeline.sender();
+                thread::spawn(move || {
+                    for session in (producer..sessions).step_by(2) {
+                        for step in 0..per_session {
+                            sender.send(SessionId(session), WebEvent::Click { x: step, y: 0 }).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let stats = pipeline.shutdown().unwrap();
+
+        let total = sessions as usize * per_session as usize;
+        assert_eq!(stats.handled.len(), 3);
+        assert_eq!(stats.handled.iter().sum::<usize>(), total);
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), total);
+        for session in 0..sessions {
+            let steps: Vec<i64> = seen
+                .iter()
+                .filter(|(id, _)| *id == SessionId(session))
+                .map(|&(_, step)| step)
+                .collect();
+            assert_eq!(steps, (0..per_session).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn full_queues_push_back() {
+        let (sink, wait_for_start, release) = held();
+        let pipeline = Pipeline::new(PipelineConfig { workers: 1, queue_capacity: 2 }, sink).unwrap();
+        let sender = pipeline.sender();
+        sender.send(SessionId(1), WebEvent::PageLoad).unwrap();
+        // The worker is stuck on the first event, so the queue can take
+        // exactly two more
+        wait_for_start.recv().unwrap();
+        sender.try_send(SessionId(1), WebEvent::KeyPress('a')).unwrap();
+        sender.try_send(SessionId(1), WebEvent::KeyPress('b')).unwrap();
+        assert_eq!(
+            sender.try_send(SessionId(1), WebEvent::KeyPress('c')),
+            Err(SendError::Full(WebEvent::KeyPress('c')))
+        );
+
+        // A blocking send just waits its turn instead
+        let blocked = {
+            let sender = sender.clone();
+            thread::spawn(move || sender.send(SessionId(1), WebEvent::KeyPress('c')))
+        };
+        drop(release);
+        blocked.join().unwrap().unwrap();
+        assert_eq!(pipeline.shutdown().unwrap().handled, vec![4]);
+    }
+
+    #[test]
+    fn shutdown_drains_the_queues_first() {
+        let (sink, wait_for_start, release) = held();
+        let pipeline = Pipeline::new(PipelineConfig { workers: 2, queue_capacity: 4 }, sink).unwrap();
+        let sender = pipeline.sender();
+        for session in 0..6 {
+            sender.send(SessionId(session), WebEvent::PageLoad).unwrap();
+        }
+        wait_for_start.recv().unwrap();
+        drop(release);
+        assert_eq!(pipeline.shutdown().unwrap().handled, vec![3, 3]);
+
+        // After shutdown the event comes back
+        let closed = sender.send(SessionId(0), WebEvent::PageUnload).unwrap_err();
+        assert_eq!(closed, SendError::Closed(WebEvent::PageUnload));
+        assert_eq!(closed.into_event(), WebEvent::PageUnload);
+        assert_eq!(
+            sender.try_send(SessionId(0), WebEvent::PageUnload),
+            Err(SendError::Closed(WebEvent::PageUnload))
+        );
+    }
+
+    #[test]
+    fn a_panicking_sink_is_reported_by_shutdown() {
+        let sink: Sink = Arc::new(|session, _| assert_ne!(session, SessionId(1), "sink gave up"));
+        let pipeline = Pipeline::new(PipelineConfig { workers: 2, queue_capacity: 4 }, sink).unwrap();
+        let sender = pipeline.sender();
+        sender.send(SessionId(0), WebEvent::PageLoad).unwrap();
+        sender.send(SessionId(1), WebEvent::PageLoad).unwrap();
+        assert!(matches!(pipeline.shutdown(), Err(PipelineError::WorkerPanicked(1))));
+    }
+
+    #[test]
+    fn no_workers_is_an_error() {
+        let (sink, _) = counting();
+        let config = PipelineConfig { workers: 0, queue_capacity: 8 };
+        assert!(matches!(Pipeline::new(config, sink), Err(PipelineError::NoWorkers)));
+    }
+
+    // The sender outlives the pipeline, which used to leave the workers
+    // waiting on it forever and this test hanging
+    #[test]
+    fn dropping_drains_the_queues_and_joins_the_workers() {
+        let (sink, count) = counting();
+        let pipeline = Pipeline::new(PipelineConfig { workers: 2, queue_capacity: 4 }, sink).unwrap();
+        let sender = pipeline.sender();
+        for session in 0..20 {
+            sender.send(SessionId(session), WebEvent::PageLoad).unwrap();
+        }
+        drop(pipeline);
+        // Every worker has been joined, so everything sent was handled
+        assert_eq!(*count.lock().unwrap(), 20);
+        assert_eq!(
+            sender.send(SessionId(0), WebEvent::PageUnload),
+            Err(SendError::Closed(WebEvent::PageUnload))
+        );
+    }
+}
@@ -1,4 +1,7 @@
-fn main() {
+// pi is written out by hand on purpose to show off the {:.2} formatting
+#![allow(clippy::approx_constant)]
+
+pub fn run() {
     let x = 5 + /*90 + */ 5;
     let y = 21;
     
@@ -0,0 +1,662 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+use crate::geometry::{Point, Rectangle};
+use crate::random::XorShift;
+
+// A quadtree: a spatial index that splits its area into four quadrants,
+// and each of those into four more, as things get crowded
+// Every item goes in the smallest quadrant that fully holds it, so a query
+// only has to look at the quadrants it overlaps instead of every item
+// Items are stored by their bounding Rectangle, a Point is just a
+// rectangle with no size
+
+// How many items a quadrant holds before it splits
+// A branch that drops back to this many items merges back into a leaf
+const CAPACITY: usize = 8;
+// How many times quadrants can split, so a pile of identical items
+// can't split forever
+const MAX_DEPTH: usize = 10;
+
+// Handle to an item in the tree, used to remove it again
+// Removed items free their slot for the next insert, so the id also says
+// which use of the slot it's for, and an id kept after its item is gone
+// can't reach whatever moved in after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemId {
+    index: usize,
+    generation: u64,
+}
+
+struct Entry<T> {
+    bounds: Rectangle,
+    value: T,
+}
+
+struct Slot<T> {
+    // Bumped every time the slot is emptied
+    generation: u64,
+    entry: Option<Entry<T>>,
+}
+
+enum Node {
+    // Leaf: A quadrant that hasn't split (yet), with the items in it
+    Leaf(Vec<usize>),
+    // Branch: A quadrant that has split, with the items that straddle
+    // its children plus the four children themselves
+    Branch(Vec<usize>, Box<[Node; 4]>),
+}
+
+pub struct Quadtree<T> {
+    bounds: Rectangle,
+    root: Node,
+    // Items that don't fit inside bounds at all, these just get checked every time
+    outside: Vec<usize>,
+    // Every item, indexed by ItemId
+    // Removed items leave a None behind so the other ids stay valid, and
+    // the slot goes on the free list to be reused
+    entries: Vec<Slot<T>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+// Split a rectangle into its four quadrants:
+// top left, top right, bottom left, bottom right
+fn quadrants(bounds: &Rectangle) -> [Rectangle; 4] {
+    let center = bounds.center();
+    [
+        Rectangle::new(bounds.top_left(), center),
+        Rectangle::new(Point::new(bounds.right(), bounds.top()), center),
+        Rectangle::new(Point::new(bounds.left(), bounds.bottom()), center),
+        Rectangle::new(bounds.bottom_right(), center),
+    ]
+}
+
+// Which quadrant holds all of item, if any
+// Always picks the same one for the same rectangle, which remove relies on
+fn quadrant_for(bounds: &Rectangle, item: &Rectangle) -> Option<usize> {
+    quadrants(bounds)
+        .iter()
+        .position(|quadrant| quadrant.contains(item.top_left()) && quadrant.contains(item.bottom_right()))
+}
+
+impl Node {
+    fn insert<T>(&mut self, bounds: &Rectangle, depth: usize, id: usize, entries: &[Slot<T>]) {
+        match self {
+            Node::Leaf(ids) => {
+                ids.push(id);
+                if ids.len() > CAPACITY && depth < MAX_DEPTH {
+                    self.split(bounds, depth, entries);
+                }
+            },
+            Node::Branch(ids, children) => {
+                let item = &entries[id].entry.as_ref().unwrap().bounds;
+                match quadrant_for(bounds, item) {
+                    Some(index) => children[index].insert(&quadrants(bounds)[index], depth + 1, id, entries),
+                    None => ids.push(id),
+                }
+            },
+        }
+    }
+
+    // Turn a crowded leaf into a branch and push its items down
+    fn split<T>(&mut self, bounds: &Rectangle, depth: usize, entries: &[Slot<T>]) {
+        let Node::Leaf(ids) = self else {
+            return;
+        };
+        let ids = std::mem::take(ids);
+        *self = Node::Branch(
+            Vec::new(),
+            Box::new([Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new())]),
+        );
+        for id in ids {
+            self.insert(bounds, depth, id, entries);
+        }
+    }
+
+    // Follow the same path insert took and take id back out
+    // Every branch on the way back up merges if it has become sparse
+    fn remove(&mut self, bounds: &Rectangle, item: &Rectangle, id: usize) -> bool {
+        let (ids, children) = match self {
+            Node::Leaf(ids) => (ids, None),
+            Node::Branch(ids, children) => (ids, Some(children)),
+        };
+
+        let removed = if let Some(position) = ids.iter().position(|&other| other == id) {
+            ids.swap_remove(position);
+            true
+        } else {
+            match (children, quadrant_for(bounds, item)) {
+                (Some(children), Some(index)) => children[index].remove(&quadrants(bounds)[index], item, id),
+                _ => false,
+            }
+        };
+
+        if removed {
+            self.merge_if_sparse();
+        }
+        removed
+    }
+
+    // How many items are in and under this node
+    // Stops counting once it's past limit, since that's all merging needs
+    // to know and a big subtree would otherwise be walked on every remove
+    fn count(&self, limit: usize) -> usize {
+        match self {
+            Node::Leaf(ids) => ids.len(),
+            Node::Branch(ids, children) => {
+                let mut total = ids.len();
+                for child in children.iter() {
+                    if total > limit {
+                        break;
+                    }
+                    total += child.count(limit - total);
+                }
+                total
+            },
+        }
+    }
+
+    // A branch with no more items than a leaf could hold goes back to
+    // being a leaf, so removing most of the items also gets rid of the
+    // quadrants that were made for them
+    fn merge_if_sparse(&mut self) {
+        if matches!(self, Node::Leaf(_)) || self.count(CAPACITY) > CAPACITY {
+            return;
+        }
+        let mut ids = Vec::new();
+        self.collect(&mut ids);
+        *self = Node::Leaf(ids);
+    }
+
+    fn collect(&self, into: &mut Vec<usize>) {
+        match self {
+            Node::Leaf(ids) => into.extend(ids),
+            Node::Branch(ids, children) => {
+                into.extend(ids);
+                children.iter().for_each(|child| child.collect(into));
+            },
+        }
+    }
+}
+
+// How far a point is from the closest part of a rectangle, 0 if it's inside
+fn distance_to(rectangle: &Rectangle, point: Point) -> f32 {
+    let dx = (rectangle.left() - point.x).max(point.x - rectangle.right()).max(0.0);
+    let dy = (rectangle.top() - point.y).max(point.y - rectangle.bottom()).max(0.0);
+    dx.hypot(dy)
+}
+
+// Something waiting to be looked at during a nearest neighbour search
+// BinaryHeap pops the biggest thing first, so the ordering is flipped to
+// make the closest candidate come out first
+enum Candidate<'a> {
+    Node(&'a Node, Rectangle),
+    Item(usize),
+}
+
+struct Queued<'a> {
+    distance: f32,
+    candidate: Candidate<'a>,
+}
+
+impl PartialEq for Queued<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued<'_> {}
+
+impl PartialOrd for Queued<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl<T> Quadtree<T> {
+    // An empty tree covering bounds
+    // Items outside bounds still work, they just don't get indexed
+    pub fn new(bounds: Rectangle) -> Quadtree<T> {
+        Quadtree {
+            bounds,
+            root: Node::Leaf(Vec::new()),
+            outside: Vec::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, bounds: Rectangle, value: T) -> ItemId {
+        let entry = Some(Entry { bounds, value });
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.entries[id].entry = entry;
+                id
+            },
+            None => {
+                self.entries.push(Slot { generation: 0, entry });
+                self.entries.len() - 1
+            },
+        };
+        self.len += 1;
+
+        if self.bounds.contains(bounds.top_left()) && self.bounds.contains(bounds.bottom_right()) {
+            self.root.insert(&self.bounds, 0, id, &self.entries);
+        } else {
+            self.outside.push(id);
+        }
+        self.id(id)
+    }
+
+    pub fn insert_point(&mut self, point: Point, value: T) -> ItemId {
+        self.insert(Rectangle::new(point, point), value)
+    }
+
+    pub fn remove(&mut self, id: ItemId) -> Option<T> {
+        let bounds = self.live(id)?.bounds;
+
+        if let Some(position) = self.outside.iter().position(|&other| other == id.index) {
+            self.outside.swap_remove(position);
+        } else if !self.root.remove(&self.bounds, &bounds, id.index) {
+            return None;
+        }
+
+        self.len -= 1;
+        let slot = &mut self.entries[id.index];
+        slot.generation += 1;
+        self.free.push(id.index);
+        slot.entry.take().map(|entry| entry.value)
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<(&Rectangle, &T)> {
+        let entry = self.live(id)?;
+        Some((&entry.bounds, &entry.value))
+    }
+
+    // The entry id points at, unless it has been removed since
+    fn live(&self, id: ItemId) -> Option<&Entry<T>> {
+        let slot = self.entries.get(id.index)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.entry.as_ref()
+    }
+
+    fn id(&self, id: usize) -> ItemId {
+        ItemId {
+            index: id,
+            generation: self.entries[id].generation,
+        }
+    }
+
+    fn entry(&self, id: usize) -> &Entry<T> {
+        // Ids in the tree always point at live entries
+        self.entries[id].entry.as_ref().unwrap()
+    }
+
+    // Every item whose rectangle overlaps region (touching counts)
+    pub fn query_region(&self, region: &Rectangle) -> Vec<(ItemId, &T)> {
+        let mut found = Vec::new();
+        let mut check = |id: usize| {
+            let entry = self.entry(id);
+            if entry.bounds.intersects(region) {
+                found.push((self.id(id), &entry.value));
+            }
+        };
+
+        for &id in &self.outside {
+            check(id);
+        }
+
+        let mut stack = vec![(&self.root, self.bounds)];
+        while let Some((node, bounds)) = stack.pop() {
+            // Everything in a node lies inside its bounds, so if the
+            // bounds miss the region, so does everything in it
+            if !bounds.intersects(region) {
+                continue;
+            }
+            match node {
+                Node::Leaf(ids) => ids.iter().for_each(|&id| check(id)),
+                Node::Branch(ids, children) => {
+                    ids.iter().for_each(|&id| check(id));
+                    stack.extend(children.iter().zip(quadrants(&bounds)));
+                },
+            }
+        }
+        found
+    }
+
+    // Every item whose rectangle contains point, edges included
+    pub fn query_point(&self, point: Point) -> Vec<(ItemId, &T)> {
+        self.query_region(&Rectangle::new(point, point))
+    }
+
+    // The item closest to point, and how far away it is
+    // Distance is measured to the nearest edge of the item's rectangle,
+    // so anything containing point is 0 away
+    pub fn nearest(&self, point: Point) -> Option<(ItemId, &T, f32)> {
+        let mut queue = BinaryHeap::new();
+        for &id in &self.outside {
+            queue.push(Queued {
+                distance: distance_to(&self.entry(id).bounds, point),
+                candidate: Candidate::Item(id),
+            });
+        }
+        queue.push(Queued {
+            distance: distance_to(&self.bounds, point),
+            candidate: Candidate::Node(&self.root, self.bounds),
+        });
+
+        // Always look at whatever could be closest next
+        // Nodes are queued by the distance to their bounds, which nothing
+        // inside them can beat, so the first item popped is the nearest one
+        while let Some(Queued { distance, candidate }) = queue.pop() {
+            match candidate {
+                Candidate::Item(id) => return Some((self.id(id), &self.entry(id).value, distance)),
+                Candidate::Node(node, bounds) => {
+                    let (ids, children) = match node {
+                        Node::Leaf(ids) => (ids, None),
+                        Node::Branch(ids, children) => (ids, Some(children)),
+                    };
+                    for &id in ids {
+                        queue.push(Queued {
+                            distance: distance_to(&self.entry(id).bounds, point),
+                            candidate: Candidate::Item(id),
+                        });
+                    }
+                    if let Some(children) = children {
+                        for (child, child_bounds) in children.iter().zip(quadrants(&bounds)) {
+                            queue.push(Queued {
+                                distance: distance_to(&child_bounds, point),
+                                candidate: Candidate::Node(child, child_bounds),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+        None
+    }
+}
+
+// Brute force versions of the queries, to check against and race
+
+fn scan_region(rectangles: &[Rectangle], region: &Rectangle) -> Vec<usize> {
+    (0..rectangles.len())
+        .filter(|&index| rectangles[index].intersects(region))
+        .collect()
+}
+
+fn scan_nearest(rectangles: &[Rectangle], point: Point) -> Option<f32> {
+    rectangles
+        .iter()
+        .map(|rectangle| distance_to(rectangle, point))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+fn time<R>(mut work: impl FnMut() -> R) -> Duration {
+    let start = Instant::now();
+    std::hint::black_box(work());
+    start.elapsed()
+}
+
+pub fn run() {
+    let point = |x, y| Point::new(x, y);
+
+    let mut tree = Quadtree::new(Rectangle::new(point(0.0, 0.0), point(100.0, 100.0)));
+    let button = tree.insert(Rectangle::new(point(10.0, 10.0), point(20.0, 15.0)), "button");
+    tree.insert(Rectangle::new(point(5.0, 5.0), point(50.0, 50.0)), "panel");
+    tree.insert_point(point(80.0, 80.0), "marker");
+    tree.insert(Rectangle::new(point(150.0, 0.0), point(160.0, 10.0)), "offscreen");
+
+    let names = |found: Vec<(ItemId, &&'static str)>| found.iter().map(|(_, name)| **name).collect::<Vec<_>>();
+    println!("Under (12, 12): {:?}", names(tree.query_point(point(12.0, 12.0))));
+    if let Some((_, name, distance)) = tree.nearest(point(90.0, 80.0)) {
+        println!("Nearest to (90, 80): {} at {}", name, distance);
+    }
+    tree.remove(button);
+    println!("Under (12, 12) without the button: {:?}", names(tree.query_point(point(12.0, 12.0))));
+
+    // Benchmark against scanning every rectangle
+    // The rectangles and queries come from a fixed seed, so every run times
+    // the same work (the tests check both give the same answers)
+    let rectangles = scattered(0x9e37_79b9_7f4a_7c15, 5_000);
+    let mut tree = Quadtree::new(Rectangle::new(point(0.0, 0.0), point(10_000.0, 10_000.0)));
+    for (index, rectangle) in rectangles.iter().enumerate() {
+        tree.insert(*rectangle, index);
+    }
+
+    let mut random = XorShift::new(0x2545_f491_4f6c_dd1d);
+    let points: Vec<Point> = (0..500).map(|_| point(random.unit() * 10_000.0, random.unit() * 10_000.0)).collect();
+    let regions: Vec<Rectangle> = points.iter().map(|&p| Rectangle::from_size(p, 200.0, 200.0)).collect();
+
+    let tree_regions = time(|| regions.iter().map(|region| tree.query_region(region).len()).sum::<usize>());
+    let scan_regions = time(|| regions.iter().map(|region| scan_region(&rectangles, region).len()).sum::<usize>());
+    let tree_nearest = time(|| points.iter().filter_map(|&p| tree.nearest(p)).count());
+    let scan_nearest = time(|| points.iter().filter_map(|&p| scan_nearest(&rectangles, p)).count());
+
+    // Each is a single pass, so expect a little wobble from run to run
+    println!("{} rectangles, {} queries each:", rectangles.len(), points.len());
+    println!("    region queries: quadtree {:.2?}, scan {:.2?}", tree_regions, scan_regions);
+    println!("    nearest:        quadtree {:.2?}, scan {:.2?}", tree_nearest, scan_nearest);
+}
+
+// count rectangles up to 100 wide, spread over a 10,000 square world
+fn scattered(seed: u64, count: usize) -> Vec<Rectangle> {
+    let mut random = XorShift::new(seed);
+    let mut random = move |limit: f32| random.unit() * limit;
+    (0..count)
+        .map(|_| Rectangle::from_size(Point::new(random(9_900.0), random(9_900.0)), random(100.0), random(100.0)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn names<'a>(found: Vec<(ItemId, &&'a str)>) -> Vec<&'a str> {
+        let mut names: Vec<&str> = found.iter().map(|(_, name)| **name).collect();
+        names.sort();
+        names
+    }
+
+    // How many branches there are, to see that they merge away again
+    fn branches(node: &Node) -> usize {
+        match node {
+            Node::Leaf(_) => 0,
+            Node::Branch(_, children) => 1 + children.iter().map(branches).sum::<usize>(),
+        }
+    }
+
+    // Every branch holds more than a leaf could, and every id in the tree
+    // is there exactly once
+    fn assert_tidy<T>(tree: &Quadtree<T>) {
+        fn check(node: &Node) {
+            if let Node::Branch(_, children) = node {
+                assert!(node.count(CAPACITY) > CAPACITY, "a sparse branch was left behind");
+                children.iter().for_each(check);
+            }
+        }
+        check(&tree.root);
+
+        let mut ids = tree.outside.clone();
+        tree.root.collect(&mut ids);
+        ids.sort();
+        let before = ids.len();
+        ids.dedup();
+        assert_eq!(ids.len(), before);
+        assert_eq!(ids.len(), tree.len());
+        assert!(ids.iter().all(|&id| tree.entries[id].entry.is_some()));
+    }
+
+    fn small_tree() -> (Quadtree<&'static str>, [ItemId; 4]) {
+        let mut tree = Quadtree::new(Rectangle::new(point(0.0, 0.0), point(100.0, 100.0)));
+        let button = tree.insert(Rectangle::new(point(10.0, 10.0), point(20.0, 15.0)), "button");
+        let panel = tree.insert(Rectangle::new(point(5.0, 5.0), point(50.0, 50.0)), "panel");
+        let marker = tree.insert_point(point(80.0, 80.0), "marker");
+        let offscreen = tree.insert(Rectangle::new(point(150.0, 0.0), point(160.0, 10.0)), "offscreen");
+        (tree, [button, panel, marker, offscreen])
+    }
+
+    #[test]
+    fn queries() {
+        let (tree, _) = small_tree();
+        assert_eq!(names(tree.query_point(point(12.0, 12.0))), ["button", "panel"]);
+        assert_eq!(names(tree.query_point(point(80.0, 80.0))), ["marker"]);
+        assert_eq!(names(tree.query_point(point(99.0, 1.0))), Vec::<&str>::new());
+        let region = Rectangle::new(point(45.0, 5.0), point(200.0, 85.0));
+        assert_eq!(names(tree.query_region(&region)), ["marker", "offscreen", "panel"]);
+        // Touching counts
+        assert_eq!(names(tree.query_point(point(50.0, 50.0))), ["panel"]);
+    }
+
+    #[test]
+    fn nearest() {
+        let (tree, [_, _, marker, offscreen]) = small_tree();
+        let (nearest, name, distance) = tree.nearest(point(90.0, 80.0)).unwrap();
+        assert_eq!((nearest, *name, distance), (marker, "marker", 10.0));
+        assert_eq!(tree.nearest(point(155.0, 20.0)).map(|(id, _, _)| id), Some(offscreen));
+        assert_eq!(tree.nearest(point(12.0, 12.0)).map(|(_, _, distance)| distance), Some(0.0));
+        let empty = Quadtree::<u32>::new(Rectangle::new(point(0.0, 0.0), point(1.0, 1.0)));
+        assert!(empty.nearest(point(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn remove_and_get() {
+        let (mut tree, [button, panel, _, offscreen]) = small_tree();
+        assert_eq!(tree.get(button).map(|(_, name)| *name), Some("button"));
+        assert_eq!(tree.remove(button), Some("button"));
+        assert_eq!(tree.remove(button), None);
+        assert_eq!(tree.get(button), None);
+        assert_eq!(names(tree.query_point(point(12.0, 12.0))), ["panel"]);
+        assert_eq!(tree.nearest(point(12.0, 12.0)).map(|(id, _, _)| id), Some(panel));
+        assert_eq!(tree.remove(offscreen), Some("offscreen"));
+        assert_eq!(tree.len(), 2);
+        assert_tidy(&tree);
+    }
+
+    // The freed slot gets used again, but the old id doesn't reach the new item
+    #[test]
+    fn stale_ids_dont_reach_reused_slots() {
+        let (mut tree, [button, ..]) = small_tree();
+        tree.remove(button);
+        let slots = tree.entries.len();
+        let replacement = tree.insert_point(point(1.0, 1.0), "replacement");
+        assert_eq!(tree.entries.len(), slots);
+        assert_ne!(replacement, button);
+        assert_eq!(tree.get(button), None);
+        assert_eq!(tree.remove(button), None);
+        assert_eq!(tree.get(replacement).map(|(_, name)| *name), Some("replacement"));
+        assert_eq!(tree.len(), 4);
+    }
+
+    // Removing items merges the branches they made, and the slots get reused
+    // instead of the entries growing forever
+    #[test]
+    fn removing_merges_branches_back() {
+        let rectangles = scattered(7, 2_000);
+        let mut tree = Quadtree::new(Rectangle::new(point(0.0, 0.0), point(10_000.0, 10_000.0)));
+        for _ in 0..3 {
+            let ids: Vec<ItemId> = rectangles.iter().map(|&rectangle| tree.insert(rectangle, ())).collect();
+            assert!(branches(&tree.root) > 10);
+            assert_tidy(&tree);
+            for (index, id) in ids.into_iter().enumerate() {
+                assert_eq!(tree.remove(id), Some(()));
+                if index % 97 == 0 {
+                    assert_tidy(&tree);
+                }
+            }
+            assert!(tree.is_empty());
+            assert_eq!(branches(&tree.root), 0);
+            assert_eq!(tree.entries.len(), rectangles.len());
+        }
+    }
+
+    // Lots of identical items stop splitting at MAX_DEPTH, and still merge
+    // back up once they're gone
+    #[test]
+    fn piles_of_the_same_point() {
+        let mut tree = Quadtree::new(Rectangle::new(point(0.0, 0.0), point(100.0, 100.0)));
+        let ids: Vec<ItemId> = (0..100).map(|index| tree.insert_point(point(1.0, 1.0), index)).collect();
+        assert_eq!(tree.query_point(point(1.0, 1.0)).len(), 100);
+        assert_eq!(branches(&tree.root), MAX_DEPTH);
+        for id in ids {
+            tree.remove(id);
+        }
+        assert_eq!(branches(&tree.root), 0);
+    }
+
+    // The tree gives the same answers as checking every rectangle, through
+    // inserts and removes in a random order
+    #[test]
+    fn matches_a_linear_scan() {
+        let mut random = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        let world = Rectangle::new(point(0.0, 0.0), point(10_000.0, 10_000.0));
+        // Some rectangles poke out of the world and go in the outside list
+        let mut rectangles = scattered(3, 3_000);
+        for rectangle in rectangles.iter_mut() {
+            if random.below(20) == 0 {
+                let shift = point(9_950.0, 0.0);
+                *rectangle = Rectangle::new(rectangle.top_left() + shift, rectangle.bottom_right() + shift);
+            }
+        }
+
+        let mut tree = Quadtree::new(world);
+        let mut live: Vec<(ItemId, usize)> = Vec::new();
+        for round in 0..6 {
+            // Insert a batch, then remove about a third of what's there
+            for (index, &rectangle) in rectangles.iter().enumerate().skip(round * 500).take(500) {
+                live.push((tree.insert(rectangle, index), index));
+            }
+            for _ in 0..live.len() / 3 {
+                let (id, index) = live.swap_remove(random.below(live.len() as u64) as usize);
+                assert_eq!(tree.remove(id), Some(index));
+            }
+            assert_eq!(tree.len(), live.len());
+            assert_tidy(&tree);
+
+            let present: Vec<Rectangle> = live.iter().map(|&(_, index)| rectangles[index]).collect();
+            let mut present_indices: Vec<usize> = live.iter().map(|&(_, index)| index).collect();
+            present_indices.sort();
+            for _ in 0..100 {
+                let p = point(random.unit() * 10_500.0 - 250.0, random.unit() * 10_500.0 - 250.0);
+                let region = Rectangle::from_size(p, random.unit() * 400.0, random.unit() * 400.0);
+
+                let mut found: Vec<usize> = tree.query_region(&region).iter().map(|(_, &index)| index).collect();
+                found.sort();
+                let mut expected: Vec<usize> = scan_region(&present, &region).iter().map(|&at| live[at].1).collect();
+                expected.sort();
+                assert_eq!(found, expected);
+
+                let nearest = tree.nearest(p);
+                assert_eq!(nearest.map(|(_, _, distance)| distance), scan_nearest(&present, p));
+                let (id, &index, distance) = nearest.unwrap();
+                assert!(present_indices.binary_search(&index).is_ok());
+                assert_eq!(tree.get(id).map(|(bounds, _)| distance_to(bounds, p)), Some(distance));
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+#![allow(dead_code)] // Allow unused code
+
+// Pseudo random numbers for the chapters that need a pile of test data
+//
+// This is Marsaglia's xorshift64: three shifts and xors per number
+// It's nowhere near good enough for anything that has to be unpredictable,
+// but it's tiny, needs no crates, and the same seed always gives the same
+// numbers, so every run checks and measures exactly the same data
+
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    // The seed can be anything but 0, which would only ever give back 0
+    pub fn new(seed: u64) -> XorShift {
+        assert_ne!(seed, 0, "xorshift needs a seed other than 0");
+        XorShift { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // A number in 0..limit
+    // Taking the remainder leans slightly towards small numbers, which is
+    // fine for test data
+    pub fn below(&mut self, limit: u64) -> u64 {
+        self.next_u64() % limit
+    }
+
+    // A number in 0.0..1.0, in steps of one millionth
+    pub fn unit(&mut self) -> f32 {
+        self.below(1_000_000) as f32 / 1_000_000.0
+    }
+}
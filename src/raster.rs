@@ -0,0 +1,407 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::custom_types::Color;
+use crate::geometry::{Point, Rectangle};
+use crate::shapes::{Circle, Polygon, Shape};
+
+// A software rasterizer: fill shapes into an RGB image in memory and save
+// it as a binary PPM, with no GPU or image crate needed
+// Pixel (x, y) covers the square from (x, y) to (x + 1, y + 1), and y grows
+// downward like in the rest of the geometry chapters
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+// How many samples to take per pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Antialiasing {
+    // One sample in the middle of the pixel, so edges come out jagged
+    Off,
+    // An n by n grid of samples, edge pixels get the fraction that's covered
+    // n is capped at MAX_SAMPLES
+    Supersample(u32),
+}
+
+// 16 by 16 samples already gives 256 shades of coverage, which is all a u8
+// channel can show, so asking for more only costs time
+// The cap also keeps samples * samples well inside a u32
+pub const MAX_SAMPLES: u32 = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    // Row by row from the top left
+    pixels: Vec<Rgb>,
+    antialiasing: Antialiasing,
+}
+
+impl From<Color> for Rgb {
+    // The discriminant is 0xrrggbb
+    fn from(color: Color) -> Rgb {
+        let hex = color as u32;
+        Rgb {
+            r: (hex >> 16) as u8,
+            g: (hex >> 8) as u8,
+            b: hex as u8,
+        }
+    }
+}
+
+impl Rgb {
+    // Mix other over self, alpha 0 keeps self and 1 gives other
+    // A NaN alpha would turn every channel into NaN, which casts to 0 and
+    // paints black, so it counts as 0 and leaves self alone
+    pub fn blend(self, other: Rgb, alpha: f32) -> Rgb {
+        if alpha.is_nan() {
+            return self;
+        }
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mix = |under: u8, over: u8| (under as f32 + (over as f32 - under as f32) * alpha).round() as u8;
+        Rgb {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+        }
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize, background: Color) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Rgb::from(background); width * height],
+            antialiasing: Antialiasing::Off,
+        }
+    }
+
+    pub fn with_antialiasing(mut self, antialiasing: Antialiasing) -> Framebuffer {
+        self.antialiasing = antialiasing;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self, background: Color) {
+        self.pixels.fill(Rgb::from(background));
+    }
+
+    // Fill any shape, alpha 1 paints over what's there and anything less
+    // lets it show through
+    // Only the pixels under the shape's bounding box get looked at, and the
+    // shape's own contains() decides what's inside
+    pub fn fill(&mut self, shape: &dyn Shape, color: Color, alpha: f32) {
+        let bounds = shape.bounding_box();
+        // Clip to the image, anything off the edges is dropped
+        let left = (bounds.left().floor().max(0.0) as usize).min(self.width);
+        let top = (bounds.top().floor().max(0.0) as usize).min(self.height);
+        let right = (bounds.right().ceil().max(0.0) as usize).min(self.width);
+        let bottom = (bounds.bottom().ceil().max(0.0) as usize).min(self.height);
+
+        let color = Rgb::from(color);
+        for y in top..bottom {
+            for x in left..right {
+                let coverage = self.coverage(shape, x, y);
+                if coverage > 0.0 {
+                    let pixel = &mut self.pixels[y * self.width + x];
+                    *pixel = pixel.blend(color, alpha * coverage);
+                }
+            }
+        }
+    }
+
+    pub fn fill_rectangle(&mut self, rectangle: &Rectangle, color: Color, alpha: f32) {
+        self.fill(rectangle, color, alpha);
+    }
+
+    pub fn fill_circle(&mut self, circle: &Circle, color: Color, alpha: f32) {
+        self.fill(circle, color, alpha);
+    }
+
+    pub fn fill_polygon(&mut self, polygon: &Polygon, color: Color, alpha: f32) {
+        self.fill(polygon, color, alpha);
+    }
+
+    // How much of pixel (x, y) the shape covers, from 0 to 1
+    fn coverage(&self, shape: &dyn Shape, x: usize, y: usize) -> f32 {
+        let samples = match self.antialiasing {
+            Antialiasing::Off => 1,
+            Antialiasing::Supersample(samples) => samples.clamp(1, MAX_SAMPLES),
+        };
+        // Samples sit in the middle of each cell of an n by n grid
+        let step = 1.0 / samples as f32;
+        let mut inside = 0;
+        for row in 0..samples {
+            for column in 0..samples {
+                let sample = Point::new(
+                    x as f32 + (column as f32 + 0.5) * step,
+                    y as f32 + (row as f32 + 0.5) * step,
+                );
+                if shape.contains(sample) {
+                    inside += 1;
+                }
+            }
+        }
+        inside as f32 / (samples * samples) as f32
+    }
+
+    // Binary PPM: a short text header and then three bytes per pixel
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let bytes: Vec<u8> = self.pixels.iter().flat_map(|pixel| [pixel.r, pixel.g, pixel.b]).collect();
+        writer.write_all(&bytes)?;
+        writer.flush()
+    }
+
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_ppm(BufWriter::new(File::create(path)?))
+    }
+}
+
+pub fn run() {
+    let point = |x, y| Point::new(x, y);
+
+    // Without antialiasing a pixel is either in or out
+    let mut image = Framebuffer::new(8, 8, Color::White);
+    image.fill_rectangle(&Rectangle::new(point(2.0, 2.0), point(4.0, 5.0)), Color::Red, 1.0);
+    println!("Pixel (2, 2) after a solid red fill: {:?}", image.get(2, 2).unwrap());
+
+    // Half transparent blue over the red mixes them
+    image.fill_rectangle(&Rectangle::new(point(3.0, 0.0), point(8.0, 8.0)), Color::Blue, 0.5);
+    println!("and (3, 3) under half transparent blue: {:?}", image.get(3, 3).unwrap());
+
+    // A circle's edge pixels get partial coverage with antialiasing on
+    let mut antialiased = Framebuffer::new(32, 32, Color::White).with_antialiasing(Antialiasing::Supersample(4));
+    antialiased.fill_circle(&Circle::new(point(16.0, 16.0), 10.0).unwrap(), Color::Blue, 1.0);
+    println!("An edge pixel of the circle: {:?}", antialiased.get(25, 19).unwrap());
+    let triangle = Polygon::new(vec![point(2.0, 30.0), point(16.0, 2.0), point(30.0, 30.0)]).unwrap();
+    antialiased.fill_polygon(&triangle, Color::Red, 0.5);
+
+    let path = std::env::temp_dir().join("rbe-raster.ppm");
+    match antialiased.save_ppm(&path) {
+        Ok(()) => println!("Wrote {}x{} image to {}", antialiased.width(), antialiased.height(), path.display()),
+        Err(error) => println!("Couldn't write {}: {}", path.display(), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn count(image: &Framebuffer, keep: impl Fn(Rgb) -> bool) -> usize {
+        image.pixels.iter().filter(|&&pixel| keep(pixel)).count()
+    }
+
+    fn white() -> Rgb {
+        Rgb::from(Color::White)
+    }
+
+    #[test]
+    fn colors_come_from_their_hex_codes() {
+        assert_eq!(Rgb::from(Color::Red), Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(Rgb::from(Color::Green), Rgb { r: 0, g: 255, b: 0 });
+        assert_eq!(Rgb::from(Color::Black), Rgb::default());
+    }
+
+    #[test]
+    fn blending() {
+        let red = Rgb::from(Color::Red);
+        let blue = Rgb::from(Color::Blue);
+        assert_eq!(red.blend(blue, 0.0), red);
+        assert_eq!(red.blend(blue, 1.0), blue);
+        assert_eq!(red.blend(blue, 0.5), Rgb { r: 128, g: 0, b: 128 });
+        // Out of range alphas are clamped
+        assert_eq!(red.blend(blue, -3.0), red);
+        assert_eq!(red.blend(blue, 7.0), blue);
+        assert_eq!(red.blend(blue, f32::INFINITY), blue);
+    }
+
+    #[test]
+    fn nan_alpha_leaves_pixels_alone() {
+        let red = Rgb::from(Color::Red);
+        assert_eq!(red.blend(Rgb::from(Color::Blue), f32::NAN), red);
+
+        let mut image = Framebuffer::new(4, 4, Color::White);
+        image.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(4.0, 4.0)), Color::Blue, f32::NAN);
+        assert_eq!(count(&image, |pixel| pixel == white()), 16);
+    }
+
+    // Without antialiasing a pixel is either in or out
+    #[test]
+    fn aliased_fill_covers_whole_pixels() {
+        let red = Rgb::from(Color::Red);
+        let mut image = Framebuffer::new(8, 8, Color::White);
+        image.fill_rectangle(&Rectangle::new(point(2.0, 2.0), point(4.0, 5.0)), Color::Red, 1.0);
+        assert_eq!(count(&image, |pixel| pixel == red), 2 * 3);
+        assert_eq!(image.get(2, 2), Some(red));
+        assert_eq!(image.get(3, 4), Some(red));
+        assert_eq!(image.get(4, 2), Some(white()));
+        assert_eq!(image.get(2, 5), Some(white()));
+        assert_eq!(image.get(8, 0), None);
+        assert_eq!(image.get(0, 8), None);
+    }
+
+    // Half transparent blue over the red mixes them, and over white goes pale
+    #[test]
+    fn partial_alpha_lets_the_background_through() {
+        let mut image = Framebuffer::new(8, 8, Color::White);
+        image.fill_rectangle(&Rectangle::new(point(2.0, 2.0), point(4.0, 5.0)), Color::Red, 1.0);
+        image.fill_rectangle(&Rectangle::new(point(3.0, 0.0), point(8.0, 8.0)), Color::Blue, 0.5);
+        assert_eq!(image.get(3, 3), Some(Rgb { r: 128, g: 0, b: 128 }));
+        assert_eq!(image.get(7, 7), Some(Rgb { r: 128, g: 128, b: 255 }));
+        assert_eq!(image.get(2, 3), Some(Rgb::from(Color::Red)));
+    }
+
+    // Shapes hanging off the edge get clipped instead of panicking
+    #[test]
+    fn shapes_off_the_edge_are_clipped() {
+        let green = Rgb::from(Color::Green);
+        let mut image = Framebuffer::new(8, 8, Color::White);
+        image.fill_circle(&Circle::new(point(-2.0, 10.0), 4.0).unwrap(), Color::Green, 1.0);
+        assert_eq!(image.get(0, 7), Some(green));
+        assert_eq!(image.get(7, 0), Some(white()));
+
+        // Entirely outside, on every side
+        for offset in [point(-100.0, 0.0), point(100.0, 0.0), point(0.0, -100.0), point(0.0, 100.0)] {
+            let away = Rectangle::new(point(0.0, 0.0) + offset, point(8.0, 8.0) + offset);
+            image.fill_rectangle(&away, Color::Red, 1.0);
+        }
+        // And covering all of it
+        image.fill_rectangle(&Rectangle::new(point(-1e9, -1e9), point(1e9, 1e9)), Color::Blue, 1.0);
+        assert_eq!(count(&image, |pixel| pixel == Rgb::from(Color::Blue)), 64);
+    }
+
+    #[test]
+    fn empty_images_are_fine() {
+        let mut image = Framebuffer::new(0, 0, Color::White);
+        image.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(4.0, 4.0)), Color::Red, 1.0);
+        assert_eq!(image.get(0, 0), None);
+    }
+
+    // With antialiasing a pixel half under a shape comes out half way
+    #[test]
+    fn antialiasing_shades_by_coverage() {
+        let mut smooth = Framebuffer::new(4, 4, Color::White).with_antialiasing(Antialiasing::Supersample(4));
+        smooth.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(1.5, 4.0)), Color::Black, 1.0);
+        assert_eq!(smooth.get(0, 0), Some(Rgb::from(Color::Black)));
+        assert_eq!(smooth.get(1, 0), Some(Rgb { r: 128, g: 128, b: 128 }));
+        assert_eq!(smooth.get(2, 0), Some(white()));
+
+        // A quarter of a pixel
+        let mut quarter = Framebuffer::new(2, 2, Color::White).with_antialiasing(Antialiasing::Supersample(2));
+        quarter.fill_rectangle(&Rectangle::new(point(0.5, 0.5), point(1.0, 1.0)), Color::Black, 1.0);
+        assert_eq!(quarter.get(0, 0), Some(Rgb { r: 191, g: 191, b: 191 }));
+    }
+
+    // A circle's edge pixels get partial coverage, the middle is solid
+    #[test]
+    fn only_antialiased_circles_have_soft_edges() {
+        let blue = Rgb::from(Color::Blue);
+        let circle = Circle::new(point(16.0, 16.0), 10.0).unwrap();
+        let mut jagged = Framebuffer::new(32, 32, Color::White);
+        let mut antialiased = Framebuffer::new(32, 32, Color::White).with_antialiasing(Antialiasing::Supersample(4));
+        jagged.fill_circle(&circle, Color::Blue, 1.0);
+        antialiased.fill_circle(&circle, Color::Blue, 1.0);
+
+        let is_partial = |pixel: Rgb| pixel != white() && pixel != blue;
+        assert_eq!(count(&jagged, is_partial), 0);
+        assert!(count(&antialiased, is_partial) > 0);
+        assert_eq!(antialiased.get(16, 16), Some(blue));
+        assert_eq!(antialiased.get(0, 0), Some(white()));
+
+        // Either way the filled area is close to pi r^2
+        let area = std::f32::consts::PI * 100.0;
+        assert!((count(&jagged, |pixel| pixel == blue) as f32 - area).abs() < area * 0.05);
+        let shaded: f32 = antialiased.pixels.iter().map(|pixel| (255 - pixel.r) as f32 / 255.0).sum();
+        assert!((shaded - area).abs() < area * 0.02);
+    }
+
+    // Supersample(0) is the same as one sample, and anything past the cap is
+    // the same as the cap instead of overflowing samples * samples
+    #[test]
+    fn sample_counts_are_clamped() {
+        let rectangle = Rectangle::new(point(0.0, 0.0), point(1.5, 2.0));
+        let draw = |antialiasing| {
+            let mut image = Framebuffer::new(2, 2, Color::White).with_antialiasing(antialiasing);
+            image.fill_rectangle(&rectangle, Color::Black, 1.0);
+            image.pixels
+        };
+        assert_eq!(draw(Antialiasing::Supersample(0)), draw(Antialiasing::Off));
+        let capped = draw(Antialiasing::Supersample(MAX_SAMPLES));
+        assert_eq!(draw(Antialiasing::Supersample(65536)), capped);
+        assert_eq!(draw(Antialiasing::Supersample(u32::MAX)), capped);
+    }
+
+    #[test]
+    fn polygons_fill_inside_only() {
+        let triangle = Polygon::new(vec![point(0.0, 0.0), point(8.0, 0.0), point(0.0, 8.0)]).unwrap();
+        let mut image = Framebuffer::new(8, 8, Color::White);
+        image.fill_polygon(&triangle, Color::Red, 1.0);
+        assert_eq!(image.get(0, 0), Some(Rgb::from(Color::Red)));
+        assert_eq!(image.get(7, 7), Some(white()));
+        // Everything above the diagonal, and the diagonal itself since the
+        // middle of those pixels is right on the edge
+        assert_eq!(count(&image, |pixel| pixel == Rgb::from(Color::Red)), 8 * 9 / 2);
+    }
+
+    #[test]
+    fn clear_resets_every_pixel() {
+        let mut image = Framebuffer::new(3, 3, Color::White);
+        image.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(2.0, 2.0)), Color::Red, 1.0);
+        image.clear(Color::Green);
+        assert_eq!(count(&image, |pixel| pixel == Rgb::from(Color::Green)), 9);
+    }
+
+    // The PPM is the header followed by the raw bytes
+    #[test]
+    fn ppm_layout() {
+        let mut smooth = Framebuffer::new(4, 4, Color::White).with_antialiasing(Antialiasing::Supersample(4));
+        smooth.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(1.5, 4.0)), Color::Black, 1.0);
+        let mut ppm = Vec::new();
+        smooth.write_ppm(&mut ppm).unwrap();
+        let header = b"P6\n4 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 4 * 4 * 3);
+        assert_eq!(&ppm[header.len()..header.len() + 6], &[0, 0, 0, 128, 128, 128]);
+    }
+
+    #[test]
+    fn save_writes_the_same_bytes() {
+        let mut image = Framebuffer::new(3, 2, Color::Blue);
+        image.fill_rectangle(&Rectangle::new(point(0.0, 0.0), point(1.0, 1.0)), Color::Red, 1.0);
+        let path = std::env::temp_dir().join(format!("rbe-raster-test-{}.ppm", std::process::id()));
+        image.save_ppm(&path).unwrap();
+        let saved = std::fs::read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut expected = Vec::new();
+        image.write_ppm(&mut expected).unwrap();
+        assert_eq!(saved.unwrap(), expected);
+    }
+}
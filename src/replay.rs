@@ -0,0 +1,529 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::custom_types::{inspect, WebEvent};
+use crate::event_log::ParseEventError;
+use crate::events::EventBus;
+
+// Record a stream of WebEvents with the time each one happened and play it
+// back later
+//
+// A session file is the event_log format with the number of microseconds
+// since recording started in front of every event:
+//
+//     0 load
+//     150000 key x
+//     420000 click 20 80
+//
+// Times never go backwards, and replay always hands the events over in
+// the recorded order, so the same file always gives the same calls
+// Only how long replay waits in between depends on the speed
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorded {
+    // Time since the recording started
+    pub at: Duration,
+    pub event: WebEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Session {
+    events: Vec<Recorded>,
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    // Instant is monotonic, so the clock changing can't mess up the times
+    start: Instant,
+    session: Session,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    // Wait exactly as long as the recording did
+    RealTime,
+    // 2.0 plays twice as fast, 0.5 at half speed
+    // It has to be positive and finite, replay refuses anything else
+    Multiple(f64),
+    // Don't wait at all
+    AsFastAsPossible,
+}
+
+// A Speed::Multiple that's zero, negative, infinite or NaN
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidSpeed(pub f64);
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    // The event after the time didn't parse, columns count from the start
+    // of the whole line
+    Parse(ParseEventError),
+    InvalidTimestamp { line: usize, text: String },
+    // A time earlier than the line before it
+    OutOfOrder { line: usize },
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    pub fn events(&self) -> &[Recorded] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // How long the session took from start to the last event
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |recorded| recorded.at)
+    }
+
+    // Add an event at a given time
+    // Times are cut down to whole microseconds, which is all a session file
+    // holds, so a session saved and loaded again is still equal
+    // A time before the last event's is moved up to it, so times never go
+    // backwards
+    pub fn push(&mut self, at: Duration, event: WebEvent) {
+        let at = Duration::from_micros(at.as_micros() as u64).max(self.duration());
+        self.events.push(Recorded { at, event });
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "# microseconds since the start, then the event")?;
+        for recorded in &self.events {
+            writeln!(writer, "{} {}", recorded.at.as_micros(), recorded.event)?;
+        }
+        writer.flush()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    // Reads a line at a time, so the whole file is never in memory as text
+    pub fn read<R: BufRead>(reader: R) -> Result<Session, SessionError> {
+        let mut session = Session::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = index + 1;
+            let content = line.trim_start_matches([' ', '\t']);
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+
+            let (time, rest) = content.split_once([' ', '\t']).unwrap_or((content, ""));
+            let at = time
+                .parse()
+                .map(Duration::from_micros)
+                .map_err(|_| SessionError::InvalidTimestamp {
+                    line: line_number,
+                    text: time.to_owned(),
+                })?;
+            if at < session.duration() {
+                return Err(SessionError::OutOfOrder { line: line_number });
+            }
+            let event = rest.parse::<WebEvent>().map_err(|mut error| {
+                // The event parser only saw the rest of the line
+                error.line = line_number;
+                error.column += line.chars().count() - rest.chars().count();
+                error
+            })?;
+            session.events.push(Recorded { at, event });
+        }
+        Ok(session)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, SessionError> {
+        Session::read(BufReader::new(File::open(path)?))
+    }
+
+    // Hand every event to handler, waiting between them as speed says
+    // Each wait is measured from when replay started rather than from the
+    // last event, so a slow handler doesn't make the rest of the replay drift
+    // Returns how many events were replayed, or an error before replaying
+    // anything if the speed makes no sense
+    pub fn replay<F: FnMut(&WebEvent)>(&self, speed: Speed, mut handler: F) -> Result<usize, InvalidSpeed> {
+        if let Speed::Multiple(multiple) = speed {
+            if !(multiple.is_finite() && multiple > 0.0) {
+                return Err(InvalidSpeed(multiple));
+            }
+        }
+
+        let start = Instant::now();
+        for recorded in &self.events {
+            if let Some(wait) = speed.due(recorded.at).and_then(|due| due.checked_sub(start.elapsed())) {
+                thread::sleep(wait);
+            }
+            handler(&recorded.event);
+        }
+        Ok(self.events.len())
+    }
+
+    // Replay through every handler on a bus
+    pub fn replay_into(&self, speed: Speed, bus: &mut EventBus) -> Result<usize, InvalidSpeed> {
+        self.replay(speed, |event| {
+            bus.dispatch(event);
+        })
+    }
+}
+
+impl Speed {
+    // How long after replay starts an event recorded at `at` should go out,
+    // None if it goes out straight away
+    fn due(self, at: Duration) -> Option<Duration> {
+        match self {
+            Speed::RealTime => Some(at),
+            // A tiny enough multiple makes the wait too long for a Duration,
+            // which is as good as waiting forever
+            Speed::Multiple(multiple) => {
+                Some(Duration::try_from_secs_f64(at.as_secs_f64() / multiple).unwrap_or(Duration::MAX))
+            },
+            Speed::AsFastAsPossible => None,
+        }
+    }
+}
+
+impl Recorder {
+    // The clock starts now
+    pub fn start() -> Recorder {
+        Recorder {
+            start: Instant::now(),
+            session: Session::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: WebEvent) {
+        self.session.push(self.start.elapsed(), event);
+    }
+
+    pub fn finish(self) -> Session {
+        self.session
+    }
+}
+
+impl fmt::Display for InvalidSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't replay at {} times the recorded speed, it has to be positive", self.0)
+    }
+}
+
+impl Error for InvalidSpeed {}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Io(error) => write!(f, "couldn't read the session: {}", error),
+            SessionError::Parse(error) => error.fmt(f),
+            SessionError::InvalidTimestamp { line, text } => {
+                write!(f, "line {}: \"{}\" is not a time in microseconds", line, text)
+            },
+            SessionError::OutOfOrder { line } => write!(f, "line {}: time goes backwards", line),
+        }
+    }
+}
+
+impl Error for SessionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SessionError::Io(error) => Some(error),
+            SessionError::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SessionError {
+    fn from(error: io::Error) -> Self {
+        SessionError::Io(error)
+    }
+}
+
+impl From<ParseEventError> for SessionError {
+    fn from(error: ParseEventError) -> Self {
+        SessionError::Parse(error)
+    }
+}
+
+pub fn run() {
+    // Record a short session live
+    let mut recorder = Recorder::start();
+    recorder.record(WebEvent::PageLoad);
+    thread::sleep(Duration::from_millis(10));
+    recorder.record(WebEvent::KeyPress('h'));
+    recorder.record(WebEvent::KeyPress('i'));
+    thread::sleep(Duration::from_millis(10));
+    recorder.record(WebEvent::Paste("line one\nline two".to_owned()));
+    recorder.record(WebEvent::Click { x: 20, y: 80 });
+    recorder.record(WebEvent::PageUnload);
+    let live = recorder.finish();
+
+    // Save it and load it back
+    let path = std::env::temp_dir().join(format!("rbe-session-{}.log", std::process::id()));
+    let loaded = match live.save(&path).map_err(SessionError::from).and_then(|()| Session::load(&path)) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            println!("Couldn't save and load {}: {}", path.display(), error);
+            live
+        },
+    };
+    let _ = std::fs::remove_file(&path);
+    println!("Recorded {} events over {:?}", loaded.len(), loaded.duration());
+
+    // Real time takes as long as the recording, a multiple takes less and
+    // as fast as possible doesn't wait at all
+    // The handler sees the same events in the same order at every speed
+    let timed = |speed| {
+        let start = Instant::now();
+        let count = loaded.replay(speed, |_| {}).unwrap();
+        (start.elapsed(), count)
+    };
+    let (real_time, count) = timed(Speed::RealTime);
+    let (doubled, _) = timed(Speed::Multiple(2.0));
+    let (fast, _) = timed(Speed::AsFastAsPossible);
+    println!("Replayed {} events in {:?} at real time, {:?} at 2x, {:?} flat out", count, real_time, doubled, fast);
+
+    // A session written by hand works as a regression test, since replaying
+    // it always makes the same calls (see the tests)
+    let session = Session::read(SCRIPT.as_bytes()).unwrap();
+    session.replay(Speed::AsFastAsPossible, inspect).unwrap();
+
+    // Broken files say where
+    println!("{}", Session::read("0 load\n  10 click 1 two\n".as_bytes()).unwrap_err());
+    println!("{}", Session::read("50 load\n10 unload\n".as_bytes()).unwrap_err());
+}
+
+// A hand written session, shared by run() and the tests
+const SCRIPT: &str = "\
+# typing into a search box
+0 load
+120000 key r
+180000 key s
+240000 paste \"t\\nexample\"
+900000 click 20 80
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Propagation;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn session() -> Session {
+        let mut session = Session::new();
+        session.push(Duration::ZERO, WebEvent::PageLoad);
+        session.push(Duration::from_secs(1), WebEvent::PageUnload);
+        session
+    }
+
+    // One of everything, a few microseconds apart so even real time
+    // replays are quick
+    fn busy_session() -> Session {
+        let mut session = Session::new();
+        let events = [
+            WebEvent::PageLoad,
+            WebEvent::KeyPress('h'),
+            WebEvent::KeyPress(' '),
+            WebEvent::KeyPress('"'),
+            WebEvent::Paste("line one\nline two".to_owned()),
+            WebEvent::Paste(String::new()),
+            WebEvent::Click { x: -20, y: 80 },
+            WebEvent::PageUnload,
+        ];
+        for (index, event) in events.into_iter().enumerate() {
+            session.push(Duration::from_micros(index as u64 * 250), event);
+        }
+        session
+    }
+
+    fn written(session: &Session) -> String {
+        let mut out = Vec::new();
+        session.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn replayed(session: &Session, speed: Speed) -> Vec<WebEvent> {
+        let mut seen = Vec::new();
+        assert_eq!(session.replay(speed, |event| seen.push(event.clone())), Ok(session.len()));
+        seen
+    }
+
+    // What typing into the SCRIPT session leaves in the box
+    fn transcript(session: &Session) -> String {
+        let typed = Rc::new(RefCell::new(String::new()));
+        let mut bus = EventBus::new();
+        {
+            let typed = Rc::clone(&typed);
+            bus.subscribe_all(0, move |event| {
+                match event {
+                    WebEvent::KeyPress(c) => typed.borrow_mut().push(*c),
+                    WebEvent::Paste(text) => typed.borrow_mut().push_str(text),
+                    _ => {},
+                }
+                Propagation::Continue
+            });
+        }
+        session.replay_into(Speed::AsFastAsPossible, &mut bus).unwrap();
+        // The bus owns the other handle to typed
+        drop(bus);
+        Rc::try_unwrap(typed).unwrap().into_inner()
+    }
+
+    #[test]
+    fn sessions_round_trip_through_text() {
+        let session = busy_session();
+        let text = written(&session);
+        assert!(text.starts_with("# microseconds"));
+        assert!(text.contains("\n250 key h\n"));
+        assert_eq!(Session::read(text.as_bytes()).unwrap(), session);
+        assert_eq!(Session::read("".as_bytes()).unwrap(), Session::new());
+    }
+
+    #[test]
+    fn sessions_round_trip_through_a_file() {
+        let session = busy_session();
+        let path = std::env::temp_dir().join(format!("rbe-session-test-{}.log", std::process::id()));
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), session);
+        assert!(matches!(Session::load(&path), Err(SessionError::Io(_))));
+    }
+
+    // A session file only holds whole microseconds
+    #[test]
+    fn times_are_cut_to_microseconds() {
+        let mut session = Session::new();
+        session.push(Duration::from_nanos(1_999), WebEvent::PageLoad);
+        assert_eq!(session.duration(), Duration::from_micros(1));
+        assert_eq!(Session::read(written(&session).as_bytes()).unwrap(), session);
+    }
+
+    #[test]
+    fn recorded_times_never_go_backwards() {
+        let mut recorder = Recorder::start();
+        for c in "recording".chars() {
+            recorder.record(WebEvent::KeyPress(c));
+        }
+        let session = recorder.finish();
+        assert_eq!(session.len(), 9);
+        assert!(session.events().windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        // Pushing an event in the past moves it up to the last time
+        let mut session = Session::new();
+        session.push(Duration::from_secs(2), WebEvent::PageLoad);
+        session.push(Duration::from_secs(1), WebEvent::PageUnload);
+        assert_eq!(session.events()[1].at, Duration::from_secs(2));
+    }
+
+    // Whatever the speed, the handler sees the same events in the same order
+    #[test]
+    fn every_speed_replays_the_same_events() {
+        let session = busy_session();
+        let expected: Vec<WebEvent> = session.events().iter().map(|recorded| recorded.event.clone()).collect();
+        for speed in [Speed::RealTime, Speed::Multiple(2.0), Speed::Multiple(0.5), Speed::AsFastAsPossible] {
+            assert_eq!(replayed(&session, speed), expected, "{:?}", speed);
+        }
+        assert_eq!(replayed(&Session::new(), Speed::RealTime), vec![]);
+    }
+
+    // Replaying a hand written session into a bus has to give this exact
+    // transcript every time
+    #[test]
+    fn scripts_replay_deterministically() {
+        let session = Session::read(SCRIPT.as_bytes()).unwrap();
+        assert_eq!(session.len(), 5);
+        assert_eq!(session.duration(), Duration::from_millis(900));
+        assert_eq!(transcript(&session), "rst\nexample");
+        assert_eq!(transcript(&session), transcript(&session));
+    }
+
+    // Blank lines and comments are skipped but still counted
+    #[test]
+    fn comments_and_blank_lines() {
+        let session = Session::read("# start\n\n   \n0 load\n\t5\tkey x\n".as_bytes()).unwrap();
+        assert_eq!(session.len(), 2);
+        assert_eq!(session.events()[1].at, Duration::from_micros(5));
+        let error = Session::read("# start\n\n0 load\nnope\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, SessionError::InvalidTimestamp { line: 4, .. }));
+    }
+
+    // Broken files say where
+    #[test]
+    fn bad_timestamps() {
+        for time in ["soon", "-5", "1.5", "99999999999999999999999"] {
+            let error = Session::read(format!("0 load\n{} key x\n", time).as_bytes()).unwrap_err();
+            match error {
+                SessionError::InvalidTimestamp { line, text } => assert_eq!((line, text.as_str()), (2, time)),
+                other => panic!("expected a bad timestamp, got {:?}", other),
+            }
+        }
+        let error = Session::read("0 load\nsoon key x\n".as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: \"soon\" is not a time in microseconds");
+    }
+
+    #[test]
+    fn out_of_order_times() {
+        let error = Session::read("50 load\n10 unload\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, SessionError::OutOfOrder { line: 2 }));
+        assert_eq!(error.to_string(), "line 2: time goes backwards");
+        // The same time twice is fine
+        assert_eq!(Session::read("50 load\n50 unload\n".as_bytes()).unwrap().len(), 2);
+    }
+
+    // Columns count from the start of the whole line, time included
+    #[test]
+    fn event_errors_point_into_the_line() {
+        let error = Session::read("0 load\n  10 click 1 two\n".as_bytes()).unwrap_err();
+        match &error {
+            SessionError::Parse(parse) => assert_eq!((parse.line, parse.column), (2, 14)),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(error.source().is_some());
+        // A time with nothing after it isn't an event
+        assert!(matches!(Session::read("10\n".as_bytes()), Err(SessionError::Parse(_))));
+    }
+
+    #[test]
+    fn nonsense_multiples_are_refused_before_replaying() {
+        for multiple in [0.0, -0.0, -2.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut replayed = 0;
+            let result = session().replay(Speed::Multiple(multiple), |_| replayed += 1);
+            assert!(matches!(result, Err(InvalidSpeed(m)) if m.to_bits() == multiple.to_bits()));
+            assert_eq!(replayed, 0);
+        }
+    }
+
+    #[test]
+    fn huge_multiples_barely_wait() {
+        let start = Instant::now();
+        assert_eq!(session().replay(Speed::Multiple(1e300), |_| {}), Ok(2));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn due_times_scale_with_the_multiple() {
+        let second = Duration::from_secs(1);
+        assert_eq!(Speed::RealTime.due(second), Some(second));
+        assert_eq!(Speed::Multiple(2.0).due(second), Some(second / 2));
+        assert_eq!(Speed::Multiple(0.5).due(second), Some(second * 2));
+        assert_eq!(Speed::AsFastAsPossible.due(second), None);
+        // Too slow to fit in a Duration, rather than a panic
+        assert_eq!(Speed::Multiple(1e-300).due(second), Some(Duration::MAX));
+    }
+}
@@ -0,0 +1,365 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::f32::consts::PI;
+use std::fmt;
+
+use crate::computational_geometry::{self, Containment};
+use crate::geometry::{Point, Rectangle};
+
+// Everything that can be drawn in a scene
+// The methods only take &self or &mut self and never return Self, so the
+// trait stays object safe and a Vec<Box<dyn Shape>> can mix any of them
+pub trait Shape: fmt::Debug {
+    fn area(&self) -> f32;
+
+    fn perimeter(&self) -> f32;
+
+    // The smallest axis-aligned rectangle the shape fits in
+    fn bounding_box(&self) -> Rectangle;
+
+    // Points on the outline count as inside
+    fn contains(&self, point: Point) -> bool;
+
+    // Move the shape by offset
+    fn translate(&mut self, offset: Point);
+}
+
+// Total area of a scene, overlapping shapes are counted twice
+pub fn total_area(shapes: &[Box<dyn Shape>]) -> f32 {
+    shapes.iter().map(|shape| shape.area()).sum()
+}
+
+// Bounding box of a whole scene, or None if it's empty
+pub fn scene_bounds(shapes: &[Box<dyn Shape>]) -> Option<Rectangle> {
+    shapes
+        .iter()
+        .map(|shape| shape.bounding_box())
+        .reduce(|bounds, next| bounds.union(&next))
+}
+
+// The Circle from the notes on converting to strings, now with a center
+// so it can live in a scene
+// The fields are private so every Circle goes through new() and has a
+// radius that makes sense
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    center: Point,
+    radius: f32,
+}
+
+// Three corners in any order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub a: Point,
+    pub b: Point,
+    pub c: Point,
+}
+
+// A closed polygon, the last vertex connects back to the first
+// Vertices can go either way round
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Shape for Rectangle {
+    // Rectangle already has all of these as its own methods
+    fn area(&self) -> f32 {
+        Rectangle::area(self)
+    }
+
+    fn perimeter(&self) -> f32 {
+        Rectangle::perimeter(self)
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        *self
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        Rectangle::contains(self, point)
+    }
+
+    fn translate(&mut self, offset: Point) {
+        *self = Rectangle::new(self.top_left() + offset, self.bottom_right() + offset);
+    }
+}
+
+impl Circle {
+    // A negative or NaN radius would give a negative or NaN area and a
+    // bounding box inside out, and an infinite one isn't much better, so
+    // those are refused
+    // A radius of 0.0 is fine, it's just a point
+    pub fn new(center: Point, radius: f32) -> Option<Circle> {
+        if radius.is_finite() && radius >= 0.0 {
+            Some(Circle { center, radius })
+        } else {
+            None
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        self.center
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl fmt::Display for Circle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Circle of radius {}", self.radius)
+    }
+}
+
+impl Shape for Circle {
+    fn area(&self) -> f32 {
+        PI * self.radius * self.radius
+    }
+
+    fn perimeter(&self) -> f32 {
+        2.0 * PI * self.radius
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        let corner = Point::new(self.radius, self.radius);
+        Rectangle::new(self.center - corner, self.center + corner)
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        self.center.distance(point) <= self.radius
+    }
+
+    fn translate(&mut self, offset: Point) {
+        self.center += offset;
+    }
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point) -> Triangle {
+        Triangle { a, b, c }
+    }
+}
+
+impl Shape for Triangle {
+    // Half the parallelogram spanned by two of the sides
+    fn area(&self) -> f32 {
+        (self.b - self.a).cross(self.c - self.a).abs() / 2.0
+    }
+
+    fn perimeter(&self) -> f32 {
+        self.a.distance(self.b) + self.b.distance(self.c) + self.c.distance(self.a)
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        // A triangle always has points so this can't be None
+        Rectangle::bounding_box([self.a, self.b, self.c]).unwrap()
+    }
+
+    // The point is inside if it's on the same side of all three edges
+    fn contains(&self, point: Point) -> bool {
+        let sides = [
+            (self.b - self.a).cross(point - self.a),
+            (self.c - self.b).cross(point - self.b),
+            (self.a - self.c).cross(point - self.c),
+        ];
+        let has_negative = sides.iter().any(|&side| side < 0.0);
+        let has_positive = sides.iter().any(|&side| side > 0.0);
+        !(has_negative && has_positive)
+    }
+
+    fn translate(&mut self, offset: Point) {
+        self.a += offset;
+        self.b += offset;
+        self.c += offset;
+    }
+}
+
+impl Polygon {
+    // A polygon needs at least three vertices to enclose anything
+    pub fn new(vertices: Vec<Point>) -> Option<Polygon> {
+        if vertices.len() < 3 {
+            None
+        } else {
+            Some(Polygon { vertices })
+        }
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+}
+
+impl Shape for Polygon {
+    fn area(&self) -> f32 {
+        computational_geometry::polygon_area(&self.vertices)
+    }
+
+    fn perimeter(&self) -> f32 {
+        computational_geometry::edges(&self.vertices)
+            .map(|edge| edge.length())
+            .sum()
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        // new() makes sure there are vertices
+        Rectangle::bounding_box(self.vertices.iter().copied()).unwrap()
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        computational_geometry::point_in_polygon(point, &self.vertices) != Containment::Outside
+    }
+
+    fn translate(&mut self, offset: Point) {
+        for vertex in &mut self.vertices {
+            *vertex += offset;
+        }
+    }
+}
+
+pub fn run() {
+    let point = |x, y| Point::new(x, y);
+
+    let mut scene = scene();
+    for shape in &scene {
+        println!("{:?}: area {}, perimeter {}", shape, shape.area(), shape.perimeter());
+    }
+    println!("Total area of the scene: {}", total_area(&scene));
+    println!("Bounds of the scene: {:?}", scene_bounds(&scene));
+
+    // Every shape can answer "is this point inside you"
+    let hits: Vec<bool> = scene.iter().map(|shape| shape.contains(point(1.5, 1.5))).collect();
+    println!("Which shapes (1.5, 1.5) is in: {:?}", hits);
+
+    // Moving the whole scene moves every bounding box with it
+    for shape in &mut scene {
+        shape.translate(point(-1.0, 2.0));
+    }
+    println!("Bounds after moving: {:?}", scene_bounds(&scene));
+
+    if let Some(circle) = Circle::new(point(0.0, 0.0), 6.0) {
+        println!("{}", circle);
+    }
+    println!("A circle with radius -1: {:?}", Circle::new(point(0.0, 0.0), -1.0));
+}
+
+// One of every shape
+fn scene() -> Vec<Box<dyn Shape>> {
+    let point = |x, y| Point::new(x, y);
+    vec![
+        Box::new(Rectangle::new(point(0.0, 0.0), point(4.0, 3.0))),
+        Box::new(Circle::new(point(10.0, 10.0), 1.0).unwrap()),
+        Box::new(Triangle::new(point(0.0, 0.0), point(4.0, 0.0), point(0.0, 3.0))),
+        // An L shape, which isn't convex
+        Box::new(
+            Polygon::new(vec![
+                point(0.0, 0.0),
+                point(2.0, 0.0),
+                point(2.0, 1.0),
+                point(1.0, 1.0),
+                point(1.0, 2.0),
+                point(0.0, 2.0),
+            ])
+            .unwrap(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn areas_and_perimeters() {
+        let scene = scene();
+        let areas: Vec<f32> = scene.iter().map(|shape| shape.area()).collect();
+        let perimeters: Vec<f32> = scene.iter().map(|shape| shape.perimeter()).collect();
+        for (area, expected) in areas.iter().zip([12.0, PI, 6.0, 3.0]) {
+            assert!(close(*area, expected), "{} != {}", area, expected);
+        }
+        for (perimeter, expected) in perimeters.iter().zip([14.0, 2.0 * PI, 12.0, 8.0]) {
+            assert!(close(*perimeter, expected), "{} != {}", perimeter, expected);
+        }
+        assert!(close(total_area(&scene), 12.0 + PI + 6.0 + 3.0));
+        assert_eq!(total_area(&[]), 0.0);
+    }
+
+    // Winding either way gives the same answers
+    #[test]
+    fn polygon_winding_doesnt_matter() {
+        let clockwise = Polygon::new(vec![point(0.0, 0.0), point(0.0, 2.0), point(3.0, 2.0), point(3.0, 0.0)]).unwrap();
+        let mut anticlockwise = clockwise.vertices().to_vec();
+        anticlockwise.reverse();
+        let anticlockwise = Polygon::new(anticlockwise).unwrap();
+        assert_eq!(clockwise.area(), 6.0);
+        assert_eq!(anticlockwise.area(), 6.0);
+        assert_eq!(clockwise.perimeter(), anticlockwise.perimeter());
+    }
+
+    #[test]
+    fn bounds() {
+        let scene = scene();
+        assert_eq!(scene_bounds(&scene), Some(Rectangle::new(point(0.0, 0.0), point(11.0, 11.0))));
+        assert_eq!(scene_bounds(&[]), None);
+        let circle = Circle::new(point(1.0, 2.0), 3.0).unwrap();
+        assert_eq!(circle.bounding_box(), Rectangle::new(point(-2.0, -1.0), point(4.0, 5.0)));
+    }
+
+    #[test]
+    fn contains() {
+        let scene = scene();
+        let hits: Vec<bool> = scene.iter().map(|shape| shape.contains(point(1.5, 1.5))).collect();
+        assert_eq!(hits, vec![true, false, true, false]);
+        assert!(scene[1].contains(point(10.5, 10.5)));
+        assert!(scene[1].contains(point(11.0, 10.0))); // on the outline
+        assert!(!scene[1].contains(point(10.8, 10.8)));
+        assert!(scene[2].contains(point(2.0, 1.5))); // on the long edge
+        assert!(!scene[2].contains(point(3.0, 2.0)));
+        assert!(scene[3].contains(point(0.5, 1.5)));
+        assert!(scene[3].contains(point(1.0, 1.5))); // on the inner corner's edge
+    }
+
+    // Moving the whole scene moves every bounding box with it, and nothing
+    // changes size
+    #[test]
+    fn translating_moves_everything() {
+        let mut scene = scene();
+        let area = total_area(&scene);
+        for shape in &mut scene {
+            shape.translate(point(-1.0, 2.0));
+        }
+        assert_eq!(scene_bounds(&scene), Some(Rectangle::new(point(-1.0, 2.0), point(10.0, 13.0))));
+        assert!(close(total_area(&scene), area));
+        assert!(scene[3].contains(point(-0.5, 3.5)));
+    }
+
+    #[test]
+    fn circles_need_a_sensible_radius() {
+        let center = point(1.0, 1.0);
+        for radius in [-1.0, -0.001, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(Circle::new(center, radius), None, "radius {}", radius);
+        }
+        let dot = Circle::new(center, 0.0).unwrap();
+        assert_eq!(dot.area(), 0.0);
+        assert!(dot.contains(center));
+        assert_eq!((dot.center(), dot.radius()), (center, 0.0));
+        assert_eq!(Circle::new(center, 6.0).unwrap().to_string(), "Circle of radius 6");
+    }
+
+    // Polygons need at least three vertices
+    #[test]
+    fn polygons_need_three_vertices() {
+        assert_eq!(Polygon::new(vec![]), None);
+        assert_eq!(Polygon::new(vec![point(0.0, 0.0), point(1.0, 1.0)]), None);
+        assert!(Polygon::new(vec![point(0.0, 0.0), point(1.0, 1.0), point(1.0, 0.0)]).is_some());
+    }
+}
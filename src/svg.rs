@@ -0,0 +1,354 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::custom_types::Color;
+use crate::geometry::{Point, Rectangle};
+use crate::shapes::{Circle, Polygon};
+
+// Draw the geometry types into an SVG picture so they can be looked at
+// instead of read out of println!
+// SVG's y axis grows downward just like ours, so coordinates go in as they are
+
+// How to draw one shape
+// None means don't draw that part, so a shape with neither is invisible
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub stroke: Option<Color>,
+    pub fill: Option<Color>,
+    pub stroke_width: f32,
+}
+
+// Lines every spacing units, optionally with the coordinate written at the
+// top and left edges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    pub spacing: f32,
+    pub labels: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Svg {
+    // The part of the plane the picture shows
+    view: Rectangle,
+    grid: Option<Grid>,
+    elements: Vec<String>,
+}
+
+// Light grey so the shapes stand out against it
+// It's written before any of them, which is what keeps it underneath
+const GRID_COLOR: &str = "#dddddd";
+const LABEL_SIZE: f32 = 3.0;
+// More lines than this is a grey smear, not a grid, and a tiny spacing would
+// otherwise take forever to write out
+const MAX_GRID_LINES: f64 = 2000.0;
+
+impl Style {
+    pub fn stroke(color: Color) -> Style {
+        Style {
+            stroke: Some(color),
+            fill: None,
+            stroke_width: 1.0,
+        }
+    }
+
+    pub fn fill(color: Color) -> Style {
+        Style {
+            stroke: None,
+            fill: Some(color),
+            stroke_width: 1.0,
+        }
+    }
+
+    pub fn with_fill(self, color: Color) -> Style {
+        Style {
+            fill: Some(color),
+            ..self
+        }
+    }
+
+    pub fn with_stroke_width(self, stroke_width: f32) -> Style {
+        Style { stroke_width, ..self }
+    }
+
+    fn attributes(&self) -> String {
+        let paint = |color: Option<Color>| match color {
+            Some(color) => hex(color),
+            None => "none".to_string(),
+        };
+        let mut attributes = format!("fill=\"{}\" stroke=\"{}\"", paint(self.fill), paint(self.stroke));
+        if self.stroke.is_some() {
+            write!(attributes, " stroke-width=\"{}\"", self.stroke_width).unwrap();
+        }
+        attributes
+    }
+}
+
+impl Svg {
+    pub fn new(view: Rectangle) -> Svg {
+        Svg {
+            view,
+            grid: None,
+            elements: Vec::new(),
+        }
+    }
+
+    // None unless spacing is a positive, finite number that gives at most
+    // MAX_GRID_LINES lines across the view
+    // A grid of zero spacing would never finish drawing, and NaN or
+    // infinite spacing has nowhere to put the lines
+    pub fn with_grid(mut self, spacing: f32, labels: bool) -> Option<Svg> {
+        if !(spacing.is_finite() && spacing > 0.0) {
+            return None;
+        }
+        // In f64 so a huge count can't wrap around or saturate into
+        // something that looks small
+        let spacing_f64 = f64::from(spacing);
+        let lines = (f64::from(self.view.width()) / spacing_f64).floor()
+            + (f64::from(self.view.height()) / spacing_f64).floor()
+            + 2.0;
+        if lines.is_nan() || lines > MAX_GRID_LINES {
+            return None;
+        }
+        self.grid = Some(Grid { spacing, labels });
+        Some(self)
+    }
+
+    pub fn rectangle(&mut self, rectangle: &Rectangle, style: Style) -> &mut Svg {
+        self.elements.push(format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+            rectangle.left(),
+            rectangle.top(),
+            rectangle.width(),
+            rectangle.height(),
+            style.attributes()
+        ));
+        self
+    }
+
+    pub fn circle(&mut self, circle: &Circle, style: Style) -> &mut Svg {
+        self.elements.push(format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+            circle.center().x,
+            circle.center().y,
+            circle.radius(),
+            style.attributes()
+        ));
+        self
+    }
+
+    pub fn polygon(&mut self, polygon: &Polygon, style: Style) -> &mut Svg {
+        let points: Vec<String> = polygon
+            .vertices()
+            .iter()
+            .map(|vertex| format!("{},{}", vertex.x, vertex.y))
+            .collect();
+        self.elements
+            .push(format!("<polygon points=\"{}\" {}/>", points.join(" "), style.attributes()));
+        self
+    }
+
+    // Points have no size, so they're drawn as a dot of the given radius
+    // A radius no Circle can have draws nothing
+    pub fn point(&mut self, point: Point, radius: f32, color: Color) -> &mut Svg {
+        match Circle::new(point, radius) {
+            Some(dot) => self.circle(&dot, Style::fill(color)),
+            None => self,
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    fn write_grid(&self, f: &mut fmt::Formatter, grid: Grid) -> fmt::Result {
+        let view = &self.view;
+        // Start at the first multiple of spacing inside the view so the
+        // lines land on round numbers
+        let first_x = (view.left() / grid.spacing).ceil() as i64;
+        let last_x = (view.right() / grid.spacing).floor() as i64;
+        let first_y = (view.top() / grid.spacing).ceil() as i64;
+        let last_y = (view.bottom() / grid.spacing).floor() as i64;
+
+        writeln!(f, "  <g stroke=\"{}\" stroke-width=\"0.5\">", GRID_COLOR)?;
+        for step in first_x..=last_x {
+            let x = step as f32 * grid.spacing;
+            writeln!(f, "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", x, view.top(), x, view.bottom())?;
+        }
+        for step in first_y..=last_y {
+            let y = step as f32 * grid.spacing;
+            writeln!(f, "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>", view.left(), y, view.right(), y)?;
+        }
+        writeln!(f, "  </g>")?;
+
+        if grid.labels {
+            writeln!(f, "  <g font-family=\"sans-serif\" font-size=\"{}\" fill=\"{}\">", LABEL_SIZE, hex(Color::Black))?;
+            // x labels just under the top edge, y labels just right of the left edge
+            // Text hangs above its baseline, so the label for a line right
+            // on the top edge is pushed down to where the x labels are
+            for step in first_x..=last_x {
+                let x = step as f32 * grid.spacing;
+                writeln!(f, "    <text x=\"{}\" y=\"{}\">{}</text>", x, view.top() + LABEL_SIZE, x)?;
+            }
+            for step in first_y..=last_y {
+                let y = step as f32 * grid.spacing;
+                let baseline = y.max(view.top() + LABEL_SIZE);
+                writeln!(f, "    <text x=\"{}\" y=\"{}\">{}</text>", view.left(), baseline, y)?;
+            }
+            writeln!(f, "  </g>")?;
+        }
+        Ok(())
+    }
+}
+
+// A whole standalone SVG document
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let view = &self.view;
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+            view.left(),
+            view.top(),
+            view.width(),
+            view.height(),
+            view.width(),
+            view.height()
+        )?;
+        if let Some(grid) = self.grid {
+            self.write_grid(f, grid)?;
+        }
+        for element in &self.elements {
+            writeln!(f, "  {}", element)?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+// Color's discriminant is already the hex code, it just needs the #
+fn hex(color: Color) -> String {
+    format!("#{:06x}", color as u32)
+}
+
+// The picture the chapter draws, also used as the golden test
+fn scene() -> Svg {
+    let point = |x, y| Point::new(x, y);
+
+    let mut svg = Svg::new(Rectangle::new(point(0.0, 0.0), point(40.0, 30.0)))
+        .with_grid(10.0, true)
+        .unwrap();
+    svg.rectangle(
+        &Rectangle::new(point(5.0, 5.0), point(20.0, 15.0)),
+        Style::stroke(Color::Blue).with_fill(Color::White),
+    )
+    .circle(&Circle::new(point(30.0, 10.0), 5.0).unwrap(), Style::fill(Color::Red))
+    .polygon(
+        &Polygon::new(vec![point(10.0, 25.0), point(20.0, 20.0), point(30.0, 25.0)]).unwrap(),
+        Style::stroke(Color::Green).with_stroke_width(2.0),
+    )
+    .point(point(20.0, 15.0), 0.5, Color::Black);
+    svg
+}
+
+pub fn run() {
+    let svg = scene();
+    println!("{}", svg);
+
+    let path = std::env::temp_dir().join("rbe-scene.svg");
+    match svg.save(&path) {
+        Ok(()) => println!("Wrote {}", path.display()),
+        Err(error) => println!("Couldn't write {}: {}", path.display(), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compare against a picture that was checked by eye
+    // If the output changes on purpose, save the new one over
+    // tests/fixtures/scene.svg
+    #[test]
+    fn scene_matches_golden_file() {
+        assert_eq!(scene().to_string(), include_str!("../tests/fixtures/scene.svg"));
+    }
+
+    // A plain picture with no grid is just the shapes
+    #[test]
+    fn no_grid_is_just_the_shapes() {
+        let mut plain = Svg::new(Rectangle::new(Point::new(-5.0, -5.0), Point::new(5.0, 5.0)));
+        plain.point(Point::new(0.0, 0.0), 1.0, Color::Red);
+        assert_eq!(
+            plain.to_string(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-5 -5 10 10\" width=\"10\" height=\"10\">\n  \
+             <circle cx=\"0\" cy=\"0\" r=\"1\" fill=\"#ff0000\" stroke=\"none\"/>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn points_with_a_bad_radius_are_skipped() {
+        let view = Rectangle::new(Point::new(-5.0, -5.0), Point::new(5.0, 5.0));
+        let mut svg = Svg::new(view);
+        svg.point(Point::new(0.0, 0.0), -1.0, Color::Red).point(Point::new(0.0, 0.0), f32::NAN, Color::Red);
+        assert_eq!(svg, Svg::new(view));
+    }
+
+    #[test]
+    fn grid_spacing_must_be_positive_and_finite() {
+        let view = Rectangle::new(Point::new(0.0, 0.0), Point::new(10.0, 10.0));
+        for spacing in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(Svg::new(view).with_grid(spacing, false), None, "spacing {}", spacing);
+        }
+        assert!(Svg::new(view).with_grid(2.5, false).is_some());
+    }
+
+    #[test]
+    fn tiny_grid_spacing_is_refused() {
+        let view = Rectangle::new(Point::new(0.0, 0.0), Point::new(40.0, 30.0));
+        for spacing in [1e-6, 1e-30, f32::MIN_POSITIVE, 0.01] {
+            assert_eq!(Svg::new(view).with_grid(spacing, true), None, "spacing {}", spacing);
+        }
+        // 1000 lines across and 1000 down is as far as it goes
+        let square = Rectangle::new(Point::new(0.0, 0.0), Point::new(999.0, 999.0));
+        let svg = Svg::new(square).with_grid(1.0, false).unwrap();
+        assert_eq!(svg.to_string().matches("<line").count(), 2000);
+        assert_eq!(Svg::new(square).with_grid(0.99, false), None);
+        // An endless view can't have a grid at all
+        let endless = Rectangle::new(Point::new(0.0, 0.0), Point::new(f32::INFINITY, 10.0));
+        assert_eq!(Svg::new(endless).with_grid(1.0, false), None);
+    }
+
+    // A label's baseline is its bottom, so nothing may sit on the top edge
+    #[test]
+    fn labels_stay_inside_the_view() {
+        let svg = Svg::new(Rectangle::new(Point::new(0.0, 0.0), Point::new(40.0, 30.0)))
+            .with_grid(10.0, true)
+            .unwrap()
+            .to_string();
+        let baselines: Vec<f32> = svg
+            .lines()
+            .filter(|line| line.contains("<text"))
+            .map(|line| {
+                let start = line.find(" y=\"").unwrap() + 4;
+                let end = start + line[start..].find('"').unwrap();
+                line[start..end].parse().unwrap()
+            })
+            .collect();
+        assert_eq!(baselines.len(), 5 + 4);
+        assert!(baselines.iter().all(|&y| (LABEL_SIZE..=30.0).contains(&y)), "{:?}", baselines);
+    }
+
+    #[test]
+    fn save_writes_the_document() {
+        // Named after the process so parallel runs don't trip over each other
+        let path = std::env::temp_dir().join(format!("rbe-svg-test-{}.svg", std::process::id()));
+        let svg = scene();
+        svg.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), svg.to_string());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,426 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Mul;
+
+use crate::geometry::{Point, Rectangle, Screen, World};
+use crate::shapes::Polygon;
+
+// A 2D affine transform: any mix of translating, scaling, rotating and skewing
+// It's the 3x3 matrix
+//
+//     | a  c  e |
+//     | b  d  f |
+//     | 0  0  1 |
+//
+// so a point (x, y) ends up at (a*x + c*y + e, b*x + d*y + f)
+// Angles follow Point::angle, positive turns from +x towards +y, which is
+// clockwise on screen since y grows downward
+//
+// Like Point, a transform is tagged with coordinate spaces: it takes points
+// in Src to points in Dst, so an Affine2<World, Screen> only accepts world
+// points and only hands back screen ones
+// The building blocks below all stay in one space, and cast_spaces says
+// which two spaces the finished transform goes between
+// Plain Affine2 stays in World
+pub struct Affine2<Src = World, Dst = Src> {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+    pub spaces: PhantomData<(Src, Dst)>,
+}
+
+// Written out by hand for the same reason as Point's: a derive would want
+// the space markers to implement them too
+impl<Src, Dst> Clone for Affine2<Src, Dst> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Src, Dst> Copy for Affine2<Src, Dst> {}
+
+impl<Src, Dst> PartialEq for Affine2<Src, Dst> {
+    fn eq(&self, other: &Self) -> bool {
+        self.matrix() == other.matrix()
+    }
+}
+
+impl<Src, Dst> fmt::Debug for Affine2<Src, Dst> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Affine2")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("c", &self.c)
+            .field("d", &self.d)
+            .field("e", &self.e)
+            .field("f", &self.f)
+            .finish()
+    }
+}
+
+impl<S> Affine2<S, S> {
+    // Leaves every point where it is
+    pub const IDENTITY: Affine2<S, S> = Affine2::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    pub fn translate(offset: Point<f32, S>) -> Affine2<S, S> {
+        Affine2 {
+            e: offset.x,
+            f: offset.y,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    // Scale around the origin, negative factors mirror
+    pub fn scale(sx: f32, sy: f32) -> Affine2<S, S> {
+        Affine2 {
+            a: sx,
+            d: sy,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    // Rotate around the origin by angle radians
+    pub fn rotate(angle: f32) -> Affine2<S, S> {
+        let (sin, cos) = angle.sin_cos();
+        Affine2 {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    // Rotate around some other point than the origin
+    pub fn rotate_around(angle: f32, center: Point<f32, S>) -> Affine2<S, S> {
+        Affine2::translate(-center)
+            .then(Affine2::rotate(angle))
+            .then(Affine2::translate(center))
+    }
+
+    // Shear x by tan(x_angle) * y and y by tan(y_angle) * x
+    pub fn skew(x_angle: f32, y_angle: f32) -> Affine2<S, S> {
+        Affine2 {
+            b: y_angle.tan(),
+            c: x_angle.tan(),
+            ..Affine2::IDENTITY
+        }
+    }
+}
+
+impl<Src, Dst> Affine2<Src, Dst> {
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Affine2<Src, Dst> {
+        Affine2 {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            spaces: PhantomData,
+        }
+    }
+
+    // The same numbers, as a transform between two other spaces
+    // This is where a transform built out of the building blocks gets to
+    // say what it's for, like Affine2<World, Screen> for drawing
+    pub fn cast_spaces<NewSrc, NewDst>(self) -> Affine2<NewSrc, NewDst> {
+        Affine2::new(self.a, self.b, self.c, self.d, self.e, self.f)
+    }
+
+    // Do self first and then next
+    // This is the same as next * self, the matrix product goes the other way
+    pub fn then<Next>(self, next: Affine2<Dst, Next>) -> Affine2<Src, Next> {
+        next * self
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    // The transform that undoes this one, going back the other way
+    // None if this one squashes everything onto a line or a point, since
+    // there is no way to get the lost dimension back
+    // A determinant so close to zero that it's subnormal counts as squashed
+    // too, dividing by it would only give infinities, and so does one that's
+    // NaN or infinite
+    pub fn inverse(&self) -> Option<Affine2<Dst, Src>> {
+        let determinant = self.determinant();
+        if !determinant.is_normal() {
+            return None;
+        }
+
+        let a = self.d / determinant;
+        let b = -self.b / determinant;
+        let c = -self.c / determinant;
+        let d = self.a / determinant;
+        Some(Affine2::new(a, b, c, d, -(a * self.e + c * self.f), -(b * self.e + d * self.f)))
+    }
+
+    pub fn apply(&self, point: Point<f32, Src>) -> Point<f32, Dst> {
+        Point::new(
+            self.a * point.x + self.c * point.y + self.e,
+            self.b * point.x + self.d * point.y + self.f,
+        )
+    }
+
+    // Directions and sizes don't care where they are, so leave out the translation
+    pub fn apply_vector(&self, vector: Point<f32, Src>) -> Point<f32, Dst> {
+        Point::new(
+            self.a * vector.x + self.c * vector.y,
+            self.b * vector.x + self.d * vector.y,
+        )
+    }
+
+    // The axis-aligned box around the transformed rectangle
+    pub fn rectangle_bounds(&self, rectangle: &Rectangle<f32, Src>) -> Rectangle<f32, Dst> {
+        Rectangle::bounding_box(rectangle.corners().map(|corner| self.apply(corner))).unwrap()
+    }
+
+    pub fn approx_eq(&self, other: &Affine2<Src, Dst>, tolerance: f32) -> bool {
+        let (ours, theirs) = (self.matrix(), other.matrix());
+        ours.iter()
+            .zip(theirs)
+            .all(|(ours, theirs)| (ours - theirs).abs() <= tolerance)
+    }
+
+    fn matrix(&self) -> [f32; 6] {
+        [self.a, self.b, self.c, self.d, self.e, self.f]
+    }
+}
+
+// Polygons only live in World, so these only work for transforms that end
+// up there
+impl<Src> Affine2<Src, World> {
+    // A rotated or skewed rectangle isn't axis-aligned anymore, so it comes
+    // back as a polygon of its four transformed corners
+    pub fn apply_to_rectangle(&self, rectangle: &Rectangle<f32, Src>) -> Polygon {
+        let corners = rectangle.corners().map(|corner| self.apply(corner));
+        // Four corners is always enough for a polygon
+        Polygon::new(corners.to_vec()).unwrap()
+    }
+}
+
+impl Affine2 {
+    pub fn apply_to_polygon(&self, polygon: &Polygon) -> Polygon {
+        let vertices = polygon.vertices().iter().map(|&vertex| self.apply(vertex)).collect();
+        // Same number of vertices as before, so still enough of them
+        Polygon::new(vertices).unwrap()
+    }
+}
+
+impl<S> Default for Affine2<S, S> {
+    fn default() -> Self {
+        Affine2::IDENTITY
+    }
+}
+
+// Matrix product: (self * other) applies other first and then self, so
+// other has to end in the space self starts from
+impl<Src, Mid, Dst> Mul<Affine2<Src, Mid>> for Affine2<Mid, Dst> {
+    type Output = Affine2<Src, Dst>;
+
+    fn mul(self, other: Affine2<Src, Mid>) -> Affine2<Src, Dst> {
+        Affine2::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+}
+
+pub fn run() {
+    use std::f32::consts::FRAC_PI_2;
+
+    let point = |x, y| Point::<f32, World>::new(x, y);
+
+    // Composition happens in the order you read it
+    let scale_then_move = Affine2::scale(2.0, 2.0).then(Affine2::translate(point(1.0, 0.0)));
+    println!("Scale then move takes (1, 1) to {:?}", scale_then_move.apply(point(1.0, 1.0)));
+
+    // Rotating a rectangle gives a polygon, or its axis-aligned box
+    let rectangle = Rectangle::new(point(0.0, 0.0), point(4.0, 2.0));
+    let quarter_turn = Affine2::rotate_around(FRAC_PI_2, rectangle.center());
+    println!("Rotated rectangle: {:?}", quarter_turn.apply_to_rectangle(&rectangle).vertices());
+    println!("Its bounding box: {:?}", quarter_turn.rectangle_bounds(&rectangle));
+
+    // Map a screen click back into model space
+    // The model is drawn twice as big, a bit rotated and panned to (100, 50)
+    // pixels, so the finished transform goes from World to Screen, and its
+    // inverse back again
+    // Handing model_to_screen a screen point, or comparing its result with
+    // a world point, doesn't compile
+    let model_to_screen: Affine2<World, Screen> = Affine2::scale(2.0, 2.0)
+        .then(Affine2::rotate(0.3))
+        .then(Affine2::translate(point(100.0, 50.0)))
+        .cast_spaces();
+    let screen_to_model: Affine2<Screen, World> = model_to_screen.inverse().unwrap();
+    let model_point = point(7.0, -3.0);
+    let click: Point<f32, Screen> = model_to_screen.apply(model_point);
+    println!("Model {:?} is drawn at {:?}", model_point, click);
+    println!("and clicking there lands back on {:?}", screen_to_model.apply(click));
+
+    // Squashing everything flat can't be undone
+    println!("Inverse of a flattening scale: {:?}", Affine2::<World>::scale(1.0, 0.0).inverse());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point::new(x, y)
+    }
+
+    fn close<S>(a: Point<f32, S>, b: Point<f32, S>) -> bool {
+        a.approx_eq(b, 1e-5)
+    }
+
+    fn model_to_screen() -> Affine2<World, Screen> {
+        Affine2::scale(2.0, 2.0)
+            .then(Affine2::rotate(0.3))
+            .then(Affine2::translate(point(100.0, 50.0)))
+            .cast_spaces()
+    }
+
+    // Whether A: Add<B>, or A: Mul<B>, worked out at compile time
+    // The inherent const only exists when the bound holds, and wins over the
+    // trait's when it does, so a mismatch quietly falls back to false
+    // This is how the tests below check that mixing spaces doesn't compile
+    // without having to compile something that fails
+    struct Probe<A, B>(PhantomData<(A, B)>);
+
+    trait Fallback {
+        const ADDS: bool = false;
+        const MULTIPLIES: bool = false;
+    }
+
+    impl<A, B> Fallback for Probe<A, B> {}
+
+    impl<A: std::ops::Add<B>, B> Probe<A, B> {
+        const ADDS: bool = true;
+    }
+
+    struct MulProbe<A, B>(PhantomData<(A, B)>);
+
+    impl<A, B> Fallback for MulProbe<A, B> {}
+
+    impl<A: Mul<B>, B> MulProbe<A, B> {
+        const MULTIPLIES: bool = true;
+    }
+
+    #[test]
+    fn building_blocks() {
+        assert_eq!(Affine2::translate(point(3.0, -1.0)).apply(point(1.0, 1.0)), point(4.0, 0.0));
+        assert_eq!(Affine2::scale(2.0, 3.0).apply(point(1.0, 1.0)), point(2.0, 3.0));
+        // A quarter turn takes +x to +y
+        assert!(close(Affine2::rotate(FRAC_PI_2).apply(point(1.0, 0.0)), point(0.0, 1.0)));
+        assert!(close(Affine2::rotate_around(FRAC_PI_2, point(1.0, 1.0)).apply(point(2.0, 1.0)), point(1.0, 2.0)));
+        assert!(close(Affine2::skew(FRAC_PI_2 / 2.0, 0.0).apply(point(0.0, 2.0)), point(2.0, 2.0)));
+        assert_eq!(Affine2::<World>::default(), Affine2::IDENTITY);
+        assert_eq!(Affine2::IDENTITY.apply(point(3.0, 4.0)), point(3.0, 4.0));
+    }
+
+    // Vectors don't get translated
+    #[test]
+    fn vectors_ignore_translation() {
+        let moved_and_scaled = Affine2::translate(point(5.0, 5.0)).then(Affine2::scale(2.0, 1.0));
+        assert_eq!(moved_and_scaled.apply_vector(point(1.0, 1.0)), point(2.0, 1.0));
+    }
+
+    // Composition happens in the order you read it
+    #[test]
+    fn composition_order() {
+        let scale_then_move = Affine2::scale(2.0, 2.0).then(Affine2::translate(point(1.0, 0.0)));
+        let move_then_scale = Affine2::translate(point(1.0, 0.0)).then(Affine2::scale(2.0, 2.0));
+        assert_eq!(scale_then_move.apply(point(1.0, 1.0)), point(3.0, 2.0));
+        assert_eq!(move_then_scale.apply(point(1.0, 1.0)), point(4.0, 2.0));
+        assert_eq!(Affine2::translate(point(1.0, 0.0)) * Affine2::scale(2.0, 2.0), scale_then_move);
+    }
+
+    #[test]
+    fn rectangles_and_polygons() {
+        let rectangle = Rectangle::new(point(0.0, 0.0), point(4.0, 2.0));
+        let quarter_turn = Affine2::rotate_around(FRAC_PI_2, rectangle.center());
+        let rotated = quarter_turn.apply_to_rectangle(&rectangle);
+        assert!(close(rotated.vertices()[0], point(3.0, -1.0)));
+        let bounds = quarter_turn.rectangle_bounds(&rectangle);
+        assert!(close(bounds.top_left(), point(1.0, -1.0)));
+        assert!(close(bounds.bottom_right(), point(3.0, 3.0)));
+
+        // An eighth of a turn gives a diamond, whose box is bigger than the square
+        let eighth_turn = Affine2::rotate(FRAC_PI_2 / 2.0);
+        let square = Rectangle::new(point(-1.0, -1.0), point(1.0, 1.0));
+        let diamond_bounds = eighth_turn.rectangle_bounds(&square);
+        assert!((diamond_bounds.width() - 2.0 * 2f32.sqrt()).abs() < 1e-5);
+
+        let triangle = Polygon::new(vec![point(0.0, 0.0), point(1.0, 0.0), point(0.0, 1.0)]).unwrap();
+        let moved = Affine2::translate(point(10.0, 10.0)).apply_to_polygon(&triangle);
+        assert_eq!(moved.vertices()[2], point(10.0, 11.0));
+    }
+
+    // World to Screen and back lands where it started, and both
+    // compositions are the identity of the space they start and end in
+    #[test]
+    fn spaces_flow_through_then_and_inverse() {
+        let model_to_screen = model_to_screen();
+        let screen_to_model: Affine2<Screen, World> = model_to_screen.inverse().unwrap();
+        let model_point = point(7.0, -3.0);
+        let click: Point<f32, Screen> = model_to_screen.apply(model_point);
+        assert!(close(screen_to_model.apply(click), model_point));
+
+        let screen_round_trip: Affine2<Screen, Screen> = model_to_screen * screen_to_model;
+        let world_round_trip: Affine2<World, World> = screen_to_model * model_to_screen;
+        assert!(screen_round_trip.approx_eq(&Affine2::IDENTITY, 1e-5));
+        assert!(world_round_trip.approx_eq(&Affine2::IDENTITY, 1e-5));
+
+        // A screen space step after the World to Screen one keeps the result in Screen
+        let nudge: Affine2<Screen> = Affine2::translate(Point::new(0.5, 0.5));
+        let nudged: Affine2<World, Screen> = model_to_screen.then(nudge);
+        assert!(close(nudged.apply(model_point), click + Point::new(0.5, 0.5)));
+        assert_eq!(nudged.inverse().unwrap().apply(nudged.apply(point(0.0, 0.0))).x.round(), 0.0);
+    }
+
+    #[test]
+    fn spaces_dont_mix() {
+        // Points in the same space add, points in different spaces don't
+        const { assert!(Probe::<Point<f32, World>, Point<f32, World>>::ADDS) };
+        const { assert!(!Probe::<Point<f32, Screen>, Point<f32, World>>::ADDS) };
+        const { assert!(!Probe::<Point<f32, World>, Point<f32, Screen>>::ADDS) };
+        // Transforms only compose when one ends where the other starts
+        const { assert!(MulProbe::<Affine2<Screen, World>, Affine2<World, Screen>>::MULTIPLIES) };
+        const { assert!(!MulProbe::<Affine2<World, Screen>, Affine2<World, Screen>>::MULTIPLIES) };
+        const { assert!(!MulProbe::<Affine2<World, World>, Affine2<World, Screen>>::MULTIPLIES) };
+    }
+
+    #[test]
+    fn flat_transforms_have_no_inverse() {
+        assert_eq!(Affine2::<World>::scale(1.0, 0.0).inverse(), None);
+        assert_eq!(Affine2::<World>::scale(0.0, 0.0).inverse(), None);
+        // Both rows point the same way
+        assert_eq!(Affine2::<World>::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0).inverse(), None);
+    }
+
+    // These used to give back an inverse full of NaNs or infinities
+    #[test]
+    fn broken_determinants_have_no_inverse() {
+        assert_eq!(Affine2::<World>::scale(f32::NAN, 1.0).inverse(), None);
+        assert_eq!(Affine2::<World>::scale(f32::INFINITY, 1.0).inverse(), None);
+        assert_eq!(Affine2::<World>::scale(1e-20, 1e-20).inverse(), None);
+        assert_eq!(Affine2::<World>::scale(f32::MIN_POSITIVE / 2.0, 1.0).inverse(), None);
+        // Small but normal is still fine
+        let tiny = Affine2::<World>::scale(1e-10, 1e-10);
+        let inverse = tiny.inverse().unwrap();
+        assert!(inverse.a.is_finite());
+        assert!(close(inverse.apply(tiny.apply(point(3.0, 4.0))), point(3.0, 4.0)));
+    }
+}
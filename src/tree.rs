@@ -0,0 +1,849 @@
+#![allow(dead_code)] // Allow unused code
+
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+// A binary search tree, built the same way as List in custom_types:
+// an enum where one variant holds the data plus boxed links to more of the
+// enum, and the other marks where it ends
+// Every key in a node's left subtree is smaller than its key, and every key
+// in its right subtree is bigger
+
+pub enum Tree<K, V> {
+    // Node: A key, its value and the two subtrees hanging off it
+    Node(Box<Node<K, V>>),
+    // Leaf: An empty subtree, the tree version of Nil
+    Leaf,
+}
+
+pub struct Node<K, V> {
+    key: K,
+    value: V,
+    left: Subtree<K, V>,
+    right: Subtree<K, V>,
+    // Number of nodes on the longest path down from here, counting this one
+    // Kept up to date in both modes so height() is free
+    height: usize,
+}
+
+// A child link of a Node
+// It only exists to drop its tree without recursing: a plain tree fed sorted
+// keys is as deep as it is long, and the default drop would go all the way
+// down, the same problem List's Link has in custom_types
+// Putting the Drop here rather than on Tree or Node means both of those can
+// still be taken apart by value
+struct Subtree<K, V>(Tree<K, V>);
+
+// Which child the way down a Path went through
+#[derive(Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+}
+
+// The nodes on the way down to where an insert or remove happens, each one
+// taken out of its parent
+// Putting them back from the bottom up fixes heights, and in AVL mode the
+// balance, just like unwinding a recursion would, but it's a loop so a
+// long plain tree can't overflow the stack
+struct Path<K, V> {
+    steps: Vec<(Box<Node<K, V>>, Side)>,
+}
+
+// Whether the tree rebalances itself after every insert and remove
+// Plain trees are just as fast for random keys, but keys that arrive
+// already sorted turn them into a very long List
+// AVL trees rotate nodes around so the two sides of every node never
+// differ in height by more than one, keeping lookups logarithmic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balance {
+    Plain,
+    Avl,
+}
+
+impl<K, V> Tree<K, V> {
+    fn node(key: K, value: V) -> Tree<K, V> {
+        Tree::Node(Box::new(Node {
+            key,
+            value,
+            left: Subtree(Tree::Leaf),
+            right: Subtree(Tree::Leaf),
+            height: 1,
+        }))
+    }
+
+    // Number of levels in the tree, a Leaf has height 0
+    pub fn height(&self) -> usize {
+        match self {
+            Tree::Node(node) => node.height,
+            Tree::Leaf => 0,
+        }
+    }
+
+    // True if no node has one side more than one level taller than the other
+    pub fn is_balanced(&self) -> bool {
+        self.nodes().all(|node| node.balance_factor().abs() <= 1)
+    }
+
+    // Keys in sorted order
+    pub fn in_order(&self) -> InOrder<'_, K, V> {
+        let mut iter = InOrder { stack: Vec::new() };
+        iter.push_left_spine(self);
+        iter
+    }
+
+    // Every node before its subtrees, left side first
+    pub fn pre_order(&self) -> PreOrder<'_, K, V> {
+        let mut stack = Vec::new();
+        if let Tree::Node(node) = self {
+            stack.push(&**node);
+        }
+        PreOrder { stack }
+    }
+
+    // Every node after both of its subtrees, left side first
+    pub fn post_order(&self) -> PostOrder<'_, K, V> {
+        let mut stack = Vec::new();
+        if let Tree::Node(node) = self {
+            stack.push((&**node, false));
+        }
+        PostOrder { stack }
+    }
+
+    // Walks every node, in no particular order we care about
+    fn nodes(&self) -> impl Iterator<Item = &Node<K, V>> {
+        let mut stack: Vec<&Node<K, V>> = Vec::new();
+        if let Tree::Node(node) = self {
+            stack.push(node);
+        }
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            for child in [&*node.left, &*node.right] {
+                if let Tree::Node(child) = child {
+                    stack.push(child);
+                }
+            }
+            Some(node)
+        })
+    }
+
+    fn balance_factor(&self) -> isize {
+        match self {
+            Tree::Node(node) => node.balance_factor(),
+            Tree::Leaf => 0,
+        }
+    }
+
+    // Recompute the height of this node from its children and, in AVL mode,
+    // rotate it back into balance
+    // Called on every node on the way back up from an insert or remove
+    fn fix(&mut self, balance: Balance) {
+        if let Tree::Node(node) = self {
+            node.update_height();
+        }
+        if balance == Balance::Plain {
+            return;
+        }
+
+        let factor = self.balance_factor();
+        if factor > 1 {
+            // Left side is too tall
+            // If its extra height is on the inside, straighten it out first
+            if let Tree::Node(node) = self {
+                if node.left.balance_factor() < 0 {
+                    node.left.rotate_left();
+                }
+            }
+            self.rotate_right();
+        } else if factor < -1 {
+            // Same thing mirrored
+            if let Tree::Node(node) = self {
+                if node.right.balance_factor() > 0 {
+                    node.right.rotate_right();
+                }
+            }
+            self.rotate_left();
+        }
+    }
+
+    //      root            pivot
+    //     /    \          /     \
+    //   pivot   c  =>    a      root
+    //   /   \                  /    \
+    //  a     b                b      c
+    fn rotate_right(&mut self) {
+        let Tree::Node(mut root) = mem::replace(self, Tree::Leaf) else {
+            return;
+        };
+        let Tree::Node(mut pivot) = root.left.take() else {
+            *self = Tree::Node(root);
+            return;
+        };
+        *root.left = pivot.right.take();
+        root.update_height();
+        *pivot.right = Tree::Node(root);
+        pivot.update_height();
+        *self = Tree::Node(pivot);
+    }
+
+    // The mirror image of rotate_right
+    fn rotate_left(&mut self) {
+        let Tree::Node(mut root) = mem::replace(self, Tree::Leaf) else {
+            return;
+        };
+        let Tree::Node(mut pivot) = root.right.take() else {
+            *self = Tree::Node(root);
+            return;
+        };
+        *root.right = pivot.left.take();
+        root.update_height();
+        *pivot.left = Tree::Node(root);
+        pivot.update_height();
+        *self = Tree::Node(pivot);
+    }
+
+    // Take the smallest entry out of this subtree
+    fn remove_min(&mut self, balance: Balance) -> Option<(K, V)> {
+        let mut path = Path::new();
+        let mut tree = mem::replace(self, Tree::Leaf);
+        let min = loop {
+            let Tree::Node(node) = tree else {
+                break None;
+            };
+            if let Tree::Node(_) = *node.left {
+                tree = path.descend(node, Side::Left);
+                continue;
+            }
+            // No left child, so this node is the smallest one
+            // Its right subtree moves up to take its place
+            let Node { key, value, mut right, .. } = *node;
+            tree = right.take();
+            break Some((key, value));
+        };
+        *self = path.rebuild(tree, balance);
+        min
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    // Look up the value for key
+    // This only walks down one path so it's a loop instead of recursion
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut tree = self;
+        while let Tree::Node(node) = tree {
+            tree = match key.cmp(&node.key) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let mut tree = self;
+        while let Tree::Node(node) = tree {
+            tree = match key.cmp(&node.key) {
+                Ordering::Less => &mut node.left,
+                Ordering::Greater => &mut node.right,
+                Ordering::Equal => return Some(&mut node.value),
+            };
+        }
+        None
+    }
+
+    // Insert key, returning the value it replaced if it was already there
+    fn insert(&mut self, key: K, value: V, balance: Balance) -> Option<V> {
+        let mut path = Path::new();
+        let mut tree = mem::replace(self, Tree::Leaf);
+        let old = loop {
+            let Tree::Node(mut node) = tree else {
+                tree = Tree::node(key, value);
+                break None;
+            };
+            tree = match key.cmp(&node.key) {
+                Ordering::Less => path.descend(node, Side::Left),
+                Ordering::Greater => path.descend(node, Side::Right),
+                // Same key, the shape of the tree doesn't change
+                Ordering::Equal => {
+                    let old = mem::replace(&mut node.value, value);
+                    tree = Tree::Node(node);
+                    break Some(old);
+                },
+            };
+        };
+        *self = path.rebuild(tree, balance);
+        old
+    }
+
+    // Remove key, returning its value if it was there
+    fn remove(&mut self, key: &K, balance: Balance) -> Option<V> {
+        let mut path = Path::new();
+        let mut tree = mem::replace(self, Tree::Leaf);
+        let removed = loop {
+            let Tree::Node(node) = tree else {
+                break None;
+            };
+            tree = match key.cmp(&node.key) {
+                Ordering::Less => path.descend(node, Side::Left),
+                Ordering::Greater => path.descend(node, Side::Right),
+                Ordering::Equal => {
+                    let Node { value, mut left, mut right, .. } = *node;
+                    tree = match (left.take(), right.take()) {
+                        // With one child or none, the child just moves up
+                        (left, Tree::Leaf) => left,
+                        (Tree::Leaf, right) => right,
+                        // With two, the smallest key on the right takes its place
+                        (left, mut right) => {
+                            let (key, value) = right.remove_min(balance).unwrap();
+                            Tree::Node(Box::new(Node {
+                                key,
+                                value,
+                                left: Subtree(left),
+                                right: Subtree(right),
+                                height: 0,
+                            }))
+                        },
+                    };
+                    tree.fix(balance);
+                    break Some(value);
+                },
+            };
+        };
+        *self = path.rebuild(tree, balance);
+        removed
+    }
+}
+
+impl<K: fmt::Display, V: fmt::Display> Tree<K, V> {
+    // Return representation of the tree as a (heap allocated) string
+    // Each node is written as (left, key: value, right) and empty subtrees
+    // as Leaf, the same way List ends with Nil
+    pub fn stringify(&self) -> String {
+        // What's still left to write, the top of the stack comes next
+        // Nesting goes on the stack instead of the call stack, so a tree as
+        // deep as it is long still works
+        enum Step<'a, K, V> {
+            Tree(&'a Tree<K, V>),
+            Entry(&'a Node<K, V>),
+            Close,
+        }
+
+        let mut out = String::new();
+        let mut stack = vec![Step::Tree(self)];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Tree(Tree::Node(node)) => {
+                    out.push('(');
+                    stack.push(Step::Close);
+                    stack.push(Step::Tree(&node.right));
+                    stack.push(Step::Entry(node));
+                    stack.push(Step::Tree(&node.left));
+                },
+                Step::Tree(Tree::Leaf) => out.push_str("Leaf"),
+                Step::Entry(node) => {
+                    let _ = write!(out, ", {}: {}, ", node.key, node.value);
+                },
+                Step::Close => out.push(')'),
+            }
+        }
+        out
+    }
+}
+
+impl<K, V> Subtree<K, V> {
+    // Leave a Leaf behind and hand back what was there
+    fn take(&mut self) -> Tree<K, V> {
+        mem::replace(&mut self.0, Tree::Leaf)
+    }
+}
+
+impl<K, V> Deref for Subtree<K, V> {
+    type Target = Tree<K, V>;
+
+    fn deref(&self) -> &Tree<K, V> {
+        &self.0
+    }
+}
+
+impl<K, V> DerefMut for Subtree<K, V> {
+    fn deref_mut(&mut self) -> &mut Tree<K, V> {
+        &mut self.0
+    }
+}
+
+// Taking the children off each node before it's dropped keeps it a loop
+// Every node's children are Subtrees too, so most drops are of a Leaf or a
+// node with nothing under it, and those return before allocating a stack
+impl<K, V> Drop for Subtree<K, V> {
+    fn drop(&mut self) {
+        let Tree::Node(node) = &self.0 else {
+            return;
+        };
+        if let (Tree::Leaf, Tree::Leaf) = (&*node.left, &*node.right) {
+            return;
+        }
+        let mut stack = vec![self.take()];
+        while let Some(tree) = stack.pop() {
+            if let Tree::Node(mut node) = tree {
+                stack.push(node.left.take());
+                stack.push(node.right.take());
+            }
+        }
+    }
+}
+
+impl<K, V> Path<K, V> {
+    fn new() -> Path<K, V> {
+        Path { steps: Vec::new() }
+    }
+
+    // Go down to one of node's children, taking it out so the caller owns it
+    fn descend(&mut self, mut node: Box<Node<K, V>>, side: Side) -> Tree<K, V> {
+        let child = match side {
+            Side::Left => node.left.take(),
+            Side::Right => node.right.take(),
+        };
+        self.steps.push((node, side));
+        child
+    }
+
+    // Hang tree back where the way down ended and fix every node above it
+    fn rebuild(self, mut tree: Tree<K, V>, balance: Balance) -> Tree<K, V> {
+        for (mut node, side) in self.steps.into_iter().rev() {
+            match side {
+                Side::Left => *node.left = tree,
+                Side::Right => *node.right = tree,
+            }
+            tree = Tree::Node(node);
+            tree.fix(balance);
+        }
+        tree
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn update_height(&mut self) {
+        self.height = 1 + self.left.height().max(self.right.height());
+    }
+
+    // How much taller the left side is than the right
+    fn balance_factor(&self) -> isize {
+        self.left.height() as isize - self.right.height() as isize
+    }
+}
+
+// In-order iterator: go as far left as possible, yield, then do the same
+// starting from the right child
+pub struct InOrder<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> InOrder<'a, K, V> {
+    fn push_left_spine(&mut self, mut tree: &'a Tree<K, V>) {
+        while let Tree::Node(node) = tree {
+            self.stack.push(node);
+            tree = &node.left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for InOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left_spine(&node.right);
+        Some((&node.key, &node.value))
+    }
+}
+
+pub struct PreOrder<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for PreOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // Right goes on first so left comes off first
+        if let Tree::Node(right) = &*node.right {
+            self.stack.push(right);
+        }
+        if let Tree::Node(left) = &*node.left {
+            self.stack.push(left);
+        }
+        Some((&node.key, &node.value))
+    }
+}
+
+// The bool marks whether a node's children have already been pushed
+pub struct PostOrder<'a, K, V> {
+    stack: Vec<(&'a Node<K, V>, bool)>,
+}
+
+impl<'a, K, V> Iterator for PostOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, children_done) = self.stack.pop()?;
+            if children_done {
+                return Some((&node.key, &node.value));
+            }
+            self.stack.push((node, true));
+            if let Tree::Node(right) = &*node.right {
+                self.stack.push((right, false));
+            }
+            if let Tree::Node(left) = &*node.left {
+                self.stack.push((left, false));
+            }
+        }
+    }
+}
+
+// A Tree plus the mode it was created in, so every insert and remove
+// keeps balancing the same way
+pub struct SearchTree<K, V> {
+    root: Tree<K, V>,
+    balance: Balance,
+    len: usize,
+}
+
+impl<K: Ord, V> SearchTree<K, V> {
+    // A tree that never rebalances
+    pub fn new() -> SearchTree<K, V> {
+        SearchTree::with_balance(Balance::Plain)
+    }
+
+    // A self-balancing tree
+    pub fn avl() -> SearchTree<K, V> {
+        SearchTree::with_balance(Balance::Avl)
+    }
+
+    pub fn with_balance(balance: Balance) -> SearchTree<K, V> {
+        SearchTree {
+            root: Tree::Leaf,
+            balance,
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.root.insert(key, value, self.balance);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let removed = self.root.remove(key, self.balance);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.root.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.root.get_mut(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, V> SearchTree<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn balance(&self) -> Balance {
+        self.balance
+    }
+
+    pub fn root(&self) -> &Tree<K, V> {
+        &self.root
+    }
+
+    pub fn height(&self) -> usize {
+        self.root.height()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.root.is_balanced()
+    }
+
+    // Entries in key order
+    pub fn iter(&self) -> InOrder<'_, K, V> {
+        self.root.in_order()
+    }
+}
+
+impl<K: Ord, V> Default for SearchTree<K, V> {
+    fn default() -> Self {
+        SearchTree::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SearchTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = SearchTree::avl();
+        for (key, value) in iter {
+            tree.insert(key, value);
+        }
+        tree
+    }
+}
+
+impl<K: fmt::Display, V: fmt::Display> fmt::Display for SearchTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.root.stringify())
+    }
+}
+
+pub fn run() {
+    let mut tree = SearchTree::new();
+    for (key, value) in [(2, "two"), (1, "one"), (3, "three")] {
+        tree.insert(key, value);
+    }
+    println!("{}", tree);
+    println!("1 is {:?} and 4 is {:?}", tree.get(&1), tree.get(&4));
+
+    // The three ways to walk a tree
+    let keys = |iter: &mut dyn Iterator<Item = (&i32, &&str)>| iter.map(|(k, _)| *k).collect::<Vec<_>>();
+    println!("in order: {:?}", keys(&mut tree.root().in_order()));
+    println!("pre order: {:?}", keys(&mut tree.root().pre_order()));
+    println!("post order: {:?}", keys(&mut tree.root().post_order()));
+
+    tree.remove(&2);
+    println!("After removing 2: {}", tree);
+
+    // Feed both kinds of tree keys in sorted order
+    // The plain tree turns into a linked list, the AVL tree stays within
+    // 1.44 * log2(n)
+    let mut plain = SearchTree::new();
+    let mut avl = SearchTree::avl();
+    for key in 0..1_000 {
+        plain.insert(key, key * 10);
+        avl.insert(key, key * 10);
+    }
+    println!("1000 sorted keys: plain height {}, AVL height {}", plain.height(), avl.height());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sorted keys make a plain tree one long line, as deep as this
+    const DEEP: i32 = 30_000;
+
+    // What inserting 0..DEEP into a plain tree gives, built bottom up since
+    // the inserts themselves would take quadratic time
+    fn deep_plain_tree() -> SearchTree<i32, i32> {
+        let mut root = Tree::Leaf;
+        for key in (0..DEEP).rev() {
+            let mut node = Tree::node(key, key);
+            if let Tree::Node(node) = &mut node {
+                node.height += root.height();
+                *node.right = root;
+            }
+            root = node;
+        }
+        SearchTree {
+            root,
+            balance: Balance::Plain,
+            len: DEEP as usize,
+        }
+    }
+
+    #[test]
+    fn deep_plain_tree_inserts_and_removes() {
+        let mut tree = deep_plain_tree();
+        assert_eq!(tree.height(), DEEP as usize);
+        assert_eq!(tree.insert(DEEP - 1, 0), Some(DEEP - 1));
+        assert_eq!(tree.remove(&(DEEP - 1)), Some(0));
+        assert_eq!(tree.remove(&0), Some(0));
+        assert_eq!(tree.height(), DEEP as usize - 2);
+        assert_eq!(tree.len(), DEEP as usize - 2);
+        assert!(tree.iter().map(|(k, _)| *k).eq(1..DEEP - 1));
+    }
+
+    #[test]
+    fn deep_plain_tree_displays() {
+        let tree = deep_plain_tree();
+        let text = tree.to_string();
+        assert!(text.starts_with("(Leaf, 0: 0, (Leaf, 1: 1, "));
+        assert!(text.ends_with(&")".repeat(DEEP as usize)));
+    }
+
+    #[test]
+    fn deep_bare_tree_drops() {
+        let mut tree = deep_plain_tree();
+        let root = mem::replace(&mut tree.root, Tree::Leaf);
+        drop(root);
+    }
+
+    // Every stored height is right, and in AVL mode no node leans by more
+    // than one level
+    fn assert_valid<K: Ord, V>(tree: &SearchTree<K, V>) {
+        for node in tree.root().nodes() {
+            assert_eq!(node.height, 1 + node.left.height().max(node.right.height()));
+            if tree.balance() == Balance::Avl {
+                assert!(node.balance_factor().abs() <= 1);
+            }
+        }
+        assert!(tree.iter().zip(tree.iter().skip(1)).all(|((a, _), (b, _))| a < b));
+        assert_eq!(tree.iter().count(), tree.len());
+    }
+
+    // The most levels an AVL tree of n nodes can have, 1.44 * log2(n + 2)
+    fn avl_height_bound(n: usize) -> usize {
+        (1.4405 * ((n + 2) as f64).log2() - 0.3277).floor() as usize
+    }
+
+    fn keys<'a>(iter: impl Iterator<Item = (&'a i32, &'a &'static str)>) -> Vec<i32> {
+        iter.map(|(k, _)| *k).collect()
+    }
+
+    fn small_tree() -> SearchTree<i32, &'static str> {
+        let mut tree = SearchTree::new();
+        for (key, value) in [(2, "two"), (1, "one"), (3, "three")] {
+            tree.insert(key, value);
+        }
+        tree
+    }
+
+    #[test]
+    fn lookups_and_replacing() {
+        let mut tree = small_tree();
+        assert_eq!(tree.root().stringify(), "((Leaf, 1: one, Leaf), 2: two, (Leaf, 3: three, Leaf))");
+        assert_eq!(tree.get(&1), Some(&"one"));
+        assert_eq!(tree.get(&4), None);
+        assert_eq!(tree.insert(3, "THREE"), Some("three"));
+        assert_eq!(tree.len(), 3);
+        *tree.get_mut(&1).unwrap() = "ONE";
+        assert_eq!(tree.get(&1), Some(&"ONE"));
+        assert!(tree.contains_key(&2));
+        assert_eq!(SearchTree::<i32, i32>::new().to_string(), "Leaf");
+    }
+
+    // The three ways to walk a tree
+    #[test]
+    fn traversal_orders() {
+        let mut tree = small_tree();
+        assert_eq!(keys(tree.root().in_order()), [1, 2, 3]);
+        assert_eq!(keys(tree.root().pre_order()), [2, 1, 3]);
+        assert_eq!(keys(tree.root().post_order()), [1, 3, 2]);
+
+        assert_eq!(tree.remove(&2), Some("two"));
+        assert_eq!(tree.remove(&2), None);
+        assert_eq!(keys(tree.iter()), [1, 3]);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn sorted_keys() {
+        let mut plain = SearchTree::new();
+        let mut avl = SearchTree::avl();
+        for key in 0..1_000 {
+            plain.insert(key, key * 10);
+            avl.insert(key, key * 10);
+        }
+        // The plain tree is really just a linked list now
+        assert_eq!(plain.height(), 1_000);
+        assert!(!plain.is_balanced());
+        assert!(avl.height() <= avl_height_bound(1_000));
+        assert!(avl.is_balanced());
+        assert_valid(&plain);
+        assert_valid(&avl);
+
+        // Removing half the keys keeps it balanced too
+        for key in (0..1_000).step_by(2) {
+            assert_eq!(avl.remove(&key), Some(key * 10));
+        }
+        assert_valid(&avl);
+        assert_eq!(avl.len(), 500);
+        assert!(avl.iter().map(|(k, _)| *k).eq((1..1_000).step_by(2)));
+        assert_eq!(avl.get(&999), Some(&9_990));
+    }
+
+    // Random inserts and removes, checked against a BTreeMap after every step
+    #[test]
+    fn random_operations_keep_every_invariant() {
+        use crate::random::XorShift;
+        use std::collections::BTreeMap;
+
+        for balance in [Balance::Plain, Balance::Avl] {
+            let mut random = XorShift::new(0x5eed);
+            let mut tree = SearchTree::with_balance(balance);
+            let mut expected = BTreeMap::new();
+            for step in 0..4_000 {
+                let key = random.below(500) as i32;
+                // Inserts win slightly more often so the tree grows and shrinks
+                if random.below(5) < 3 {
+                    assert_eq!(tree.insert(key, step), expected.insert(key, step));
+                } else {
+                    assert_eq!(tree.remove(&key), expected.remove(&key));
+                }
+                assert_eq!(tree.len(), expected.len());
+                if balance == Balance::Avl {
+                    assert!(tree.height() <= avl_height_bound(tree.len()));
+                }
+                if step % 50 == 0 {
+                    assert_valid(&tree);
+                    assert!(tree.iter().eq(expected.iter()));
+                }
+            }
+            assert_valid(&tree);
+            assert!(tree.iter().eq(expected.iter()));
+
+            // Pre and post order visit the same nodes, each parent before or
+            // after its children
+            let mut pre: Vec<i32> = tree.root().pre_order().map(|(k, _)| *k).collect();
+            let mut post: Vec<i32> = tree.root().post_order().map(|(k, _)| *k).collect();
+            if let Tree::Node(root) = tree.root() {
+                assert_eq!(pre.first(), Some(&root.key));
+                assert_eq!(post.last(), Some(&root.key));
+            }
+            pre.sort();
+            post.sort();
+            assert!(pre.iter().eq(expected.keys()));
+            assert!(post.iter().eq(expected.keys()));
+
+            for key in 0..500 {
+                assert_eq!(tree.remove(&key), expected.remove(&key));
+            }
+            assert!(tree.is_empty());
+            assert_eq!(tree.height(), 0);
+        }
+    }
+
+    #[test]
+    fn removing_with_two_children_keeps_order_and_balance() {
+        for balance in [Balance::Plain, Balance::Avl] {
+            let mut tree = SearchTree::with_balance(balance);
+            for key in [50, 30, 70, 20, 40, 60, 80, 65] {
+                tree.insert(key, key);
+            }
+            assert_eq!(tree.remove(&50), Some(50));
+            assert_eq!(tree.remove(&70), Some(70));
+            assert!(tree.iter().map(|(k, _)| *k).eq([20, 30, 40, 60, 65, 80]));
+            if balance == Balance::Avl {
+                assert!(tree.is_balanced());
+            }
+        }
+    }
+}
@@ -1,3 +1,6 @@
+// Most of these bindings only exist to show off a type so they are never read
+#![allow(unused_variables, unused_assignments, unused_mut, dead_code)]
+
 use std::mem;
 
 fn analyze_slice(slice: &[i32]) {
@@ -14,7 +17,7 @@ fn reverse(pair: (i32, bool)) -> (bool, i32) {
     // Also apparently it doesn't need ;?
 }
 
-fn main() {
+pub fn run() {
     let logical: bool = false;
     
     let a_float: f64 = 1.0;
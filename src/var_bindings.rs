@@ -1,4 +1,7 @@
-fn main() {
+// Declaring first and initializing later is the whole point of one example
+#![allow(clippy::needless_late_init)]
+
+pub fn run() {
     
     // Variable bindings are immutable by default
     // but this can be overridden using the mut modifier